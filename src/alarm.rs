@@ -0,0 +1,339 @@
+//! Wake-up alarms: at a set time of day a preset starts from silence while the
+//! master volume rises over several minutes. Kept in `alarms.toml` and edited
+//! from the *Alarms* tab.
+//!
+//! ```toml
+//! [[alarm]]
+//! at = "07:00"
+//! preset = "Morning"
+//! fade_minutes = 10
+//! days = ["weekdays"]
+//! ```
+
+use crate::schedule::ScheduleEntry;
+use anyhow::Result;
+use chrono::{Local, NaiveDateTime};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+pub const DEFAULT_FADE_MINUTES: u64 = 10;
+/// Longest wake-up fade the Alarms view steps to.
+pub const MAX_FADE_MINUTES: u64 = 60;
+/// How often `alarms.toml` is checked for edits.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct AlarmsConfig {
+    #[serde(default, rename = "alarm")]
+    pub alarms: Vec<Alarm>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alarm {
+    /// Local time of day, `HH:MM`.
+    pub at: String,
+    pub preset: String,
+    /// Minutes the master volume takes to rise from silence.
+    #[serde(default = "default_fade_minutes")]
+    pub fade_minutes: u64,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Day names (`mon`, `tuesday`, ...) or `weekdays` / `weekends`; empty means every day.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<String>,
+}
+
+fn default_fade_minutes() -> u64 {
+    DEFAULT_FADE_MINUTES
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Alarm {
+    /// Parses `HH:MM Preset Name` as typed in the Alarms view.
+    pub fn parse_input(input: &str) -> Result<Self> {
+        let (at, preset) = input
+            .trim()
+            .split_once(' ')
+            .ok_or_else(|| anyhow::anyhow!("expected HH:MM followed by a preset name"))?;
+        let alarm = Self {
+            at: at.to_string(),
+            preset: preset.trim().to_string(),
+            fade_minutes: DEFAULT_FADE_MINUTES,
+            enabled: true,
+            days: Vec::new(),
+        };
+        alarm.entry()?;
+        Ok(alarm)
+    }
+
+    pub fn entry(&self) -> Result<ScheduleEntry> {
+        ScheduleEntry::new(&self.at, &self.days, &self.preset)
+    }
+
+    pub fn fade(&self) -> Duration {
+        Duration::from_secs(self.fade_minutes * 60)
+    }
+}
+
+/// The alarms and when they were last checked. Picks up changes other Tanin
+/// processes make to `alarms.toml`.
+pub struct Alarms {
+    pub config: AlarmsConfig,
+    last_check: NaiveDateTime,
+    modified: Option<SystemTime>,
+    reload_checked: Instant,
+}
+
+impl Default for Alarms {
+    fn default() -> Self {
+        Self {
+            config: AlarmsConfig::default(),
+            last_check: Local::now().naive_local(),
+            modified: None,
+            reload_checked: Instant::now(),
+        }
+    }
+}
+
+impl Alarms {
+    pub fn load() -> Result<Self> {
+        let mut alarms = Self::default();
+        alarms.reload()?;
+        Ok(alarms)
+    }
+
+    fn reload(&mut self) -> Result<()> {
+        let path = get_alarms_path()?;
+        self.modified = modified(&path);
+        self.config = if path.exists() {
+            toml::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            AlarmsConfig::default()
+        };
+        for alarm in &self.config.alarms {
+            if let Err(e) = alarm.entry() {
+                log::error!("Alarm for '{}' will never go off: {}", alarm.preset, e);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save(&mut self) -> Result<()> {
+        let path = get_alarms_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, toml::to_string_pretty(&self.config)?)?;
+        self.modified = modified(&path);
+        Ok(())
+    }
+
+    /// Returns the latest enabled alarm that went off since the previous call.
+    pub fn poll(&mut self) -> Option<Alarm> {
+        if self.reload_checked.elapsed() >= RELOAD_INTERVAL {
+            self.reload_checked = Instant::now();
+            if let Ok(path) = get_alarms_path() {
+                if modified(&path) != self.modified {
+                    if let Err(e) = self.reload() {
+                        log::error!("Failed to reload alarms: {}", e);
+                    }
+                }
+            }
+        }
+        self.due(Local::now().naive_local())
+    }
+
+    /// The part of `poll` that doesn't touch the file or the clock.
+    fn due(&mut self, now: NaiveDateTime) -> Option<Alarm> {
+        let since = self.last_check;
+        self.last_check = now;
+        // The clock went backwards (DST, manual change); just start over from now
+        if now <= since {
+            return None;
+        }
+
+        self.config
+            .alarms
+            .iter()
+            .filter(|alarm| alarm.enabled)
+            .filter_map(|alarm| {
+                let entry = alarm.entry().ok()?;
+                let at = std::iter::successors(entry.next_after(since), |at| entry.next_after(*at))
+                    .take_while(|at| *at <= now)
+                    .last()?;
+                Some((at, alarm))
+            })
+            .max_by_key(|(at, _)| *at)
+            .map(|(_, alarm)| alarm.clone())
+    }
+
+    /// When the alarm at `index` goes off next, if it is enabled and valid.
+    pub fn next(&self, index: usize) -> Option<NaiveDateTime> {
+        let alarm = self.config.alarms.get(index).filter(|a| a.enabled)?;
+        alarm.entry().ok()?.next_after(Local::now().naive_local())
+    }
+
+    /// The soonest enabled alarm.
+    pub fn upcoming(&self) -> Option<NaiveDateTime> {
+        (0..self.config.alarms.len())
+            .filter_map(|i| self.next(i))
+            .min()
+    }
+}
+
+/// The master volume rising after an alarm went off.
+pub struct WakeFade {
+    pub elapsed: Duration,
+    pub duration: Duration,
+}
+
+impl WakeFade {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            elapsed: Duration::ZERO,
+            duration,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    /// Multiplier applied to the master volume, from 0.0 up to 1.0. Squared so
+    /// the first minutes stay gentle, as loudness is heard logarithmically.
+    pub fn volume_factor(&self) -> f32 {
+        if self.is_done() {
+            return 1.0;
+        }
+        let t = self.elapsed.as_secs_f32() / self.duration.as_secs_f32();
+        t * t
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).ok()?.modified().ok()
+}
+
+fn get_alarms_path() -> Result<PathBuf> {
+    if let Some(proj_dirs) = ProjectDirs::from("com", "tanin", "tanin") {
+        Ok(proj_dirs.config_dir().join("alarms.toml"))
+    } else {
+        Ok(PathBuf::from("alarms.toml"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        // 2024-01-01 was a Monday
+        NaiveDate::from_ymd_opt(2024, 1, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn alarms(toml: &str, last_check: NaiveDateTime) -> Alarms {
+        Alarms {
+            config: toml::from_str(toml).unwrap(),
+            last_check,
+            modified: None,
+            reload_checked: Instant::now(),
+        }
+    }
+
+    fn fired(alarms: &mut Alarms, now: NaiveDateTime) -> Option<String> {
+        alarms.due(now).map(|alarm| alarm.preset)
+    }
+
+    #[test]
+    fn alarm_goes_off_once() {
+        let mut alarms = alarms(
+            r#"
+            [[alarm]]
+            at = "07:00"
+            preset = "Morning"
+            "#,
+            at(1, 6, 0),
+        );
+        assert_eq!(fired(&mut alarms, at(1, 6, 59)), None);
+        let alarm = alarms.due(at(1, 7, 0)).unwrap();
+        assert_eq!(alarm.preset, "Morning");
+        assert_eq!(alarm.fade(), Duration::from_secs(DEFAULT_FADE_MINUTES * 60));
+        assert_eq!(fired(&mut alarms, at(1, 7, 1)), None);
+    }
+
+    #[test]
+    fn alarm_goes_off_after_midnight() {
+        let mut alarms = alarms(
+            r#"
+            [[alarm]]
+            at = "00:30"
+            preset = "Night"
+            "#,
+            at(1, 23, 0),
+        );
+        assert_eq!(fired(&mut alarms, at(1, 23, 59)), None);
+        assert_eq!(fired(&mut alarms, at(2, 0, 31)).as_deref(), Some("Night"));
+        // And again the night after
+        assert_eq!(fired(&mut alarms, at(2, 12, 0)), None);
+        assert_eq!(fired(&mut alarms, at(3, 0, 30)).as_deref(), Some("Night"));
+    }
+
+    #[test]
+    fn alarm_already_past_at_startup_waits_a_day() {
+        let mut alarms = alarms(
+            r#"
+            [[alarm]]
+            at = "07:00"
+            preset = "Morning"
+            "#,
+            at(1, 8, 0),
+        );
+        assert_eq!(fired(&mut alarms, at(1, 8, 1)), None);
+        let entry = alarms.config.alarms[0].entry().unwrap();
+        assert_eq!(entry.next_after(at(1, 8, 1)), Some(at(2, 7, 0)));
+        assert_eq!(fired(&mut alarms, at(2, 7, 0)).as_deref(), Some("Morning"));
+    }
+
+    #[test]
+    fn disabled_and_off_day_alarms_stay_quiet() {
+        let mut alarms = alarms(
+            r#"
+            [[alarm]]
+            at = "07:00"
+            preset = "Disabled"
+            enabled = false
+
+            [[alarm]]
+            at = "07:00"
+            preset = "Workday"
+            days = ["weekdays"]
+            "#,
+            // Saturday morning
+            at(6, 6, 0),
+        );
+        assert_eq!(fired(&mut alarms, at(6, 8, 0)), None);
+        assert_eq!(fired(&mut alarms, at(7, 8, 0)), None);
+        assert_eq!(fired(&mut alarms, at(8, 8, 0)).as_deref(), Some("Workday"));
+    }
+
+    #[test]
+    fn wake_fade_rises_to_full_volume() {
+        let mut fade = WakeFade::new(Duration::from_secs(60));
+        assert_eq!(fade.volume_factor(), 0.0);
+        fade.elapsed = Duration::from_secs(30);
+        assert_eq!(fade.volume_factor(), 0.25);
+        fade.elapsed = Duration::from_secs(90);
+        assert!(fade.is_done());
+        assert_eq!(fade.volume_factor(), 1.0);
+    }
+}
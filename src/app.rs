@@ -1,17 +1,40 @@
+pub mod alarm;
 pub mod audio;
+pub mod devices;
 pub mod download;
+pub mod drift;
 pub mod input;
+pub mod loudness;
+#[cfg(feature = "mpris")]
+pub mod mpris;
 pub mod navigation;
+pub mod pomodoro;
 pub mod presets;
+pub mod schedule;
+pub mod seek;
+pub mod sleep;
+pub mod spatial;
+pub mod tone;
 
+use crate::alarm::{Alarms, WakeFade};
 use crate::audio::AudioEngine;
 use crate::config::Config;
-use crate::presets::PresetsConfig;
+use crate::daemon::DaemonClient;
+use crate::loudness::Analyzer;
+use crate::output::{OutputArgs, OutputDevice};
+use crate::player::Player;
+use crate::pomodoro::Pomodoro;
+use crate::presets::{PresetSort, PresetsConfig};
+use crate::schedule::Schedule;
 use crate::session::{Session, SoundState};
+use crate::share::SoundSource;
+use crate::sleep::SleepTimer;
 use crate::static_data::{check_assets, get_bundled_sounds, AssetStatus, Sound};
 use anyhow::Result;
 pub use download::{DownloadEvent, DownloadStatus, DownloadTask};
+pub use presets::PresetField;
 use std::sync::mpsc::Receiver;
+use std::time::Duration;
 
 pub enum AssetDownloadEvent {
     ConfigDownloaded(Vec<Sound>),
@@ -22,8 +45,12 @@ pub enum AssetDownloadEvent {
 pub enum CurrentView {
     Main,
     Presets,
+    Schedule,
+    Alarms,
     Help,
     Downloads,
+    Devices,
+    Details,
     AssetMissing,
     DownloadingAssets,
 }
@@ -32,7 +59,7 @@ pub struct App {
     pub sounds: Vec<Sound>,
     pub cursor_pos: usize,
     pub view: CurrentView,
-    pub audio_engine: Option<AudioEngine>,
+    pub audio_engine: Option<Player>,
     pub config: Config,
     pub session: Session,
     pub presets_config: PresetsConfig,
@@ -49,9 +76,49 @@ pub struct App {
     pub preset_input_mode: bool,
     pub preset_input_buffer: String,
     pub preset_rename_target: Option<usize>,
+    // The input box takes a preset file or code instead of a name
+    pub preset_import_input: bool,
+    // ... or one of the selected preset's details
+    pub preset_edit_field: Option<PresetField>,
+    pub preset_sort: PresetSort,
+    pub preset_tag_filter: Option<String>,
+    pub preset_status: Option<String>,
+    // Sounds of an imported preset waiting for the go-ahead to download
+    pub preset_downloads: Vec<SoundSource>,
     pub active_preset: Option<String>,
     pub animation_offset: f32,
 
+    // Sleep timer
+    pub sleep_timer: Option<SleepTimer>,
+
+    // Focus session switching mixes between work and breaks
+    pub pomodoro: Option<Pomodoro>,
+
+    // Time-of-day preset rules
+    pub schedule: Schedule,
+
+    // Wake-up alarms and the master volume rising after one went off
+    pub alarms: Alarms,
+    pub wake_fade: Option<WakeFade>,
+    pub alarm_cursor_pos: usize,
+    pub alarm_input_mode: bool,
+    pub alarm_input_buffer: String,
+    pub alarm_error: Option<String>,
+
+    // Output device picker
+    pub output: OutputArgs,
+    pub devices: Vec<OutputDevice>,
+    pub device_cursor_pos: usize,
+
+    // Measures sound files for loudness normalisation; the daemon runs its own
+    pub loudness_analyzer: Option<Analyzer>,
+
+    // Sounds restarted by "resume" after a stop all
+    pub resume_sounds: Vec<String>,
+
+    #[cfg(feature = "mpris")]
+    pub mpris: Option<crate::mpris::MprisServer>,
+
     // Add Sound view state
     pub add_sound_name: String,
     pub add_sound_category: String,
@@ -77,21 +144,31 @@ pub struct App {
 }
 
 impl App {
-    pub fn new() -> Result<Self> {
+    pub fn new(output: &OutputArgs) -> Result<Self> {
         let config = Config::load()?;
         let session = Session::load()?;
         let presets_config = PresetsConfig::load().unwrap_or_default();
 
-        let audio_engine = AudioEngine::new().ok();
+        // Prefer attaching to a running daemon over opening a second output stream
+        let audio_engine = match DaemonClient::connect() {
+            Ok(client) => {
+                log::info!("Attached to running Tanin daemon");
+                Some(Player::Remote(client))
+            }
+            Err(e) => {
+                log::debug!("Not attaching to daemon: {}", e);
+                match AudioEngine::new(&output.apply(&config.audio)) {
+                    Ok(engine) => Some(Player::Local(Box::new(engine))),
+                    Err(e) => {
+                        log::error!("Failed to open audio output: {:#}", e);
+                        None
+                    }
+                }
+            }
+        };
 
         // Check yt-dlp availability
-        let yt_dlp_available = std::process::Command::new("yt-dlp")
-            .arg("--version")
-            .stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null())
-            .status()
-            .map(|s| s.success())
-            .unwrap_or(false);
+        let yt_dlp_available = download::yt_dlp_available();
 
         let mut app = Self {
             sounds: Vec::new(),
@@ -112,8 +189,38 @@ impl App {
             preset_input_mode: false,
             preset_input_buffer: String::new(),
             preset_rename_target: None,
+            preset_import_input: false,
+            preset_edit_field: None,
+            preset_sort: PresetSort::default(),
+            preset_tag_filter: None,
+            preset_status: None,
+            preset_downloads: Vec::new(),
             active_preset: None,
             animation_offset: 0.0,
+            sleep_timer: None,
+            pomodoro: None,
+            schedule: Schedule::load().unwrap_or_else(|e| {
+                log::error!("Failed to load schedule: {}", e);
+                Schedule::default()
+            }),
+            alarms: Alarms::load().unwrap_or_else(|e| {
+                log::error!("Failed to load alarms: {}", e);
+                Alarms::default()
+            }),
+            wake_fade: None,
+            alarm_cursor_pos: 0,
+            alarm_input_mode: false,
+            alarm_input_buffer: String::new(),
+            alarm_error: None,
+            output: output.clone(),
+            devices: Vec::new(),
+            device_cursor_pos: 0,
+            loudness_analyzer: None,
+            resume_sounds: Vec::new(),
+            #[cfg(feature = "mpris")]
+            mpris: crate::mpris::MprisServer::start()
+                .map_err(|e| log::warn!("MPRIS unavailable: {}", e))
+                .ok(),
             add_sound_name: String::new(),
             add_sound_category: String::new(),
             add_sound_icon: "🎵".to_string(),
@@ -144,20 +251,52 @@ impl App {
 
         // Sort all sounds to ensure categories are grouped correctly (merging bundled + custom)
         app.sort_sounds();
+        app.start_loudness_analysis();
 
         app.check_and_download_missing_files();
 
         // Apply config
-        if let Some(engine) = &mut app.audio_engine {
+        if let Some(Player::Remote(client)) = &app.audio_engine {
+            // The daemon owns playback state, mirror it instead of restoring the session
+            let status = client.status().clone();
+            app.session.global_volume = status.master;
+            app.previous_volume = status.master;
+            app.active_preset = status.preset;
+            for sound in &mut app.sounds {
+                if let Some((_, vol)) = status.playing.iter().find(|(id, _)| *id == sound.id) {
+                    sound.volume_linear = *vol;
+                }
+                // Tone, pan and drift settings are not part of the status line, but the
+                // daemon keeps them in the shared session
+                if let Some(sc) = session.sounds.get(&sound.id) {
+                    sound.tone = sc.tone;
+                    sound.spatial = sc.spatial;
+                    sound.drift = sc.drift;
+                }
+            }
+        } else if let Some(engine) = &mut app.audio_engine {
             engine.set_master_volume(session.global_volume);
 
             for sound in &mut app.sounds {
                 if let Some(sc) = session.sounds.get(&sound.id) {
                     sound.volume_linear = sc.volume;
+                    sound.tone = sc.tone;
+                    sound.spatial = sc.spatial;
+                    sound.drift = sc.drift;
+                    engine.set_tone(&sound.id, sc.tone);
+                    engine.set_spatial(&sound.id, sc.spatial);
+                    engine.set_drift(&sound.id, sc.drift);
                     if sc.enabled {
-                        if let Err(e) =
-                            engine.play(&sound.id, &sound.file_path, sound.volume_linear)
-                        {
+                        // Long tracks carry on where they were left
+                        if let Some(secs) = sc.position {
+                            match Duration::try_from_secs_f64(secs) {
+                                Ok(position) => engine.seek(&sound.id, position),
+                                Err(_) => {
+                                    log::warn!("Ignoring saved position {} of '{}'", secs, sound.id)
+                                }
+                            }
+                        }
+                        if let Err(e) = engine.play(sound, sound.volume_linear) {
                             log::error!("Failed to auto-play sound '{}': {}", sound.id, e);
                             sound.error_state = true;
                         }
@@ -166,6 +305,8 @@ impl App {
             }
         }
 
+        app.restore_sleep_timer();
+
         Ok(app)
     }
 
@@ -216,6 +357,7 @@ impl App {
                         }
                         self.sounds.extend(crate::static_data::load_custom_sounds());
                         self.sort_sounds();
+                        self.measure_pending_loudness();
 
                         // Switch to Downloads view
                         self.view = CurrentView::Downloads;
@@ -243,6 +385,13 @@ impl App {
         if let Some(engine) = &mut self.audio_engine {
             engine.update(dt);
         }
+        self.update_sleep_timer(dt);
+        self.update_pomodoro(dt);
+        self.update_schedule();
+        self.update_alarms(dt);
+        self.update_loudness();
+        #[cfg(feature = "mpris")]
+        self.update_mpris();
         self.animation_offset += dt.as_secs_f32() * 3.0;
 
         // Queue Management
@@ -270,14 +419,6 @@ impl App {
                             }
                         }
                         DownloadEvent::Success(name, cat, path, icon, url) => {
-                            if let Some(idx) = self.active_download_index {
-                                if let Some(task) = self.download_queue.get_mut(idx) {
-                                    task.status = DownloadStatus::Done;
-                                }
-                            }
-                            self.active_download_index = None;
-                            self.download_rx = None;
-
                             // Keep URL in config
                             if let Err(e) = crate::static_data::add_custom_sound(
                                 &name,
@@ -285,8 +426,10 @@ impl App {
                                 &path,
                                 &icon,
                                 Some(&url),
+                                None,
                             ) {
                                 log::error!("Failed to save config after download: {}", e);
+                                self.finish_download(DownloadStatus::Done);
                             } else {
                                 log::info!("Successfully added sound '{}' with URL", name);
                                 let id = name.to_lowercase().replace(" ", "_");
@@ -294,10 +437,19 @@ impl App {
                                     id: id.clone(),
                                     name,
                                     category: cat,
-                                    file_path: path,
+                                    file_path: path.clone(),
                                     volume_linear: 0.5,
                                     icon,
                                     url: Some(url.clone()),
+                                    generator: None,
+                                    events: None,
+                                    tone: Default::default(),
+                                    spatial: Default::default(),
+                                    drift: None,
+                                    variants: None,
+                                    loop_region: None,
+                                    loudness: None,
+                                    normalize: true,
                                     error_state: false,
                                 };
                                 // Check if sound already exists (update case)
@@ -313,31 +465,35 @@ impl App {
                                 }
 
                                 self.sort_sounds();
+                                self.measure_pending_loudness();
+                                self.spawn_analysis_task(path);
                             }
                             break;
                         }
-                        DownloadEvent::Error(e) => {
-                            if let Some(idx) = self.active_download_index {
-                                if let Some(task) = self.download_queue.get_mut(idx) {
-                                    task.status = DownloadStatus::Error(e.clone());
-                                }
+                        DownloadEvent::Analyzed(points) => {
+                            // Re-downloads of missing files leave their sounds.toml entry alone
+                            let id = self
+                                .active_download_index
+                                .and_then(|idx| self.download_queue.get(idx))
+                                .filter(|task| task.target_filename.is_none())
+                                .map(|task| task.name.to_lowercase().replace(" ", "_"));
+                            self.finish_download(DownloadStatus::Done);
+                            if let (Some(points), Some(id)) = (points, id) {
+                                self.save_loop_points(&id, points);
                             }
-                            self.active_download_index = None;
-                            self.download_rx = None;
+                            break;
+                        }
+                        DownloadEvent::Error(e) => {
                             log::error!("Download error: {}", e);
+                            self.finish_download(DownloadStatus::Error(e));
                             break;
                         }
                     },
                     Err(std::sync::mpsc::TryRecvError::Empty) => break,
                     Err(std::sync::mpsc::TryRecvError::Disconnected) => {
-                        if let Some(idx) = self.active_download_index {
-                            if let Some(task) = self.download_queue.get_mut(idx) {
-                                task.status =
-                                    DownloadStatus::Error("Thread disconnected".to_string());
-                            }
-                        }
-                        self.active_download_index = None;
-                        self.download_rx = None;
+                        self.finish_download(DownloadStatus::Error(
+                            "Thread disconnected".to_string(),
+                        ));
                         break;
                     }
                 }
@@ -419,6 +575,12 @@ impl App {
     }
 
     pub fn save_session(&mut self) {
+        if self.audio_engine.as_ref().is_some_and(Player::is_remote) {
+            // The daemon persists its own session
+            let _ = self.presets_config.save();
+            return;
+        }
+
         for sound in &self.sounds {
             let enabled = if let Some(engine) = &self.audio_engine {
                 engine.is_playing(&sound.id)
@@ -431,9 +593,18 @@ impl App {
                 SoundState {
                     enabled,
                     volume: sound.volume_linear,
+                    tone: sound.tone,
+                    spatial: sound.spatial,
+                    drift: sound.drift,
+                    position: self
+                        .audio_engine
+                        .as_ref()
+                        .and_then(|engine| engine.position(&sound.id))
+                        .map(|(elapsed, _)| elapsed.as_secs_f64()),
                 },
             );
         }
+        self.session.sleep_until = self.sleep_deadline();
         let _ = self.session.save();
         let _ = self.presets_config.save();
     }
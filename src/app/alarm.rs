@@ -0,0 +1,91 @@
+use super::App;
+use crate::alarm::{Alarm, WakeFade, MAX_FADE_MINUTES};
+use crate::player::Player;
+use std::time::Duration;
+
+impl App {
+    pub fn update_alarms(&mut self, dt: Duration) {
+        if let Some(fade) = &mut self.wake_fade {
+            fade.elapsed += dt;
+            let done = fade.is_done();
+            self.apply_master_volume();
+            if done {
+                self.wake_fade = None;
+            }
+        }
+
+        // An attached daemon rings the alarms itself
+        if self.audio_engine.as_ref().is_some_and(Player::is_remote) {
+            return;
+        }
+        let Some(alarm) = self.alarms.poll() else {
+            return;
+        };
+        let Some(index) = self
+            .presets_config
+            .presets
+            .iter()
+            .position(|p| p.name.eq_ignore_ascii_case(&alarm.preset))
+        else {
+            log::error!("Alarm preset '{}' does not exist", alarm.preset);
+            return;
+        };
+
+        log::info!("Alarm at {} starting preset '{}'", alarm.at, alarm.preset);
+        self.wake_fade = Some(WakeFade::new(alarm.fade()));
+        self.apply_master_volume();
+        self.switch_to_preset(index);
+        // Nothing may keep the alarm quiet, not even a preset saved muted
+        self.set_sleep_timer(None);
+        if self.muted {
+            self.toggle_mute();
+        }
+    }
+
+    /// Adds the alarm typed in the Alarms view as `HH:MM Preset`.
+    pub fn confirm_alarm_input(&mut self) {
+        match Alarm::parse_input(&self.alarm_input_buffer) {
+            Ok(alarm) => {
+                self.alarms.config.alarms.push(alarm);
+                self.alarm_cursor_pos = self.alarms.config.alarms.len() - 1;
+                self.alarm_error = None;
+                self.save_alarms();
+            }
+            Err(e) => self.alarm_error = Some(e.to_string()),
+        }
+    }
+
+    pub fn toggle_alarm(&mut self, index: usize) {
+        if let Some(alarm) = self.alarms.config.alarms.get_mut(index) {
+            alarm.enabled = !alarm.enabled;
+            self.save_alarms();
+        }
+    }
+
+    pub fn delete_alarm(&mut self, index: usize) {
+        if index < self.alarms.config.alarms.len() {
+            self.alarms.config.alarms.remove(index);
+            self.alarm_cursor_pos = self
+                .alarm_cursor_pos
+                .min(self.alarms.config.alarms.len().saturating_sub(1));
+            self.save_alarms();
+        }
+    }
+
+    /// Lengthens (positive `delta`) or shortens the wake-up fade by a minute.
+    pub fn step_alarm_fade(&mut self, index: usize, delta: i64) {
+        if let Some(alarm) = self.alarms.config.alarms.get_mut(index) {
+            alarm.fade_minutes = alarm
+                .fade_minutes
+                .saturating_add_signed(delta)
+                .min(MAX_FADE_MINUTES);
+            self.save_alarms();
+        }
+    }
+
+    fn save_alarms(&mut self) {
+        if let Err(e) = self.alarms.save() {
+            log::error!("Failed to save alarms: {}", e);
+        }
+    }
+}
@@ -1,4 +1,6 @@
 use super::App;
+use crate::alarm::WakeFade;
+use crate::player::Player;
 
 impl App {
     pub fn toggle_current_sound(&mut self) {
@@ -10,7 +12,7 @@ impl App {
                 } else {
                     log::info!("Starting sound '{}'", sound.id);
                     sound.error_state = false;
-                    if let Err(e) = engine.play(&sound.id, &sound.file_path, sound.volume_linear) {
+                    if let Err(e) = engine.play(sound, sound.volume_linear) {
                         log::error!("Failed to play sound '{}': {}", sound.id, e);
                         sound.error_state = true;
                     }
@@ -30,8 +32,23 @@ impl App {
 
     pub fn set_master_volume(&mut self, vol: f32) {
         self.session.global_volume = vol.clamp(0.0, 1.0);
+        self.apply_master_volume();
+    }
+
+    /// Pushes the master volume to the engine, scaled by any running sleep or
+    /// wake-up fade.
+    pub fn apply_master_volume(&mut self) {
+        // An attached daemon fades its own master volume for the sleep timer
+        let sleep = match &self.sleep_timer {
+            Some(timer) if !self.audio_engine.as_ref().is_some_and(Player::is_remote) => {
+                timer.volume_factor()
+            }
+            _ => 1.0,
+        };
+        let wake = self.wake_fade.as_ref().map_or(1.0, WakeFade::volume_factor);
+        let factor = sleep * wake;
         if let Some(engine) = &mut self.audio_engine {
-            engine.set_master_volume(self.session.global_volume);
+            engine.set_master_volume(self.session.global_volume * factor);
         }
     }
 
@@ -47,8 +64,39 @@ impl App {
     }
 
     pub fn stop_all(&mut self) {
+        let playing = self.playing_sound_ids();
+        if !playing.is_empty() {
+            self.resume_sounds = playing;
+        }
         if let Some(engine) = &mut self.audio_engine {
             engine.stop_all();
         }
     }
+
+    /// Restarts the sounds that were playing before the last `stop_all`.
+    pub fn resume_all(&mut self) {
+        if let Some(engine) = &mut self.audio_engine {
+            for sound in &mut self.sounds {
+                if !self.resume_sounds.contains(&sound.id) {
+                    continue;
+                }
+                sound.error_state = false;
+                if let Err(e) = engine.play(sound, sound.volume_linear) {
+                    log::error!("Failed to resume sound '{}': {}", sound.id, e);
+                    sound.error_state = true;
+                }
+            }
+        }
+    }
+
+    pub fn playing_sound_ids(&self) -> Vec<String> {
+        let Some(engine) = &self.audio_engine else {
+            return Vec::new();
+        };
+        self.sounds
+            .iter()
+            .filter(|s| engine.is_playing(&s.id))
+            .map(|s| s.id.clone())
+            .collect()
+    }
 }
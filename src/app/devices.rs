@@ -0,0 +1,62 @@
+use super::{App, CurrentView};
+use crate::audio::AudioEngine;
+use crate::output::{self, BackendKind};
+use crate::player::Player;
+
+impl App {
+    pub fn open_devices(&mut self) {
+        self.devices = output::list_devices();
+        // Row 0 is "Automatic", devices follow
+        self.device_cursor_pos = self
+            .devices
+            .iter()
+            .position(|d| d.is_chosen(&self.config.audio))
+            .map_or(0, |i| i + 1);
+        self.view = CurrentView::Devices;
+    }
+
+    pub fn device_up(&mut self) {
+        self.device_cursor_pos = self.device_cursor_pos.saturating_sub(1);
+    }
+
+    pub fn device_down(&mut self) {
+        if self.device_cursor_pos < self.devices.len() {
+            self.device_cursor_pos += 1;
+        }
+    }
+
+    /// Saves the device under the cursor to config.toml and moves playback to it.
+    pub fn select_device(&mut self) {
+        let device = self
+            .device_cursor_pos
+            .checked_sub(1)
+            .and_then(|i| self.devices.get(i))
+            .cloned();
+        self.config.audio.host = device.as_ref().map(|d| d.host.clone());
+        self.config.audio.device = device.map(|d| d.name);
+        if let Err(e) = self.config.save() {
+            log::error!("Failed to save config: {}", e);
+        }
+
+        let audio = self.output.apply(&self.config.audio);
+        if audio.backend != BackendKind::Cpal {
+            log::info!("Not using the sound card, the device applies on the next cpal run");
+            return;
+        }
+        match &mut self.audio_engine {
+            Some(engine) => {
+                if let Err(e) = engine.switch_output(&audio) {
+                    log::error!("Failed to switch audio output: {:#}", e);
+                }
+            }
+            // Opening the output failed at startup; the new device may work
+            None => match AudioEngine::new(&audio) {
+                Ok(mut engine) => {
+                    engine.set_master_volume(self.session.global_volume);
+                    self.audio_engine = Some(Player::Local(Box::new(engine)));
+                }
+                Err(e) => log::error!("Failed to open audio output: {:#}", e),
+            },
+        }
+    }
+}
@@ -1,4 +1,6 @@
 use super::App;
+use crate::audio::create_decoder_from_path;
+use crate::dsp::loop_points::{find_loop_points, LoopPoints};
 use std::io::{BufRead, BufReader, Read};
 use std::sync::mpsc;
 use std::thread;
@@ -6,6 +8,8 @@ use std::thread;
 pub enum DownloadStatus {
     Pending,
     Downloading(f32),
+    /// Downloaded, looking for silence and loop points.
+    Analyzing,
     Done,
     Error(String),
 }
@@ -22,9 +26,21 @@ pub struct DownloadTask {
 pub enum DownloadEvent {
     Progress(f32),
     Success(String, String, String, String, String), // name, category, file_path, icon, url
+    /// Loop points found for the downloaded file, if it has any sound at all.
+    Analyzed(Option<LoopPoints>),
     Error(String),
 }
 
+pub fn yt_dlp_available() -> bool {
+    std::process::Command::new("yt-dlp")
+        .arg("--version")
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
 impl App {
     pub fn check_and_download_missing_files(&mut self) {
         if !self.yt_dlp_available {
@@ -97,228 +113,332 @@ impl App {
         let yt_dlp_available = self.yt_dlp_available;
 
         thread::spawn(move || {
-            let proj_dirs = match directories::ProjectDirs::from("com", "tanin", "tanin") {
-                Some(dirs) => dirs,
-                None => {
-                    let _ = tx.send(DownloadEvent::Error(
-                        "Could not determine data directory.".to_string(),
-                    ));
-                    return;
-                }
-            };
+            download_sound(
+                name,
+                category,
+                icon,
+                url,
+                target_filename,
+                yt_dlp_available,
+                tx,
+            )
+        });
+    }
 
-            let sounds_dir = proj_dirs.data_dir().join("sounds");
-            if !sounds_dir.exists() {
-                if let Err(e) = std::fs::create_dir_all(&sounds_dir) {
-                    let _ = tx.send(DownloadEvent::Error(format!(
-                        "Error creating directory: {}",
-                        e
-                    )));
-                    return;
-                }
-            }
+    /// Moves the active download into its analysis phase, reporting on the same
+    /// channel as the download did.
+    pub fn spawn_analysis_task(&mut self, path: String) {
+        if let Some(task) = self
+            .active_download_index
+            .and_then(|idx| self.download_queue.get_mut(idx))
+        {
+            task.status = DownloadStatus::Analyzing;
+        }
+
+        let (tx, rx) = mpsc::channel();
+        self.download_rx = Some(rx);
+        thread::spawn(move || {
+            let _ = tx.send(DownloadEvent::Analyzed(analyze_download(&path)));
+        });
+    }
+
+    /// Ends the active download with its final `status`, letting the next
+    /// queued one start.
+    pub fn finish_download(&mut self, status: DownloadStatus) {
+        if let Some(task) = self
+            .active_download_index
+            .and_then(|idx| self.download_queue.get_mut(idx))
+        {
+            task.status = status;
+        }
+        self.active_download_index = None;
+        self.download_rx = None;
+    }
+
+    /// Writes detected loop points into the sound's sounds.toml entry. They
+    /// take effect the next time the sound starts. A loop region the user
+    /// already set is kept.
+    pub fn save_loop_points(&mut self, id: &str, points: LoopPoints) {
+        let Some(sound) = self.sounds.iter_mut().find(|s| s.id == id) else {
+            return;
+        };
+        if sound.loop_region.is_some() {
+            log::info!("'{}' already has a loop region, keeping it", id);
+            return;
+        }
+        match crate::static_data::add_custom_sound(
+            &sound.name,
+            &sound.category,
+            &sound.file_path,
+            &sound.icon,
+            sound.url.as_deref(),
+            Some(points),
+        ) {
+            Ok(()) => sound.loop_region = Some(points.into()),
+            Err(e) => log::error!("Failed to save loop points of '{}': {}", id, e),
+        }
+    }
+}
+
+/// Finds where a downloaded file should loop. Failures are logged rather than
+/// reported, as the sound plays fine without loop points.
+pub fn analyze_download(path: &str) -> Option<LoopPoints> {
+    log::info!("Looking for loop points in {}", path);
+    let result = std::panic::catch_unwind(|| find_loop_points(|| create_decoder_from_path(path)))
+        .unwrap_or_else(|_| Err(anyhow::anyhow!("Decoder panicked")));
+    match result {
+        Ok(Some(points)) => {
+            log::info!(
+                "{}: loop from {:.3}s to {:.3}s",
+                path,
+                points.start,
+                points.end
+            );
+            Some(points)
+        }
+        Ok(None) => {
+            log::warn!("{} is silent, leaving it unlooped", path);
+            None
+        }
+        Err(e) => {
+            log::error!("Cannot analyse {}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// Downloads a sound into the data directory, reporting progress and the final
+/// file path through `tx`. Blocks until the download finishes.
+pub fn download_sound(
+    name: String,
+    category: String,
+    icon: String,
+    url: String,
+    target_filename: Option<String>,
+    yt_dlp_available: bool,
+    tx: mpsc::Sender<DownloadEvent>,
+) {
+    let proj_dirs = match directories::ProjectDirs::from("com", "tanin", "tanin") {
+        Some(dirs) => dirs,
+        None => {
+            let _ = tx.send(DownloadEvent::Error(
+                "Could not determine data directory.".to_string(),
+            ));
+            return;
+        }
+    };
 
-            let output_template = if let Some(tf) = &target_filename {
-                let stem = std::path::Path::new(tf)
-                    .file_stem()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("unknown");
-                sounds_dir.join(format!("{}.%(ext)s", stem))
-            } else {
-                let safe_name: String = name
-                    .trim()
-                    .chars()
-                    .map(|c| if c.is_alphanumeric() { c } else { '_' })
-                    .collect();
-                sounds_dir.join(format!("{}.%(ext)s", safe_name))
+    let sounds_dir = proj_dirs.data_dir().join("sounds");
+    if !sounds_dir.exists() {
+        if let Err(e) = std::fs::create_dir_all(&sounds_dir) {
+            let _ = tx.send(DownloadEvent::Error(format!(
+                "Error creating directory: {}",
+                e
+            )));
+            return;
+        }
+    }
+
+    let output_template = if let Some(tf) = &target_filename {
+        let stem = std::path::Path::new(tf)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("unknown");
+        sounds_dir.join(format!("{}.%(ext)s", stem))
+    } else {
+        let safe_name: String = name
+            .trim()
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+        sounds_dir.join(format!("{}.%(ext)s", safe_name))
+    };
+
+    let output_template_str = output_template.to_string_lossy();
+    let safe_name = output_template
+        .file_stem()
+        .unwrap()
+        .to_string_lossy()
+        .to_string(); // For checking later
+
+    log::debug!("Download target: {}", output_template_str);
+
+    if !yt_dlp_available {
+        // Fallback to minreq
+        if let Some(target_file) = &target_filename {
+            // Shared presets name the file too, so only its last component is used
+            let Some(file_name) = std::path::Path::new(target_file).file_name() else {
+                let _ = tx.send(DownloadEvent::Error(format!(
+                    "Invalid file name '{}'",
+                    target_file
+                )));
+                return;
             };
+            let final_path = sounds_dir.join(file_name);
 
-            let output_template_str = output_template.to_string_lossy();
-            let safe_name = output_template
-                .file_stem()
-                .unwrap()
-                .to_string_lossy()
-                .to_string(); // For checking later
-
-            log::debug!("Download target: {}", output_template_str);
-
-            if !yt_dlp_available {
-                // Fallback to minreq
-                if let Some(target_file) = &target_filename {
-                    // We trust the target filename provided (from sounds.toml)
-                    let final_path = sounds_dir.join(target_file);
-
-                    match minreq::get(&url).send_lazy() {
-                        Ok(resp) => {
-                            let total_size = resp
-                                .headers
-                                .iter()
-                                .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
-                                .and_then(|(_, v)| v.parse::<usize>().ok())
-                                .unwrap_or(0);
-
-                            let mut reader = resp;
-
-                            let mut file = match std::fs::File::create(&final_path) {
-                                Ok(f) => f,
-                                Err(e) => {
+            match minreq::get(&url).send_lazy() {
+                Ok(resp) => {
+                    let total_size = resp
+                        .headers
+                        .iter()
+                        .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+                        .and_then(|(_, v)| v.parse::<usize>().ok())
+                        .unwrap_or(0);
+
+                    let mut reader = resp;
+
+                    let mut file = match std::fs::File::create(&final_path) {
+                        Ok(f) => f,
+                        Err(e) => {
+                            let _ = tx.send(DownloadEvent::Error(format!(
+                                "Failed to create file: {}",
+                                e
+                            )));
+                            return;
+                        }
+                    };
+
+                    let mut buffer = [0; 8192];
+                    let mut downloaded = 0;
+                    loop {
+                        match reader.read(&mut buffer) {
+                            Ok(0) => break,
+                            Ok(n) => {
+                                if let Err(e) = std::io::Write::write_all(&mut file, &buffer[..n]) {
                                     let _ = tx.send(DownloadEvent::Error(format!(
-                                        "Failed to create file: {}",
+                                        "Failed to write to file: {}",
                                         e
                                     )));
                                     return;
                                 }
-                            };
-
-                            let mut buffer = [0; 8192];
-                            let mut downloaded = 0;
-                            loop {
-                                match reader.read(&mut buffer) {
-                                    Ok(0) => break,
-                                    Ok(n) => {
-                                        if let Err(e) =
-                                            std::io::Write::write_all(&mut file, &buffer[..n])
-                                        {
-                                            let _ = tx.send(DownloadEvent::Error(format!(
-                                                "Failed to write to file: {}",
-                                                e
-                                            )));
-                                            return;
-                                        }
-                                        downloaded += n;
-                                        if total_size > 0 {
-                                            let pct =
-                                                (downloaded as f32 / total_size as f32) * 100.0;
-                                            let _ = tx.send(DownloadEvent::Progress(pct));
-                                        }
-                                    }
-                                    Err(e) => {
-                                        let _ = tx.send(DownloadEvent::Error(format!(
-                                            "Download failed: {}",
-                                            e
-                                        )));
-                                        return;
-                                    }
+                                downloaded += n;
+                                if total_size > 0 {
+                                    let pct = (downloaded as f32 / total_size as f32) * 100.0;
+                                    let _ = tx.send(DownloadEvent::Progress(pct));
                                 }
                             }
-
-                            let _ = tx.send(DownloadEvent::Success(
-                                name,
-                                category,
-                                final_path.to_string_lossy().into_owned(),
-                                icon,
-                                url,
-                            ));
-                            return;
-                        }
-                        Err(e) => {
-                            let _ = tx.send(DownloadEvent::Error(format!(
-                                "Direct download failed: {}",
-                                e
-                            )));
-                            return;
+                            Err(e) => {
+                                let _ = tx
+                                    .send(DownloadEvent::Error(format!("Download failed: {}", e)));
+                                return;
+                            }
                         }
                     }
-                } else {
-                    let _ = tx.send(DownloadEvent::Error(
-                        "yt-dlp is missing and no filename provided for direct download."
-                            .to_string(),
+
+                    let _ = tx.send(DownloadEvent::Success(
+                        name,
+                        category,
+                        final_path.to_string_lossy().into_owned(),
+                        icon,
+                        url,
                     ));
                     return;
                 }
+                Err(e) => {
+                    let _ = tx.send(DownloadEvent::Error(format!(
+                        "Direct download failed: {}",
+                        e
+                    )));
+                    return;
+                }
             }
+        } else {
+            let _ = tx.send(DownloadEvent::Error(
+                "yt-dlp is missing and no filename provided for direct download.".to_string(),
+            ));
+            return;
+        }
+    }
 
-            let child = std::process::Command::new("yt-dlp")
-                .arg("--ignore-config")
-                .arg("--no-playlist")
-                .arg("--force-overwrites")
-                .arg("-x")
-                .arg("--audio-format")
-                .arg("opus")
-                .arg("-f")
-                .arg("ba[ext=webm]/ba")
-                .arg("-o")
-                .arg(&*output_template_str)
-                .arg("--newline")
-                .arg("--progress")
-                .arg(&url)
-                .stdout(std::process::Stdio::piped())
-                .stderr(std::process::Stdio::piped())
-                .spawn();
-
-            match child {
-                Ok(mut child) => {
-                    let stdout = child.stdout.take().unwrap();
-                    let stderr = child.stderr.take().unwrap();
-
-                    let (err_tx, ___) = std::sync::mpsc::channel();
-                    std::thread::spawn(move || {
-                        let reader = BufReader::new(stderr);
-                        for line in reader.lines().map_while(Result::ok) {
-                            let _ = err_tx.send(line);
-                        }
-                    });
+    let child = std::process::Command::new("yt-dlp")
+        .arg("--ignore-config")
+        .arg("--no-playlist")
+        .arg("--force-overwrites")
+        .arg("-x")
+        .arg("--audio-format")
+        .arg("opus")
+        .arg("-f")
+        .arg("ba[ext=webm]/ba")
+        .arg("-o")
+        .arg(&*output_template_str)
+        .arg("--newline")
+        .arg("--progress")
+        .arg(&url)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn();
 
-                    // Process stdout
-                    let reader = BufReader::new(stdout);
-                    for line in reader.lines().map_while(Result::ok) {
-                        // Parse percentage: [download]  23.5%
-                        if line.contains("[download]") && line.contains("%") {
-                            if let Some(pct_idx) = line.find('%') {
-                                let slice = &line[..pct_idx];
-                                if let Some(last_space) = slice.rfind(' ') {
-                                    if let Ok(pct) = slice[last_space + 1..].parse::<f32>() {
-                                        let _ = tx.send(DownloadEvent::Progress(pct));
-                                    }
-                                }
+    match child {
+        Ok(mut child) => {
+            let stdout = child.stdout.take().unwrap();
+            let stderr = child.stderr.take().unwrap();
+
+            let (err_tx, ___) = std::sync::mpsc::channel();
+            std::thread::spawn(move || {
+                let reader = BufReader::new(stderr);
+                for line in reader.lines().map_while(Result::ok) {
+                    let _ = err_tx.send(line);
+                }
+            });
+
+            // Process stdout
+            let reader = BufReader::new(stdout);
+            for line in reader.lines().map_while(Result::ok) {
+                // Parse percentage: [download]  23.5%
+                if line.contains("[download]") && line.contains("%") {
+                    if let Some(pct_idx) = line.find('%') {
+                        let slice = &line[..pct_idx];
+                        if let Some(last_space) = slice.rfind(' ') {
+                            if let Ok(pct) = slice[last_space + 1..].parse::<f32>() {
+                                let _ = tx.send(DownloadEvent::Progress(pct));
                             }
                         }
                     }
+                }
+            }
 
-                    match child.wait() {
-                        Ok(status) => {
-                            if status.success() {
-                                // Identify the downloaded file
-                                let fallbacks = ["opus", "m4a", "mp3", "wav", "ogg"];
-                                let mut downloaded_path = None;
-                                for ext in fallbacks {
-                                    let p = sounds_dir.join(format!("{}.{}", safe_name, ext));
-                                    if p.exists() {
-                                        downloaded_path = Some(p);
-                                        break;
-                                    }
-                                }
-
-                                if let Some(final_path) = downloaded_path {
-                                    let _ = tx.send(DownloadEvent::Success(
-                                        name,
-                                        category,
-                                        final_path.to_string_lossy().into_owned(),
-                                        icon,
-                                        url,
-                                    ));
-                                } else {
-                                    let _ = tx.send(DownloadEvent::Error(
-                                        "Download success but file not found.".to_string(),
-                                    ));
-                                }
+            match child.wait() {
+                Ok(status) => {
+                    if status.success() {
+                        // Identify the downloaded file
+                        let fallbacks = ["opus", "m4a", "mp3", "wav", "ogg"];
+                        let mut downloaded_path = None;
+                        for ext in fallbacks {
+                            let p = sounds_dir.join(format!("{}.{}", safe_name, ext));
+                            if p.exists() {
+                                downloaded_path = Some(p);
+                                break;
                             }
                         }
-                        Err(e) => {
-                            let _ = tx.send(DownloadEvent::Error(format!(
-                                "Failed to wait on child: {}",
-                                e
-                            )));
+
+                        if let Some(final_path) = downloaded_path {
+                            let _ = tx.send(DownloadEvent::Success(
+                                name,
+                                category,
+                                final_path.to_string_lossy().into_owned(),
+                                icon,
+                                url,
+                            ));
+                        } else {
+                            let _ = tx.send(DownloadEvent::Error(
+                                "Download success but file not found.".to_string(),
+                            ));
                         }
                     }
                 }
                 Err(e) => {
                     let _ = tx.send(DownloadEvent::Error(format!(
-                        "Failed to start yt-dlp: {}",
+                        "Failed to wait on child: {}",
                         e
                     )));
                 }
             }
-        });
+        }
+        Err(e) => {
+            let _ = tx.send(DownloadEvent::Error(format!(
+                "Failed to start yt-dlp: {}",
+                e
+            )));
+        }
     }
 }
@@ -0,0 +1,28 @@
+use super::App;
+use crate::dsp::{Drift, DriftMode};
+
+impl App {
+    /// Cycles the current sound through off, wander and LFO drift.
+    pub fn cycle_current_drift(&mut self) {
+        let defaults = Drift {
+            mode: DriftMode::Wander,
+            min: self.config.drift.min,
+            max: self.config.drift.max,
+            period_secs: self.config.drift.period_secs,
+        };
+
+        if let Some(sound) = self.sounds.get_mut(self.cursor_pos) {
+            sound.drift = match sound.drift {
+                None => Some(defaults.clamped()),
+                Some(drift) if drift.mode == DriftMode::Wander => Some(Drift {
+                    mode: DriftMode::Lfo,
+                    ..drift
+                }),
+                Some(_) => None,
+            };
+            if let Some(engine) = &mut self.audio_engine {
+                engine.set_drift(&sound.id, sound.drift);
+            }
+        }
+    }
+}
@@ -89,7 +89,7 @@ impl App {
 
         // View Specifics
         match self.view {
-            CurrentView::Presets => self.handle_preset_interaction(y, kind),
+            CurrentView::Presets => self.handle_preset_interaction(x, y, kind),
             CurrentView::Main => self.handle_grid_interaction(x, y, kind),
             _ => {}
         }
@@ -115,8 +115,16 @@ impl App {
                 else if (9..18).contains(&rel_x) {
                     self.view = CurrentView::Presets;
                 }
-                // " Downloads " is 11 chars. Starts at 18+1=19. Ends at 19+11=30.
-                else if self.yt_dlp_available && (19..30).contains(&rel_x) {
+                // " Schedule " is 10 chars. Starts at 18+1=19. Ends at 19+10=29.
+                else if (19..29).contains(&rel_x) {
+                    self.view = CurrentView::Schedule;
+                }
+                // " Alarms " is 8 chars. Starts at 29+1=30. Ends at 30+8=38.
+                else if (30..38).contains(&rel_x) {
+                    self.view = CurrentView::Alarms;
+                }
+                // " Downloads " is 11 chars. Starts at 38+1=39. Ends at 39+11=50.
+                else if self.yt_dlp_available && (39..50).contains(&rel_x) {
                     self.view = CurrentView::Downloads;
                 }
             }
@@ -158,7 +166,7 @@ impl App {
         }
     }
 
-    pub fn handle_preset_interaction(&mut self, y: u16, kind: MouseEventKind) {
+    pub fn handle_preset_interaction(&mut self, x: u16, y: u16, kind: MouseEventKind) {
         if y >= 7 && y < self.height - 3 {
             // Header(3) + Input(3) + Border(1) = 7
             let list_start_y = 7;
@@ -167,16 +175,19 @@ impl App {
 
             let clicked_row = (y - list_start_y) as usize;
 
-            if clicked_row < list_content_height {
-                let offset = if self.preset_cursor_pos >= list_content_height {
-                    self.preset_cursor_pos - list_content_height + 1
+            if clicked_row < list_content_height && x < crate::ui::presets::list_width(self.width) {
+                let visible = self.visible_presets();
+                let cursor_row = visible
+                    .iter()
+                    .position(|&i| i == self.preset_cursor_pos)
+                    .unwrap_or(0);
+                let offset = if cursor_row >= list_content_height {
+                    cursor_row - list_content_height + 1
                 } else {
                     0
                 };
 
-                let target_idx = offset + clicked_row;
-
-                if target_idx < self.presets_config.presets.len() {
+                if let Some(&target_idx) = visible.get(offset + clicked_row) {
                     if let MouseEventKind::Down(MouseButton::Left) = kind {
                         if self.preset_cursor_pos == target_idx {
                             // Double click / second click -> Load
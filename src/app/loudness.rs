@@ -0,0 +1,70 @@
+use super::{App, CurrentView};
+use crate::loudness::{self, Analyzer};
+use crate::player::Player;
+
+impl App {
+    pub fn start_loudness_analysis(&mut self) {
+        // An attached daemon measures files itself
+        if !self.audio_engine.as_ref().is_some_and(Player::is_remote) {
+            self.loudness_analyzer = Some(Analyzer::spawn());
+        }
+        self.measure_pending_loudness();
+    }
+
+    /// Picks up cached measurements and queues the files that have none.
+    pub fn measure_pending_loudness(&mut self) {
+        let pending = loudness::annotate(&mut self.sounds, &self.config);
+        if let Some(analyzer) = &self.loudness_analyzer {
+            for path in pending {
+                analyzer.queue(path);
+            }
+        }
+    }
+
+    pub fn update_loudness(&mut self) {
+        let Some(analyzer) = &self.loudness_analyzer else {
+            return;
+        };
+        for measurement in analyzer.poll() {
+            for sound in &mut self.sounds {
+                if sound.file_path == measurement.path {
+                    sound.loudness = measurement.lufs;
+                    if let Some(engine) = &mut self.audio_engine {
+                        engine.set_loudness(sound);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn open_details(&mut self) {
+        if self.sounds.get(self.cursor_pos).is_some() {
+            self.view = CurrentView::Details;
+        }
+    }
+
+    /// Switches loudness normalisation of the current sound, saved as
+    /// `sounds.<id>.normalize` in config.toml.
+    pub fn toggle_current_normalize(&mut self) {
+        // Synthesized and event sounds are never measured, so there is nothing to trim
+        let Some(sound) = self
+            .sounds
+            .get_mut(self.cursor_pos)
+            .filter(|s| s.has_file())
+        else {
+            return;
+        };
+        sound.normalize = !sound.normalize;
+        self.config
+            .sounds
+            .entry(sound.id.clone())
+            .or_default()
+            .normalize = sound.normalize;
+        if let Err(e) = self.config.save() {
+            log::error!("Failed to save config: {}", e);
+        }
+        if let Some(engine) = &mut self.audio_engine {
+            engine.set_loudness(sound);
+        }
+    }
+}
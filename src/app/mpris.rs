@@ -0,0 +1,82 @@
+use super::App;
+use crate::mpris::{MprisCommand, MprisState, PlaybackStatus};
+
+impl App {
+    pub fn update_mpris(&mut self) {
+        let Some(server) = &self.mpris else {
+            return;
+        };
+
+        for command in server.poll() {
+            match command {
+                MprisCommand::PlayPause => {
+                    if self.playing_sound_ids().is_empty() {
+                        self.resume_all();
+                    } else {
+                        self.toggle_mute();
+                    }
+                }
+                MprisCommand::Play => {
+                    if self.playing_sound_ids().is_empty() {
+                        self.resume_all();
+                    } else if self.muted {
+                        self.toggle_mute();
+                    }
+                }
+                MprisCommand::Pause => {
+                    if !self.muted {
+                        self.toggle_mute();
+                    }
+                }
+                MprisCommand::Stop => self.stop_all(),
+                MprisCommand::Next => self.cycle_preset(1),
+                MprisCommand::Previous => self.cycle_preset(-1),
+                MprisCommand::Seek(delta) => self.seek_current(delta),
+                MprisCommand::SetPosition(position) => self.seek_current_to(position),
+                MprisCommand::SetVolume(vol) => {
+                    self.muted = false;
+                    self.set_master_volume(vol);
+                }
+                MprisCommand::Quit => self.quitting = true,
+            }
+        }
+
+        let state = self.mpris_state();
+        if let Some(server) = &mut self.mpris {
+            server.publish(state);
+        }
+    }
+
+    fn mpris_state(&self) -> MprisState {
+        let sounds: Vec<String> = self
+            .sounds
+            .iter()
+            .filter(|s| {
+                self.audio_engine
+                    .as_ref()
+                    .is_some_and(|engine| engine.is_playing(&s.id))
+            })
+            .map(|s| s.name.clone())
+            .collect();
+
+        let status = if sounds.is_empty() {
+            PlaybackStatus::Stopped
+        } else if self.muted {
+            PlaybackStatus::Paused
+        } else {
+            PlaybackStatus::Playing
+        };
+
+        let position = self.current_position();
+
+        MprisState {
+            status,
+            volume: self.session.global_volume,
+            preset: self.active_preset.clone(),
+            sounds,
+            has_presets: !self.presets_config.presets.is_empty(),
+            position: position.map(|(elapsed, _)| elapsed),
+            length: position.and_then(|(_, total)| total),
+        }
+    }
+}
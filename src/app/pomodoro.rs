@@ -0,0 +1,99 @@
+use super::App;
+use crate::pomodoro::{self, Phase, PhaseMix, Pomodoro};
+use std::io::Write;
+use std::time::Duration;
+
+impl App {
+    /// Starts a focus session, or ends the running one.
+    pub fn toggle_pomodoro(&mut self) {
+        if self.pomodoro.take().is_some() {
+            log::info!("Pomodoro stopped");
+            return;
+        }
+        let pomodoro = Pomodoro::start(&self.config.pomodoro);
+        log::info!("Pomodoro started");
+        self.pomodoro = Some(pomodoro);
+        self.enter_pomodoro_phase(Phase::Work);
+    }
+
+    /// Cuts the current phase short.
+    pub fn skip_pomodoro_phase(&mut self) {
+        let Some(pomodoro) = &mut self.pomodoro else {
+            return;
+        };
+        let phase = pomodoro.next_phase();
+        self.enter_pomodoro_phase(phase);
+    }
+
+    pub fn update_pomodoro(&mut self, dt: Duration) {
+        let Some(pomodoro) = &mut self.pomodoro else {
+            return;
+        };
+        let Some(phase) = pomodoro.advance(dt) else {
+            return;
+        };
+        if phase != Phase::Work {
+            let (cycle, length) = (pomodoro.cycle(), pomodoro.length(Phase::Work));
+            if let Err(e) = pomodoro::log_completed(cycle, length) {
+                log::error!("Failed to write pomodoro log: {}", e);
+            }
+        }
+        self.enter_pomodoro_phase(phase);
+    }
+
+    fn enter_pomodoro_phase(&mut self, phase: Phase) {
+        let Some(pomodoro) = &self.pomodoro else {
+            return;
+        };
+        let (mix, minutes) = (pomodoro.mix(phase), pomodoro.remaining.as_secs() / 60);
+        log::info!("Pomodoro phase: {}", phase.label());
+        match mix {
+            PhaseMix::Keep => {}
+            PhaseMix::Mute => self.fade_out_all(),
+            PhaseMix::Preset(name) => match self
+                .presets_config
+                .presets
+                .iter()
+                .position(|p| p.name.eq_ignore_ascii_case(&name))
+            {
+                Some(index) => self.switch_to_preset(index),
+                None => log::error!("Pomodoro preset '{}' does not exist", name),
+            },
+        }
+
+        let settings = &self.config.pomodoro;
+        if settings.bell {
+            let mut stdout = std::io::stdout();
+            let _ = stdout.write_all(b"\x07");
+            let _ = stdout.flush();
+        }
+        if settings.notify {
+            let body = format!("{} for {} minutes", phase.label(), minutes);
+            // Waited on in the background so a slow notification daemon can't stall the UI
+            std::thread::spawn(move || {
+                if let Err(e) = std::process::Command::new("notify-send")
+                    .args(["Tanin", &body])
+                    .status()
+                {
+                    log::error!("Failed to run notify-send: {}", e);
+                }
+            });
+        }
+    }
+
+    /// Fades every sound out over the preset crossfade; `r` brings them back.
+    fn fade_out_all(&mut self) {
+        let playing = self.playing_sound_ids();
+        if playing.is_empty() {
+            return;
+        }
+        let fade = self.config.transition.crossfade();
+        if let Some(engine) = &mut self.audio_engine {
+            for id in &playing {
+                engine.stop_with_fade(id, fade);
+            }
+        }
+        self.resume_sounds = playing;
+        self.active_preset = None;
+    }
+}
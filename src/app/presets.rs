@@ -1,4 +1,26 @@
-use super::{App, CurrentView};
+use super::{App, CurrentView, DownloadStatus, DownloadTask};
+use crate::presets::{self, PresetSort, PresetSound};
+use crate::share::{self, SharedPreset};
+use std::collections::HashMap;
+
+/// Preset details edited through the Presets view's input box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresetField {
+    Description,
+    /// Comma separated.
+    Tags,
+    Icon,
+}
+
+impl PresetField {
+    pub fn label(self) -> &'static str {
+        match self {
+            PresetField::Description => "Description",
+            PresetField::Tags => "Tags (comma separated)",
+            PresetField::Icon => "Icon",
+        }
+    }
+}
 
 impl App {
     pub fn confirm_preset_input(&mut self) {
@@ -14,20 +36,13 @@ impl App {
             }
         } else {
             // Create new
-            let mut preset_sounds = std::collections::HashMap::new();
-
-            // Capture currently playing sounds
-            if let Some(engine) = &self.audio_engine {
-                for sound in &self.sounds {
-                    if engine.is_playing(&sound.id) {
-                        preset_sounds.insert(sound.id.clone(), sound.volume_linear);
-                    }
-                }
-            }
-
             let new_preset = crate::presets::Preset {
                 name,
-                sounds: preset_sounds,
+                sounds: self.capture_preset_sounds(),
+                created: Some(presets::now()),
+                master_volume: Some(self.unmuted_master_volume()),
+                muted: self.muted,
+                ..Default::default()
             };
 
             self.presets_config.presets.push(new_preset);
@@ -38,72 +53,369 @@ impl App {
     }
 
     pub fn start_renaming_preset(&mut self) {
-        if let Some(preset) = self.presets_config.presets.get(self.preset_cursor_pos) {
-            self.preset_input_buffer = preset.name.clone();
-            self.preset_rename_target = Some(self.preset_cursor_pos);
-            self.preset_input_mode = true;
-        }
+        let Some(index) = self.selected_preset() else {
+            return;
+        };
+        self.preset_input_buffer = self.presets_config.presets[index].name.clone();
+        self.preset_rename_target = Some(index);
+        self.preset_input_mode = true;
     }
 
     pub fn update_preset_sounds(&mut self) {
-        if self.preset_cursor_pos >= self.presets_config.presets.len() {
+        let Some(index) = self.selected_preset() else {
             return;
-        }
+        };
 
-        let mut preset_sounds = std::collections::HashMap::new();
-        if let Some(engine) = &self.audio_engine {
-            for sound in &self.sounds {
-                if engine.is_playing(&sound.id) {
-                    preset_sounds.insert(sound.id.clone(), sound.volume_linear);
-                }
-            }
-        }
-
-        if let Some(preset) = self.presets_config.presets.get_mut(self.preset_cursor_pos) {
-            preset.sounds = preset_sounds;
+        let sounds = self.capture_preset_sounds();
+        let (master_volume, muted) = (self.unmuted_master_volume(), self.muted);
+        if let Some(preset) = self.presets_config.presets.get_mut(index) {
+            preset.sounds = sounds;
+            preset.master_volume = Some(master_volume);
+            preset.muted = muted;
         }
         let _ = self.presets_config.save();
     }
 
+    /// Settings of every playing sound, as stored in a preset.
+    fn capture_preset_sounds(&self) -> HashMap<String, PresetSound> {
+        let Some(engine) = &self.audio_engine else {
+            return HashMap::new();
+        };
+        self.sounds
+            .iter()
+            .filter(|sound| engine.is_playing(&sound.id))
+            .map(|sound| (sound.id.clone(), PresetSound::from_sound(sound)))
+            .collect()
+    }
+
     pub fn load_preset(&mut self, index: usize) {
         if index >= self.presets_config.presets.len() {
             return;
         }
 
-        // Clone the sounds map to avoid borrowing self while mutating self later
-        let preset_sounds = self.presets_config.presets[index].sounds.clone();
+        self.switch_to_preset(index);
+        self.view = CurrentView::Main;
+    }
 
-        self.stop_all();
+    /// Crossfades from the current mix into a preset: sounds that are not part of
+    /// it fade out, shared sounds keep playing and glide to their new volume, and
+    /// the rest fade in.
+    pub fn switch_to_preset(&mut self, index: usize) {
+        // Clone the preset to avoid borrowing self while mutating self later
+        let Some(preset) = self.presets_config.presets.get(index).cloned() else {
+            return;
+        };
+        let fade = self.config.transition.crossfade();
 
-        // Need to update app.sounds volumes and play them
         if let Some(engine) = &mut self.audio_engine {
             for sound in &mut self.sounds {
-                if let Some(&vol) = preset_sounds.get(&sound.id) {
-                    sound.volume_linear = vol;
-                    sound.error_state = false;
-                    if let Err(e) = engine.play(&sound.id, &sound.file_path, sound.volume_linear) {
-                        log::error!("Failed to play preset sound '{}': {}", sound.id, e);
-                        sound.error_state = true;
+                let Some(entry) = preset.sounds.get(&sound.id) else {
+                    if engine.is_playing(&sound.id) {
+                        engine.stop_with_fade(&sound.id, fade);
                     }
-                } else {
-                    // Optionally reset volume of unused sounds or keep them as is?
-                    // Usually presets define the active set.
+                    continue;
+                };
+
+                entry.apply_to(sound);
+                let vol = entry.volume;
+                engine.set_tone(&sound.id, sound.tone);
+                engine.set_spatial(&sound.id, sound.spatial);
+                engine.set_drift(&sound.id, sound.drift);
+
+                if engine.is_playing(&sound.id) {
+                    engine.ramp_volume(&sound.id, vol, fade);
+                    continue;
+                }
+
+                sound.error_state = false;
+                if let Err(e) = engine.play_with_fade(sound, vol, fade) {
+                    log::error!("Failed to play preset sound '{}': {}", sound.id, e);
+                    sound.error_state = true;
                 }
             }
         }
-        self.active_preset = Some(self.presets_config.presets[index].name.clone());
-        self.view = CurrentView::Main;
+        if let Some(volume) = preset.master_volume {
+            self.restore_master(volume, preset.muted);
+        }
+        if let Some(preset) = self.presets_config.presets.get_mut(index) {
+            preset.mark_used();
+            let _ = self.presets_config.save();
+        }
+        self.active_preset = Some(preset.name);
+    }
+
+    /// Sets the master volume and mute state a preset was saved with.
+    fn restore_master(&mut self, volume: f32, muted: bool) {
+        if muted {
+            if !self.muted {
+                self.toggle_mute();
+            }
+            self.previous_volume = volume;
+        } else {
+            self.muted = false;
+            self.set_master_volume(volume);
+        }
+    }
+
+    /// The master volume to store in a preset, the one from before muting if muted.
+    fn unmuted_master_volume(&self) -> f32 {
+        if self.muted {
+            self.previous_volume
+        } else {
+            self.session.global_volume
+        }
+    }
+
+    /// Indices of the presets the Presets view lists, in its order.
+    pub fn visible_presets(&self) -> Vec<usize> {
+        self.presets_config
+            .sorted(self.preset_sort, self.preset_tag_filter.as_deref())
+    }
+
+    /// The preset under the cursor, unless the list doesn't show it.
+    pub fn selected_preset(&self) -> Option<usize> {
+        let index = self.preset_cursor_pos;
+        self.visible_presets().contains(&index).then_some(index)
+    }
+
+    /// Row of the selected preset in the list, the top one if it isn't listed.
+    fn selected_preset_row(&self) -> usize {
+        self.visible_presets()
+            .iter()
+            .position(|&i| i == self.preset_cursor_pos)
+            .unwrap_or(0)
+    }
+
+    /// Selects the preset listed at `row`, or the last one if there are fewer.
+    fn select_preset_row(&mut self, row: usize) {
+        let visible = self.visible_presets();
+        self.preset_cursor_pos = visible
+            .get(row.min(visible.len().saturating_sub(1)))
+            .copied()
+            .unwrap_or(0);
+    }
+
+    /// Moves the cursor to the preset now at `row` if the list stopped showing
+    /// the selected one, so keys never act on a preset that can't be seen.
+    fn keep_preset_cursor_listed(&mut self, row: usize) {
+        if self.selected_preset().is_none() {
+            self.select_preset_row(row);
+        }
+    }
+
+    /// Moves the selection `delta` rows through the listed presets.
+    pub fn move_preset_cursor(&mut self, delta: isize) {
+        let visible = self.visible_presets();
+        let row = visible
+            .iter()
+            .position(|&i| i == self.preset_cursor_pos)
+            .map_or(0, |row| {
+                (row as isize + delta).clamp(0, visible.len() as isize - 1) as usize
+            });
+        if let Some(&index) = visible.get(row) {
+            self.preset_cursor_pos = index;
+        }
+    }
+
+    pub fn cycle_preset_sort(&mut self) {
+        self.preset_sort = self.preset_sort.next();
+    }
+
+    /// Steps the tag filter through every tag in use and back to showing all.
+    pub fn cycle_preset_tag_filter(&mut self) {
+        let tags = self.presets_config.tags();
+        let next = match &self.preset_tag_filter {
+            None => 0,
+            Some(tag) => tags
+                .iter()
+                .position(|t| t.eq_ignore_ascii_case(tag))
+                .map_or(0, |i| i + 1),
+        };
+        self.preset_tag_filter = tags.get(next).cloned();
+        self.keep_preset_cursor_listed(0);
+    }
+
+    pub fn toggle_preset_favourite(&mut self) {
+        let Some(index) = self.selected_preset() else {
+            return;
+        };
+        let preset = &mut self.presets_config.presets[index];
+        preset.favourite = !preset.favourite;
+        let _ = self.presets_config.save();
+    }
+
+    /// Swaps the selected preset with the one listed `delta` rows away,
+    /// switching to the manual order first.
+    pub fn move_preset(&mut self, delta: isize) {
+        self.preset_sort = PresetSort::Manual;
+        let visible = self.visible_presets();
+        let Some(row) = visible.iter().position(|&i| i == self.preset_cursor_pos) else {
+            return;
+        };
+        let Some(&other) = row
+            .checked_add_signed(delta)
+            .and_then(|row| visible.get(row))
+        else {
+            return;
+        };
+        self.presets_config
+            .presets
+            .swap(self.preset_cursor_pos, other);
+        self.preset_cursor_pos = other;
+        let _ = self.presets_config.save();
+    }
+
+    pub fn start_editing_preset(&mut self, field: PresetField) {
+        let Some(index) = self.selected_preset() else {
+            return;
+        };
+        let preset = &self.presets_config.presets[index];
+        self.preset_input_buffer = match field {
+            PresetField::Description => preset.description.clone().unwrap_or_default(),
+            PresetField::Tags => preset.tags.join(", "),
+            PresetField::Icon => preset.icon.clone().unwrap_or_default(),
+        };
+        self.preset_edit_field = Some(field);
+        self.preset_input_mode = true;
+    }
+
+    pub fn confirm_preset_field(&mut self, field: PresetField) {
+        let Some(index) = self.selected_preset() else {
+            return;
+        };
+        let row = self.selected_preset_row();
+        let preset = &mut self.presets_config.presets[index];
+        let value = self.preset_input_buffer.trim();
+        let text = (!value.is_empty()).then(|| value.to_string());
+        match field {
+            PresetField::Description => preset.description = text,
+            PresetField::Tags => {
+                preset.tags = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                    .map(String::from)
+                    .collect();
+            }
+            PresetField::Icon => preset.icon = text,
+        }
+        let _ = self.presets_config.save();
+        // New tags can take the preset out of the filtered list
+        self.keep_preset_cursor_listed(row);
+    }
+
+    /// Loads the preset `delta` steps away from the active one, wrapping around.
+    pub fn cycle_preset(&mut self, delta: isize) {
+        let count = self.presets_config.presets.len();
+        if count == 0 {
+            return;
+        }
+
+        let index = match self.active_preset.as_ref().and_then(|name| {
+            self.presets_config
+                .presets
+                .iter()
+                .position(|p| &p.name == name)
+        }) {
+            Some(current) => (current as isize + delta).rem_euclid(count as isize) as usize,
+            None => 0,
+        };
+
+        self.preset_cursor_pos = index;
+        self.load_preset(index);
     }
 
     pub fn delete_preset(&mut self, index: usize) {
         if index < self.presets_config.presets.len() {
+            let row = self.selected_preset_row();
             self.presets_config.presets.remove(index);
             let _ = self.presets_config.save();
-            if self.preset_cursor_pos >= self.presets_config.presets.len()
-                && !self.presets_config.presets.is_empty()
-            {
-                self.preset_cursor_pos = self.presets_config.presets.len() - 1;
+            // The preset listed below takes its place, wherever it is in the file
+            self.select_preset_row(row);
+        }
+    }
+
+    /// Writes the preset to a file that `i` or `tanin preset import` reads back.
+    pub fn export_preset(&mut self, index: usize) {
+        let Some(preset) = self.presets_config.presets.get(index) else {
+            return;
+        };
+        let path = share::export_path(&preset.name);
+        let result = SharedPreset::new(preset, &self.sounds)
+            .to_toml()
+            .and_then(|content| Ok(std::fs::write(&path, content)?));
+        self.preset_status = Some(match result {
+            Ok(()) => format!("Exported to {}", path.display()),
+            Err(e) => format!("Export failed: {}", e),
+        });
+    }
+
+    /// Puts the preset's code on the clipboard and shows it.
+    pub fn copy_preset_code(&mut self, index: usize) {
+        let Some(preset) = self.presets_config.presets.get(index) else {
+            return;
+        };
+        self.preset_status = Some(match SharedPreset::new(preset, &self.sounds).to_code() {
+            Ok(code) => {
+                if let Err(e) = share::copy_to_clipboard(&code) {
+                    log::error!("Failed to copy preset code: {}", e);
+                }
+                code
             }
+            Err(e) => format!("Export failed: {}", e),
+        });
+    }
+
+    /// Adds the preset file or code typed into the input box. Sounds missing
+    /// here wait in `preset_downloads` until the download is confirmed.
+    pub fn confirm_preset_import(&mut self) {
+        let shared = match SharedPreset::read(&self.preset_input_buffer) {
+            Ok(shared) => shared,
+            Err(e) => {
+                self.preset_status = Some(format!("Import failed: {:#}", e));
+                return;
+            }
+        };
+        let missing = shared.missing(&self.sounds);
+        let name = shared.add_to(&mut self.presets_config);
+        let _ = self.presets_config.save();
+        self.preset_cursor_pos = self.presets_config.presets.len() - 1;
+        self.keep_preset_cursor_listed(0);
+
+        let mut status = format!("Imported '{}'", name);
+        if !missing.unavailable.is_empty() {
+            status.push_str(&format!(
+                ", without {} (no download source)",
+                missing.unavailable.join(", ")
+            ));
         }
+        if !missing.downloadable.is_empty() {
+            status.push_str(&format!(
+                ". Download {} missing sound(s)? y / n",
+                missing.downloadable.len()
+            ));
+        }
+        self.preset_status = Some(status);
+        self.preset_downloads = missing.downloadable;
+    }
+
+    pub fn queue_preset_downloads(&mut self) {
+        let count = self.preset_downloads.len();
+        for source in self.preset_downloads.drain(..) {
+            self.download_queue.push(DownloadTask {
+                name: source.download_name(),
+                category: source.category,
+                icon: source.icon,
+                url: source.url,
+                status: DownloadStatus::Pending,
+                target_filename: source.file,
+            });
+        }
+        self.preset_status = Some(format!("Queued {} download(s)", count));
+    }
+
+    pub fn skip_preset_downloads(&mut self) {
+        self.preset_downloads.clear();
+        self.preset_status = None;
     }
 }
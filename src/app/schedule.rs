@@ -0,0 +1,35 @@
+use super::App;
+use crate::player::Player;
+use crate::schedule::Schedule;
+
+impl App {
+    pub fn update_schedule(&mut self) {
+        // An attached daemon runs the schedule itself
+        if self.audio_engine.as_ref().is_some_and(Player::is_remote) {
+            return;
+        }
+
+        let Some(name) = self.schedule.poll() else {
+            return;
+        };
+        match self
+            .presets_config
+            .presets
+            .iter()
+            .position(|p| p.name.eq_ignore_ascii_case(&name))
+        {
+            Some(index) => {
+                log::info!("Schedule switching to preset '{}'", name);
+                self.switch_to_preset(index);
+            }
+            None => log::error!("Scheduled preset '{}' does not exist", name),
+        }
+    }
+
+    pub fn reload_schedule(&mut self) {
+        match Schedule::load() {
+            Ok(schedule) => self.schedule = schedule,
+            Err(e) => log::error!("Failed to reload schedule: {}", e),
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use super::App;
+use std::time::Duration;
+
+/// Seconds jumped by `f` / `b`.
+pub const SEEK_STEP: f64 = 30.0;
+/// Seconds jumped by `F` / `B`.
+pub const LONG_SEEK_STEP: f64 = 300.0;
+
+impl App {
+    /// Jumps the current sound forward (positive `delta`) or back by `delta`
+    /// seconds, if it is playing a file.
+    pub fn seek_current(&mut self, delta: f64) {
+        let Some((elapsed, _)) = self.current_position() else {
+            return;
+        };
+        let target = (elapsed.as_secs_f64() + delta).max(0.0);
+        self.seek_current_to(Duration::from_secs_f64(target));
+    }
+
+    /// Jumps the current sound to `target`, if it is playing a file.
+    pub fn seek_current_to(&mut self, target: Duration) {
+        if self.current_position().is_none() {
+            return;
+        }
+        let sound = &self.sounds[self.cursor_pos];
+        if let Some(engine) = &mut self.audio_engine {
+            engine.seek(&sound.id, target);
+        }
+    }
+
+    /// How far the current sound is into its file and the file's length, if
+    /// it is playing one.
+    pub fn current_position(&self) -> Option<(Duration, Option<Duration>)> {
+        let sound = self.sounds.get(self.cursor_pos)?;
+        if !sound.has_file() {
+            return None;
+        }
+        self.audio_engine.as_ref()?.position(&sound.id)
+    }
+}
@@ -0,0 +1,91 @@
+use super::App;
+use crate::player::Player;
+use crate::sleep::SleepTimer;
+use std::time::Duration;
+
+impl App {
+    pub fn set_sleep_timer(&mut self, duration: Option<Duration>) {
+        self.sleep_timer = duration.map(|remaining| SleepTimer::new(remaining, &self.config.sleep));
+
+        match &self.sleep_timer {
+            Some(timer) => log::info!("Sleep timer set for {:?}", timer.remaining),
+            None => log::info!("Sleep timer cancelled"),
+        }
+
+        // An attached daemon runs the timer, so it keeps going once the TUI quits
+        if let Some(Player::Remote(client)) = &mut self.audio_engine {
+            client.set_sleep_timer(duration);
+            return;
+        }
+        self.apply_master_volume();
+    }
+
+    /// Advances the sleep timer to the next configured step, wrapping back to off.
+    pub fn cycle_sleep_timer(&mut self) {
+        let steps = &self.config.sleep.steps_minutes;
+        let current_minutes = self
+            .sleep_timer
+            .as_ref()
+            .map(|t| t.remaining.as_secs().div_ceil(60));
+
+        let next = match current_minutes {
+            None => steps.first().copied(),
+            Some(current) => steps.iter().copied().find(|&m| m > current),
+        };
+
+        self.set_sleep_timer(next.map(|m| Duration::from_secs(m * 60)));
+    }
+
+    pub fn restore_sleep_timer(&mut self) {
+        // The daemon restores its own timer
+        if self.audio_engine.as_ref().is_some_and(Player::is_remote) {
+            return;
+        }
+        let Some(deadline) = self.session.sleep_until else {
+            return;
+        };
+
+        match SleepTimer::resume(deadline, &self.config.sleep) {
+            Some(timer) => self.set_sleep_timer(Some(timer.remaining)),
+            None => self.session.sleep_until = None,
+        }
+    }
+
+    pub fn sleep_deadline(&self) -> Option<u64> {
+        self.sleep_timer.as_ref().map(SleepTimer::deadline)
+    }
+
+    pub fn update_sleep_timer(&mut self, dt: Duration) {
+        if let Some(Player::Remote(client)) = &mut self.audio_engine {
+            // Mirror the daemon's timer for the footer
+            self.sleep_timer = client
+                .sleep_remaining()
+                .map(|remaining| SleepTimer::new(remaining, &self.config.sleep));
+            if client.take_sleep_finished() && self.config.sleep.quit_on_finish {
+                log::info!("Daemon sleep timer finished, quitting");
+                self.quitting = true;
+            }
+            return;
+        }
+
+        let Some(timer) = &mut self.sleep_timer else {
+            return;
+        };
+
+        if !timer.tick(dt) {
+            if timer.is_fading() {
+                self.apply_master_volume();
+            }
+            return;
+        }
+
+        log::info!("Sleep timer finished, stopping all sounds");
+        self.sleep_timer = None;
+        self.stop_all();
+        self.apply_master_volume();
+
+        if self.config.sleep.quit_on_finish {
+            self.quitting = true;
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use super::App;
+use crate::dsp::Spatial;
+
+const PAN_STEP: f32 = 0.1;
+const WIDTH_STEP: f32 = 0.25;
+
+impl App {
+    /// Moves the current sound left (negative `delta`) or right.
+    pub fn pan_current(&mut self, delta: f32) {
+        self.update_current_spatial(|spatial| spatial.pan += delta * PAN_STEP);
+    }
+
+    pub fn step_current_width(&mut self, delta: f32) {
+        self.update_current_spatial(|spatial| spatial.width += delta * WIDTH_STEP);
+    }
+
+    pub fn center_current(&mut self) {
+        self.update_current_spatial(|spatial| *spatial = Spatial::default());
+    }
+
+    fn update_current_spatial(&mut self, f: impl FnOnce(&mut Spatial)) {
+        if let Some(sound) = self.sounds.get_mut(self.cursor_pos) {
+            f(&mut sound.spatial);
+            // Snap away float drift so stepping back lands exactly on centre
+            sound.spatial = Spatial {
+                pan: (sound.spatial.pan * 100.0).round() / 100.0,
+                width: (sound.spatial.width * 100.0).round() / 100.0,
+            }
+            .clamped();
+            if let Some(engine) = &mut self.audio_engine {
+                engine.set_spatial(&sound.id, sound.spatial);
+            }
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use super::App;
+use crate::dsp::Tone;
+
+/// Low-pass steps, from open to darkest.
+const LOW_PASS_STEPS: [Option<f32>; 9] = [
+    None,
+    Some(12000.0),
+    Some(8000.0),
+    Some(5000.0),
+    Some(3000.0),
+    Some(2000.0),
+    Some(1200.0),
+    Some(800.0),
+    Some(500.0),
+];
+
+/// High-pass steps, from open to thinnest.
+const HIGH_PASS_STEPS: [Option<f32>; 7] = [
+    None,
+    Some(40.0),
+    Some(80.0),
+    Some(120.0),
+    Some(200.0),
+    Some(300.0),
+    Some(500.0),
+];
+
+/// Shelf EQ profiles cycled with `e`: (bass, mid, treble) in dB.
+const EQ_PROFILES: [(f32, f32, f32); 4] = [
+    (0.0, 0.0, 0.0),
+    (3.0, 0.0, -4.0),
+    (0.0, -2.0, -8.0),
+    (-3.0, 0.0, 3.0),
+];
+
+fn step(steps: &[Option<f32>], current: Option<f32>, delta: isize) -> Option<f32> {
+    // Values edited by hand may fall between steps, start from the nearest one
+    let index = steps
+        .iter()
+        .position(|s| match (s, current) {
+            (None, None) => true,
+            (Some(s), Some(c)) => *s == c,
+            _ => false,
+        })
+        .or_else(|| {
+            let c = current?;
+            steps
+                .iter()
+                .enumerate()
+                .filter_map(|(i, s)| s.map(|s| (i, (s - c).abs())))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(i, _)| i)
+        })
+        .unwrap_or(0);
+
+    let next = (index as isize + delta).clamp(0, steps.len() as isize - 1);
+    steps[next as usize]
+}
+
+impl App {
+    /// Moves the current sound's low-pass cutoff; positive `delta` makes it darker.
+    pub fn step_low_pass(&mut self, delta: isize) {
+        self.update_current_tone(|tone| {
+            tone.low_pass = step(&LOW_PASS_STEPS, tone.low_pass, delta)
+        });
+    }
+
+    /// Moves the current sound's high-pass cutoff; positive `delta` removes more rumble.
+    pub fn step_high_pass(&mut self, delta: isize) {
+        self.update_current_tone(|tone| {
+            tone.high_pass = step(&HIGH_PASS_STEPS, tone.high_pass, delta)
+        });
+    }
+
+    pub fn cycle_eq_profile(&mut self) {
+        self.update_current_tone(|tone| {
+            let current = (tone.bass_db, tone.mid_db, tone.treble_db);
+            let next = EQ_PROFILES
+                .iter()
+                .position(|p| *p == current)
+                .map_or(0, |i| (i + 1) % EQ_PROFILES.len());
+            (tone.bass_db, tone.mid_db, tone.treble_db) = EQ_PROFILES[next];
+        });
+    }
+
+    pub fn reset_current_tone(&mut self) {
+        self.update_current_tone(|tone| *tone = Tone::default());
+    }
+
+    fn update_current_tone(&mut self, f: impl FnOnce(&mut Tone)) {
+        if let Some(sound) = self.sounds.get_mut(self.cursor_pos) {
+            f(&mut sound.tone);
+            if let Some(engine) = &mut self.audio_engine {
+                engine.set_tone(&sound.id, sound.tone);
+            }
+        }
+    }
+}
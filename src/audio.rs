@@ -1,14 +1,22 @@
 use anyhow::{Context, Result};
-use cpal::traits::{DeviceTrait, HostTrait};
-use cpal::HostId;
-use magnum::container::ogg::OpusSourceOgg;
-use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
-use std::collections::HashMap;
+use rodio::dynamic_mixer::{self, DynamicMixer, DynamicMixerController};
+use rodio::source::UniformSourceIterator;
+use rodio::{Decoder, Sink, Source};
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::BufReader;
 use std::time::Duration;
-use std::sync::mpsc::Sender;
-use crate::buffered::{self, DecodeTask};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use crate::buffered::{self, StreamControl, WorkerPool};
+use crate::config::{AudioConfig, LoudnessConfig};
+use crate::dsp::events::{self, Clip};
+use crate::dsp::{
+    Drift, DriftState, Panner, Spatial, SpatialControl, Tone, ToneControl, ToneFilter,
+};
+use crate::opus::OggOpusSource;
+use crate::output::{self, MixerTap, OutputBackend};
+use crate::static_data::{LoopRegion, Sound, VariantOrder, Variants};
 
 struct FadingSink {
     id: String,
@@ -18,21 +26,40 @@ struct FadingSink {
     total_duration: Duration,
 }
 
-struct MagnumOggWrapper<R: std::io::Read + std::io::Seek>(OpusSourceOgg<R>);
-impl<R: std::io::Read + std::io::Seek> Iterator for MagnumOggWrapper<R> {
-    type Item = f32;
-    fn next(&mut self) -> Option<Self::Item> {
-        self.0.next()
-    }
+/// A volume glide in progress, see [`AudioEngine::ramp_volume`].
+struct VolumeRamp {
+    from: f32,
+    to: f32,
+    elapsed: Duration,
+    duration: Duration,
 }
-impl<R: std::io::Read + std::io::Seek> Source for MagnumOggWrapper<R> {
-    fn current_frame_len(&self) -> Option<usize> { None }
-    fn channels(&self) -> u16 { 2 }
-    fn sample_rate(&self) -> u32 { 48000 }
-    fn total_duration(&self) -> Option<Duration> { None }
+
+impl VolumeRamp {
+    fn is_done(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    fn level(&self) -> f32 {
+        if self.is_done() {
+            return self.to;
+        }
+        // Eased at both ends so the glide never starts or stops with a jump in slope
+        let t = self.elapsed.as_secs_f32() / self.duration.as_secs_f32();
+        let eased = 0.5 - 0.5 * (t * std::f32::consts::PI).cos();
+        self.from + (self.to - self.from) * eased
+    }
 }
 
-fn create_decoder_from_path(file_path: &str) -> Result<Box<dyn Source<Item = f32> + Send>> {
+/// Fade used when sounds start and stop outside of preset crossfades.
+pub const FADE_DURATION: Duration = Duration::from_secs(2);
+
+/// Shortest fade handed to rodio, whose fade-in divides by its length.
+const MIN_FADE: Duration = Duration::from_millis(10);
+
+/// Event clips are held in memory, so anything longer is cut short.
+const MAX_CLIP_DURATION: Duration = Duration::from_secs(60);
+
+pub fn create_decoder_from_path(file_path: &str) -> Result<Box<dyn Source<Item = f32> + Send>> {
     log::debug!("Opening file: {}", file_path);
     let file = File::open(file_path).context(format!("Failed to open sound file: {}", file_path))?;
 
@@ -43,14 +70,14 @@ fn create_decoder_from_path(file_path: &str) -> Result<Box<dyn Source<Item = f32
         || file_path.to_lowercase().ends_with(".webm");
 
     if is_opus {
-        log::info!("Attempting to use Magnum (Opus) decoder for: {}", file_path);
-        match OpusSourceOgg::new(BufReader::new(file_for_closure)) {
+        log::info!("Attempting to use Opus decoder for: {}", file_path);
+        match OggOpusSource::new(BufReader::new(file_for_closure)) {
             Ok(decoder) => {
-                log::info!("Magnum decoder created successfully.");
-                return Ok(Box::new(MagnumOggWrapper(decoder)));
+                log::info!("Opus decoder created successfully.");
+                return Ok(Box::new(decoder));
             }
             Err(e) => {
-                log::error!("Magnum decoder failed: {:?}. Falling back to Rodio.", e);
+                log::error!("Opus decoder failed: {:?}. Falling back to Rodio.", e);
             }
         }
     }
@@ -77,78 +104,252 @@ fn create_decoder_from_path(file_path: &str) -> Result<Box<dyn Source<Item = f32
     }
 }
 
+/// Event clips decoded so far, by path, so restarting a sound doesn't decode them again.
+static CLIPS: Mutex<BTreeMap<String, Clip>> = Mutex::new(BTreeMap::new());
+
+/// Decodes the clips of an event sound, taking those decoded before from the cache.
+pub fn decode_clips(paths: &[String]) -> Result<Vec<Clip>> {
+    paths
+        .iter()
+        .map(|path| {
+            if let Some(clip) = cached_clip(path) {
+                return Ok(clip);
+            }
+            let clip = Arc::new(decode_clip(path)?);
+            CLIPS
+                .lock()
+                .unwrap()
+                .insert(path.clone(), Arc::clone(&clip));
+            Ok(clip)
+        })
+        .collect()
+}
+
+fn cached_clip(path: &str) -> Option<Clip> {
+    CLIPS.lock().unwrap().get(path).cloned()
+}
+
+/// Decodes a whole event clip into memory, in the format events are mixed in.
+fn decode_clip(path: &str) -> Result<Vec<f32>> {
+    let decoder = create_decoder_from_path(path)?.take_duration(MAX_CLIP_DURATION);
+    let samples = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        UniformSourceIterator::<_, f32>::new(decoder, events::CHANNELS, events::SAMPLE_RATE)
+            .collect()
+    }))
+    .map_err(|_| anyhow::anyhow!("Decoder panicked on '{}'", path))?;
+    Ok(samples)
+}
+
+/// Cuts a decoded pass down to its loop region. Unlike rodio's `take_duration`,
+/// seeking moves the end along so it stays at the same place in the file.
+struct RegionSource {
+    inner: Box<dyn Source<Item = f32> + Send>,
+    /// Samples into the file where the region ends.
+    end: Option<u64>,
+    /// Samples left before `end`.
+    remaining: Option<u64>,
+}
+
+impl RegionSource {
+    fn new(mut inner: Box<dyn Source<Item = f32> + Send>, region: LoopRegion) -> Self {
+        let samples_per_sec = inner.sample_rate() as f64 * inner.channels() as f64;
+        let start = (region.start().as_secs_f64() * inner.sample_rate() as f64) as u64
+            * inner.channels() as u64;
+        // Decoding the intro away is the fallback for decoders that can't seek
+        if start > 0 && inner.try_seek(region.start()).is_err() {
+            for _ in inner.by_ref().take(start as usize) {}
+        }
+        let end = region
+            .length()
+            .map(|length| start + (length.as_secs_f64() * samples_per_sec) as u64);
+        Self {
+            inner,
+            end,
+            remaining: end.map(|end| end - start),
+        }
+    }
+}
+
+impl Iterator for RegionSource {
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        if let Some(remaining) = &mut self.remaining {
+            if *remaining == 0 {
+                return None;
+            }
+            *remaining -= 1;
+        }
+        self.inner.next()
+    }
+}
+
+impl Source for RegionSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), rodio::source::SeekError> {
+        self.inner.try_seek(pos)?;
+        let at = (pos.as_secs_f64() * self.inner.sample_rate() as f64) as u64
+            * self.inner.channels() as u64;
+        self.remaining = self.end.map(|end| end.saturating_sub(at));
+        Ok(())
+    }
+}
+
+/// Builds the chain every sound plays through: a looping decoder (or a
+/// generator), its tone filter and its panner. Shared with offline rendering so
+/// a rendered file matches what the engine plays. `seed` drives everything
+/// random about the sound: generator noise, event timing and variant order.
+pub fn sound_source(
+    sound: &Sound,
+    pool: &WorkerPool,
+    tone: Arc<ToneControl>,
+    spatial: Arc<SpatialControl>,
+    stream: Arc<StreamControl>,
+    seed: u64,
+) -> Result<Panner<ToneFilter<Box<dyn Source<Item = f32> + Send>>>> {
+    let base_source: Box<dyn Source<Item = f32> + Send> = if let Some(generator) = &sound.generator
+    {
+        // Synthesized on the fly, cheap enough to skip the decode workers
+        Box::new(generator.source(seed))
+    } else if let Some(events) = &sound.events {
+        let (clips_tx, clips_rx) = mpsc::channel();
+        let cached: Option<Vec<Clip>> = events.clips.iter().map(|path| cached_clip(path)).collect();
+        match cached {
+            Some(clips) => {
+                let _ = clips_tx.send(clips);
+            }
+            None => {
+                // Up to a minute of audio per clip, too slow to decode on the caller's
+                // thread; the sound stays silent until they are ready
+                let paths = events.clips.clone();
+                let id = sound.id.clone();
+                thread::spawn(move || match decode_clips(&paths) {
+                    Ok(clips) => {
+                        let _ = clips_tx.send(clips);
+                    }
+                    Err(e) => log::error!("Failed to decode the clips of '{}': {}", id, e),
+                });
+            }
+        }
+        Box::new(events.source(clips_rx, seed))
+    } else {
+        // A single file is a sound with one variant
+        let variants = sound.variants.clone().unwrap_or_else(|| Variants {
+            files: vec![sound.file_path.clone()],
+            order: VariantOrder::Sequential,
+        });
+        let mut picker = variants.picker(seed);
+        let region = sound.loop_region;
+        let loop_start = region.map_or(Duration::ZERO, |r| r.start());
+        let crossfade = region.map_or(Duration::ZERO, |r| r.crossfade());
+        // The stream keeps the format of the first file, later ones are converted to it
+        let mut format = None;
+        let control = Arc::clone(&stream);
+        let factory = move || {
+            let decoder = create_decoder_from_path(picker.next_file())?;
+            control.set_total(decoder.total_duration());
+            let decoder: Box<dyn Source<Item = f32> + Send> = match region {
+                Some(region) => Box::new(RegionSource::new(decoder, region)),
+                None => decoder,
+            };
+            let (channels, rate) =
+                *format.get_or_insert((decoder.channels(), decoder.sample_rate()));
+            if decoder.channels() == channels && decoder.sample_rate() == rate {
+                return Ok(decoder);
+            }
+            let converted: Box<dyn Source<Item = f32> + Send> =
+                Box::new(UniformSourceIterator::<_, f32>::new(
+                    decoder, channels, rate,
+                ));
+            Ok(converted)
+        };
+        Box::new(buffered::spawn_stream(
+            pool, stream, loop_start, crossfade, factory,
+        )?)
+    };
+    Ok(Panner::new(ToneFilter::new(base_source, tone), spatial))
+}
+
 pub struct AudioEngine {
-    _stream: OutputStream,
-    stream_handle: OutputStreamHandle,
+    output: Box<dyn OutputBackend>,
+    /// Every sink plays into this mixer, which is what the output plays.
+    mixer: Arc<DynamicMixerController<f32>>,
+    mix: Arc<Mutex<DynamicMixer<f32>>>,
     sinks: HashMap<String, Sink>,
     fading_sinks: Vec<FadingSink>,
     master_volume: f32,
     sound_volumes: HashMap<String, f32>,
+    tones: HashMap<String, Arc<ToneControl>>,
+    spatials: HashMap<String, Arc<SpatialControl>>,
+    /// Playback position and seeks of each playing file-backed sound.
+    streams: HashMap<String, Arc<StreamControl>>,
+    drifts: HashMap<String, DriftState>,
+    ramps: HashMap<String, VolumeRamp>,
+    /// Loudness normalisation gain of each sound.
+    trims: HashMap<String, f32>,
+    loudness: LoudnessConfig,
     fade_duration: Duration,
-    task_dispatcher: Sender<DecodeTask>,
+    worker_pool: WorkerPool,
 }
 
 impl AudioEngine {
-    pub fn new() -> Result<Self> {
-        let available_hosts = cpal::available_hosts();
-        log::info!("Available audio hosts: {:?}", available_hosts);
-
-        let mut device = None;
-        let mut host_name = "Default";
-
-        #[allow(unused_mut)]
-        let mut priority_hosts: Vec<(HostId, &'static str)> = Vec::new();
-
-        #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd"))]
-        {
-            #[cfg(feature = "jack")]
-            priority_hosts.push((HostId::Jack, "JACK"));
-            priority_hosts.push((HostId::Alsa, "ALSA"));
-        }
-
-        for &(host_id, name_str) in &priority_hosts {
-            if available_hosts.contains(&host_id) {
-                log::debug!("Attempting to use audio host: {:?}", host_id);
-                if let Ok(host) = cpal::host_from_id(host_id) {
-                    if let Some(d) = host.default_output_device() {
-                        log::info!(
-                            "Selected audio device from host {:?}: {}",
-                            host_id,
-                            d.name().unwrap_or_else(|_| "Unknown".to_string())
-                        );
-                        device = Some(d);
-                        host_name = name_str;
-                        break;
-                    }
-                }
-            }
-        }
-
-        let (_stream, stream_handle) = if let Some(d) = device {
-            OutputStream::try_from_device(&d)
-                .map_err(|e| anyhow::anyhow!("Failed to create output stream from device: {}", e))?
-        } else {
-            log::warn!("No preferred audio host found. Falling back to default.");
-            OutputStream::try_default().context("No audio output device available")?
-        };
+    pub fn new(config: &AudioConfig) -> Result<Self> {
+        let (mixer, mix) = dynamic_mixer::mixer::<f32>(2, config.sample_rate);
+        let mix = Arc::new(Mutex::new(mix));
+        let output = output::open(config)?;
+        output.play(Box::new(MixerTap::new(mix.clone())))?;
 
         // init bufferd source worker pool
-        let task_dispatcher = buffered::init_worker_pool();
+        let worker_pool = buffered::init_worker_pool(&config.decoder);
 
-        log::info!("Audio engine initialized successfully using {}", host_name);
+        log::info!("Audio engine initialized successfully");
 
         Ok(Self {
-            _stream,
-            stream_handle,
+            output,
+            mixer,
+            mix,
             sinks: HashMap::new(),
             fading_sinks: Vec::new(),
             master_volume: 1.0,
             sound_volumes: HashMap::new(),
-            fade_duration: Duration::from_secs(2),
-            task_dispatcher,
+            tones: HashMap::new(),
+            spatials: HashMap::new(),
+            streams: HashMap::new(),
+            drifts: HashMap::new(),
+            ramps: HashMap::new(),
+            trims: HashMap::new(),
+            loudness: config.loudness.clone(),
+            fade_duration: FADE_DURATION,
+            worker_pool,
         })
     }
 
+    /// Moves playback to another output, e.g. after picking a different device.
+    /// Playing sounds carry on where they were, at their current volume.
+    pub fn switch_output(&mut self, config: &AudioConfig) -> Result<()> {
+        // Open the new output first so a failure leaves the old one playing
+        let output = output::open(config)?;
+        // The old stream has to stop pulling from the mixer before the new one starts
+        drop(std::mem::replace(&mut self.output, output));
+        self.output
+            .play(Box::new(MixerTap::new(self.mix.clone())))?;
+        log::info!("Switched audio output");
+        Ok(())
+    }
+
     pub fn update(&mut self, dt: Duration) {
         let mut finished_indices = Vec::new();
 
@@ -168,10 +369,53 @@ impl AudioEngine {
         for i in finished_indices.into_iter().rev() {
             self.fading_sinks.swap_remove(i);
         }
+
+        for drift in self.drifts.values_mut() {
+            drift.advance(dt);
+        }
+        for (id, ramp) in self.ramps.iter_mut() {
+            ramp.elapsed += dt;
+            self.sound_volumes.insert(id.clone(), ramp.level());
+        }
+        for (id, sink) in &self.sinks {
+            if self.drifts.contains_key(id) || self.ramps.contains_key(id) {
+                sink.set_volume(self.effective_volume(id));
+            }
+        }
+        self.ramps.retain(|_, ramp| !ramp.is_done());
+    }
+
+    /// Volume a sink should be playing at: own volume, loudness trim, drift and
+    /// master combined.
+    fn effective_volume(&self, id: &str) -> f32 {
+        let volume = self.sound_volumes.get(id).copied().unwrap_or(0.0);
+        let trim = self.trims.get(id).copied().unwrap_or(1.0);
+        let drift = self.drifts.get(id).map_or(1.0, DriftState::level);
+        volume * trim * drift * self.master_volume
+    }
+
+    /// Re-reads a sound's loudness and normalisation setting, e.g. once its
+    /// measurement is done or normalisation was toggled.
+    pub fn set_loudness(&mut self, sound: &Sound) {
+        self.trims
+            .insert(sound.id.clone(), sound.loudness_gain(&self.loudness));
+        if let Some(sink) = self.sinks.get(&sound.id) {
+            sink.set_volume(self.effective_volume(&sound.id));
+        }
     }
 
-    pub fn play(&mut self, id: &str, file_path: &str, volume: f32) -> Result<()> {
-        log::info!("Attempting to play sound '{}' from '{}'", id, file_path);
+    pub fn play(&mut self, sound: &Sound, volume: f32) -> Result<()> {
+        self.play_with_fade(sound, volume, self.fade_duration)
+    }
+
+    /// Like [`play`](Self::play), with a fade-in of the given length.
+    pub fn play_with_fade(&mut self, sound: &Sound, volume: f32, fade: Duration) -> Result<()> {
+        let id = sound.id.as_str();
+        log::info!(
+            "Attempting to play sound '{}' from '{}'",
+            id,
+            sound.file_path
+        );
         if self.sinks.contains_key(id) {
             log::debug!("Sound '{}' is already playing", id);
             return Ok(());
@@ -184,21 +428,30 @@ impl AudioEngine {
             fading.sink.stop();
         }
 
-        // Clone the string to move it into the factory closure safely.
-        let path_clone = file_path.to_string();
-        let base_source = buffered::spawn_stream(&self.task_dispatcher, move || {
-            create_decoder_from_path(&path_clone)
-        })?;
-        let final_source = base_source.fade_in(self.fade_duration);
+        let tone = self.tones.entry(id.to_string()).or_default().clone();
+        let spatial = self.spatials.entry(id.to_string()).or_default().clone();
+        // Kept from a seek made before playing, so the stream starts there
+        let stream = if sound.has_file() {
+            self.streams.entry(id.to_string()).or_default().clone()
+        } else {
+            self.streams.remove(id);
+            Arc::default()
+        };
+        // A fresh seed each time, so variants and events never repeat the last run
+        let seed = fastrand::u64(..);
+        let final_source = sound_source(sound, &self.worker_pool, tone, spatial, stream, seed)?
+            .fade_in(fade.max(MIN_FADE));
 
         log::debug!("Creating sink for: {}", id);
 
-        let sink = Sink::try_new(&self.stream_handle)?;
+        let (sink, queue) = Sink::new_idle();
+        self.mixer.add(queue);
         sink.append(final_source);
 
         self.sound_volumes.insert(id.to_string(), volume);
-        let effective_vol = volume * self.master_volume;
-        sink.set_volume(effective_vol);
+        self.trims
+            .insert(id.to_string(), sound.loudness_gain(&self.loudness));
+        sink.set_volume(self.effective_volume(id));
 
         self.sinks.insert(id.to_string(), sink);
         log::info!("Started playing '{}'", id);
@@ -206,6 +459,14 @@ impl AudioEngine {
     }
 
     pub fn stop(&mut self, id: &str) {
+        self.stop_with_fade(id, self.fade_duration);
+    }
+
+    /// Like [`stop`](Self::stop), with a fade-out of the given length.
+    pub fn stop_with_fade(&mut self, id: &str, fade: Duration) {
+        self.ramps.remove(id);
+        // The next play starts from the top with its own stream
+        self.streams.remove(id);
         if let Some(sink) = self.sinks.remove(id) {
             let start_vol = sink.volume();
 
@@ -214,25 +475,100 @@ impl AudioEngine {
                 sink,
                 start_volume: start_vol,
                 elapsed: Duration::ZERO,
-                total_duration: self.fade_duration,
+                total_duration: fade,
             });
         }
     }
 
     pub fn set_volume(&mut self, id: &str, volume: f32) {
+        self.ramps.remove(id);
         self.sound_volumes.insert(id.to_string(), volume);
         if let Some(sink) = self.sinks.get(id) {
-            let effective_vol = volume * self.master_volume;
-            sink.set_volume(effective_vol);
+            sink.set_volume(self.effective_volume(id));
         }
     }
 
+    /// Glides a playing sound's volume to `volume` over `duration` instead of
+    /// jumping there. A later `set_volume` or ramp replaces this one.
+    pub fn ramp_volume(&mut self, id: &str, volume: f32, duration: Duration) {
+        if duration.is_zero() || !self.sinks.contains_key(id) {
+            self.set_volume(id, volume);
+            return;
+        }
+        let from = self.sound_volumes.get(id).copied().unwrap_or(volume);
+        self.ramps.insert(
+            id.to_string(),
+            VolumeRamp {
+                from,
+                to: volume,
+                elapsed: Duration::ZERO,
+                duration,
+            },
+        );
+    }
+
+    /// Sets the filter/EQ settings for a sound, live if it is already playing.
+    pub fn set_tone(&mut self, id: &str, tone: Tone) {
+        self.tones.entry(id.to_string()).or_default().set(tone);
+    }
+
+    /// Sets the pan and stereo width for a sound, live if it is already playing.
+    pub fn set_spatial(&mut self, id: &str, spatial: Spatial) {
+        self.spatials
+            .entry(id.to_string())
+            .or_default()
+            .set(spatial);
+    }
+
+    /// Enables, changes or (with `None`) disables volume drift for a sound.
+    pub fn set_drift(&mut self, id: &str, drift: Option<Drift>) {
+        match drift {
+            Some(drift) => {
+                // Keep the running walk when nothing changed, e.g. on session restore
+                if self.drifts.get(id).map(DriftState::drift) != Some(drift.clamped()) {
+                    self.drifts
+                        .insert(id.to_string(), DriftState::new(drift, fastrand::u64(..)));
+                }
+            }
+            None => {
+                self.drifts.remove(id);
+            }
+        }
+        if let Some(sink) = self.sinks.get(id) {
+            sink.set_volume(self.effective_volume(id));
+        }
+    }
+
+    /// How far a playing file-backed sound is into its file, and the file's
+    /// length if known.
+    pub fn position(&self, id: &str) -> Option<(Duration, Option<Duration>)> {
+        if !self.sinks.contains_key(id) {
+            return None;
+        }
+        let stream = self.streams.get(id)?;
+        Some((stream.position(), stream.total()))
+    }
+
+    /// Jumps a file-backed sound to `target`. A sound that isn't playing yet
+    /// starts there when it is played.
+    pub fn seek(&mut self, id: &str, target: Duration) {
+        let stream = self.streams.entry(id.to_string()).or_default();
+        let target = match stream.total() {
+            Some(total) => target.min(total),
+            None => target,
+        };
+        stream.seek(target);
+    }
+
+    /// The live drift multiplier of a sound, if it is drifting.
+    pub fn drift_level(&self, id: &str) -> Option<f32> {
+        self.drifts.get(id).map(DriftState::level)
+    }
+
     pub fn set_master_volume(&mut self, volume: f32) {
         self.master_volume = volume;
         for (id, sink) in &self.sinks {
-            if let Some(&vol) = self.sound_volumes.get(id) {
-                sink.set_volume(vol * self.master_volume);
-            }
+            sink.set_volume(self.effective_volume(id));
         }
     }
 
@@ -243,5 +579,7 @@ impl AudioEngine {
     pub fn stop_all(&mut self) {
         self.sinks.clear();
         self.fading_sinks.clear();
+        self.ramps.clear();
+        self.streams.clear();
     }
 }
@@ -0,0 +1,88 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Marks an empty seek slot or an unknown length.
+const NONE: u64 = u64::MAX;
+
+/// Shared between a stream's [`DecodeTask`], its [`BufferedSource`] and the
+/// engine, which reads the playback position and asks for seeks through it.
+///
+/// [`DecodeTask`]: crate::buffered::worker::DecodeTask
+/// [`BufferedSource`]: crate::buffered::source::BufferedSource
+pub struct StreamControl {
+    /// Microseconds into the file of the sample being played.
+    position_us: AtomicU64,
+    /// Length of the file being played in microseconds.
+    total_us: AtomicU64,
+    /// Seek target in microseconds, waiting for the decode task.
+    seek_us: AtomicU64,
+    /// Bumped by every seek so audio decoded before it can be dropped.
+    generation: AtomicU64,
+}
+
+impl Default for StreamControl {
+    fn default() -> Self {
+        Self {
+            position_us: AtomicU64::new(0),
+            total_us: AtomicU64::new(NONE),
+            seek_us: AtomicU64::new(NONE),
+            generation: AtomicU64::new(0),
+        }
+    }
+}
+
+impl StreamControl {
+    pub fn position(&self) -> Duration {
+        Duration::from_micros(self.position_us.load(Ordering::Relaxed))
+    }
+
+    /// Length of the file being played, if its decoder knows it.
+    pub fn total(&self) -> Option<Duration> {
+        match self.total_us.load(Ordering::Relaxed) {
+            NONE => None,
+            us => Some(Duration::from_micros(us)),
+        }
+    }
+
+    pub fn set_total(&self, total: Option<Duration>) {
+        let us = total.map_or(NONE, |total| total.as_micros() as u64);
+        self.total_us.store(us, Ordering::Relaxed);
+    }
+
+    /// Jumps to `target` once the decode task gets to it. A stream that hasn't
+    /// started yet starts there instead. The position only moves once the
+    /// decoder has managed the seek.
+    pub fn seek(&self, target: Duration) {
+        self.seek_us
+            .store(target.as_micros() as u64, Ordering::SeqCst);
+    }
+
+    pub(super) fn take_seek(&self) -> Option<Duration> {
+        match self.seek_us.swap(NONE, Ordering::SeqCst) {
+            NONE => None,
+            us => Some(Duration::from_micros(us)),
+        }
+    }
+
+    pub(super) fn seek_pending(&self) -> bool {
+        self.seek_us.load(Ordering::Relaxed) != NONE
+    }
+
+    /// Publishes a seek the decoder has made, returning the generation audio
+    /// decoded after it carries.
+    pub(super) fn finish_seek(&self, position: Duration) -> u64 {
+        // Bump first, so no chunk from before the seek publishes its position after this one
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        self.set_position(position);
+        generation
+    }
+
+    pub(super) fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    pub(super) fn set_position(&self, position: Duration) {
+        self.position_us
+            .store(position.as_micros() as u64, Ordering::Relaxed);
+    }
+}
@@ -1,7 +1,9 @@
 //! Streaming audio architecture with gapless loop prefetching.
 
+pub mod control;
 pub mod source;
 pub mod worker;
 
-pub use worker::{init_worker_pool, spawn_stream, DecodeTask};
+pub use control::StreamControl;
+pub use worker::{init_worker_pool, spawn_stream, WorkerPool};
 
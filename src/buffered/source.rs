@@ -3,7 +3,11 @@ use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-use crate::buffered::worker::DecodeTask;
+use crate::buffered::control::StreamControl;
+use crate::buffered::worker::{Chunk, DecodeTask};
+
+/// How often, in samples, the playback position is published and checked for seeks.
+const POSITION_INTERVAL: usize = 4096;
 
 ///A frontend audio source that consumes pre-decoded chunks.
 /// Employs a dual-channel memory pooling architecture to recycle buffers,
@@ -11,14 +15,14 @@ use crate::buffered::worker::DecodeTask;
 pub struct BufferedSource {
     /// The channel used to receive fully decoded audio chunks (filled buckets)
     /// from the background workers.
-    receiver: Receiver<Vec<f32>>,
+    receiver: Receiver<Chunk>,
 
     /// The channel used to send consumed, empty buffers (empty buckets) back
     /// to the background workers for zero-allocation recycling.
     recycle_tx: Sender<Vec<f32>>,
 
     /// The audio buffer currently being played/consumed by the frontend audio sink.
-    current_chunk: Option<Vec<f32>>,
+    current_chunk: Option<Chunk>,
 
     /// The current read position (index) within the `current_chunk`.
     cursor: usize,
@@ -30,6 +34,10 @@ pub struct BufferedSource {
     /// The sample rate of the audio (e.g., 44100, 48000) extracted from the original source.
     sample_rate: u32,
 
+    /// Shared with the decode task and the engine, carrying seeks one way and
+    /// the playback position the other.
+    control: Arc<StreamControl>,
+
     /// The "waiting room" for the background task.
     /// If the prefetch buffer reaches its capacity, the worker will park the `DecodeTask`
     /// here to prevent thread blocking and free up the worker for other streams.
@@ -43,19 +51,40 @@ pub struct BufferedSource {
 
 impl BufferedSource {
     pub fn new(
-        receiver: Receiver<Vec<f32>>, 
+        receiver: Receiver<Chunk>, 
         recycle_tx: Sender<Vec<f32>>,
         channels: u16,
         sample_rate: u32,
+        control: Arc<StreamControl>,
         suspended_task: Arc<Mutex<Option<DecodeTask>>>,
         global_task_tx: Sender<DecodeTask>,
     ) -> Self {
         Self {
             receiver, recycle_tx, current_chunk: None,
-            cursor: 0, channels, sample_rate,
+            cursor: 0, channels, sample_rate, control,
             suspended_task, global_task_tx,
         }
     }
+
+    /// Hands a buffer back to the worker, waking the task if it was waiting for one.
+    fn recycle(&self, buffer: Vec<f32>) {
+        let _ = self.recycle_tx.send(buffer);
+        self.wake();
+    }
+
+    fn wake(&self) {
+        let mut suspended = self.suspended_task.lock().unwrap();
+        if let Some(task) = suspended.take() {
+            log::trace!("Waking up suspended task!");
+            let _ = self.global_task_tx.send(task);
+        }
+    }
+
+    fn publish_position(&self, chunk: &Chunk) {
+        let frame = chunk.start_frame + (self.cursor / self.channels.max(1) as usize) as u64;
+        let secs = frame as f64 / self.sample_rate.max(1) as f64;
+        self.control.set_position(Duration::from_secs_f64(secs));
+    }
 }
 
 impl Iterator for BufferedSource {
@@ -63,28 +92,32 @@ impl Iterator for BufferedSource {
 
     #[inline(always)]
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(chunk) = &self.current_chunk {
-            if self.cursor < chunk.len() {
-                let sample = chunk[self.cursor];
-                self.cursor += 1;
-                return Some(sample);
-            } else {
-                if let Some(old_chunk) = self.current_chunk.take() {
-                    let _ = self.recycle_tx.send(old_chunk);
-                    let mut suspended = self.suspended_task.lock().unwrap();
-                    if let Some(task) = suspended.take() {
-                        log::trace!("Waking up suspended task!");
-                        let _ = self.global_task_tx.send(task);
+        loop {
+            if let Some(chunk) = &self.current_chunk {
+                if self.cursor.is_multiple_of(POSITION_INTERVAL) {
+                    if self.control.seek_pending() {
+                        // The task may be parked behind a full prefetch queue
+                        self.wake();
                     }
+                    if chunk.generation < self.control.generation() {
+                        // Decoded before a seek, drop the rest of it
+                        self.cursor = chunk.samples.len();
+                    } else {
+                        self.publish_position(chunk);
+                    }
+                }
+                if self.cursor < chunk.samples.len() {
+                    let sample = chunk.samples[self.cursor];
+                    self.cursor += 1;
+                    return Some(sample);
+                }
+                if let Some(old_chunk) = self.current_chunk.take() {
+                    self.recycle(old_chunk.samples);
                 }
             }
-        }
-        if let Ok(next_chunk) = self.receiver.recv() {
+            let next_chunk = self.receiver.recv().ok()?;
             self.current_chunk = Some(next_chunk);
             self.cursor = 0;
-            self.next()
-        } else {
-            None
         }
     }
 }
@@ -1,19 +1,34 @@
 use anyhow::Result;
 use rodio::Source;
+use std::collections::VecDeque;
+use std::f32::consts::FRAC_PI_2;
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Mutex, Weak};
 use std::thread;
+use std::time::Duration;
 
+use crate::buffered::control::StreamControl;
 use crate::buffered::source::BufferedSource;
 
-const WORKER_COUNT: usize = 2;
-const PREFETCH_COUNT: usize = 3;
+use crate::config::DecoderConfig;
 
-pub fn init_worker_pool() -> Sender<DecodeTask> {
+/// The priming chunk and the one being decoded each need a buffer.
+const MIN_PREFETCH_COUNT: usize = 2;
+const MIN_CHUNK_SAMPLES: usize = 1024;
+
+/// Handle to the decode threads, carrying the buffering settings for new streams.
+#[derive(Clone)]
+pub struct WorkerPool {
+    dispatcher: Sender<DecodeTask>,
+    prefetch_count: usize,
+    chunk_ms: u32,
+}
+
+pub fn init_worker_pool(config: &DecoderConfig) -> WorkerPool {
     let (task_tx, task_rx) = channel::<DecodeTask>();
     let shared_rx = Arc::new(Mutex::new(task_rx));
 
-    for i in 0..WORKER_COUNT {
+    for i in 0..config.workers.max(1) {
         let worker_rx = Arc::clone(&shared_rx);
         thread::Builder::new()
             .name(format!("AudioWorker-{}", i))
@@ -31,124 +46,283 @@ pub fn init_worker_pool() -> Sender<DecodeTask> {
             }).expect("Failed to spawn audio worker thread");
     }
 
-    task_tx
+    WorkerPool {
+        dispatcher: task_tx,
+        prefetch_count: config.prefetch_chunks.max(MIN_PREFETCH_COUNT),
+        chunk_ms: config.chunk_ms,
+    }
 }
 
+/// Streams the sources made by `decoder_factory`, starting a new one each time
+/// the previous one ends. Each pass starts `loop_start` into the file, which
+/// the factory's sources are expected to have skipped. A non-zero `crossfade`
+/// blends that much of each pass's tail into the head of the next instead of
+/// butting them together.
 pub fn spawn_stream<F>(
-    dispatcher: &Sender<DecodeTask>,
-    decoder_factory: F,
+    pool: &WorkerPool,
+    control: Arc<StreamControl>,
+    loop_start: Duration,
+    crossfade: Duration,
+    mut decoder_factory: F,
 ) -> Result<BufferedSource>
 where
-    F: Fn() -> Result<Box<dyn Source<Item = f32> + Send>> + Send + 'static,
+    F: FnMut() -> Result<Box<dyn Source<Item = f32> + Send>> + Send + 'static,
 {
-    let mut initial_decoder = decoder_factory()?;
+    let initial_decoder = decoder_factory()?;
     
     // Extract metadata from the opened file.
     let channels = initial_decoder.channels();
     let sample_rate = initial_decoder.sample_rate();
 
     // Calculate chunk sizes based on actual file properties.
-    // chunk_size = 1 second of audio.
-    let chunk_size = std::cmp::max((sample_rate as usize) * (channels as usize), 1024);
+    // chunk_size = `chunk_ms` of audio.
+    let samples_per_sec = (sample_rate as usize) * (channels as usize);
+    let chunk_size = std::cmp::max(
+        samples_per_sec * pool.chunk_ms as usize / 1000,
+        MIN_CHUNK_SAMPLES,
+    );
 
-    // prime_chunk_size = 100 milliseconds of audio for instant startup.
+    let crossfade_len =
+        (crossfade.as_secs_f64() * sample_rate as f64) as usize * (channels as usize);
+
+    // prime_chunk_size = a tenth of a chunk for instant startup.
     let prime_chunk_size = chunk_size / 10;
 
-    let (reply_tx, reply_rx) = channel::<Vec<f32>>();
+    let (reply_tx, reply_rx) = channel::<Chunk>();
     let (recycle_tx, recycle_rx) = channel::<Vec<f32>>();
     let suspended_task = Arc::new(Mutex::new(None));
 
-    for _ in 0..PREFETCH_COUNT {
+    for _ in 0..pool.prefetch_count {
         recycle_tx.send(Vec::with_capacity(chunk_size)).unwrap();
     }
 
-    let mut prime_chunk = recycle_rx.recv().unwrap();
-    prime_chunk.extend(initial_decoder.by_ref().take(prime_chunk_size));
-    let _ = reply_tx.send(prime_chunk);
-
-    let task = DecodeTask {
+    let pass_start = samples_at(loop_start, sample_rate, channels);
+    let mut task = DecodeTask {
         decoder: initial_decoder,
         factory: Box::new(decoder_factory),
         reply_tx,
         recycle_rx,
         chunk_size, // Store the calculated size for background execution
+        channels,
+        sample_rate,
+        crossfade_len,
+        tail: VecDeque::with_capacity(crossfade_len + 1),
+        control: Arc::clone(&control),
+        generation: control.generation(),
+        pass_start,
+        position: pass_start,
+        ended: false,
         suspended_task: Arc::downgrade(&suspended_task),
-        global_task_tx: dispatcher.clone(),
+        global_task_tx: pool.dispatcher.clone(),
         next_buffer: None,
     };
 
-    dispatcher.send(task).unwrap();
+    // Resuming part way through
+    if let Some(target) = control.take_seek() {
+        task.seek(target);
+    }
+
+    let mut prime_chunk = task.recycle_rx.recv().unwrap();
+    let start = task.position;
+    task.pull(&mut prime_chunk, prime_chunk_size);
+    task.position += prime_chunk.len() as u64;
+    let _ = task.reply_tx.send(Chunk {
+        samples: prime_chunk,
+        start_frame: start / channels.max(1) as u64,
+        generation: task.generation,
+    });
+
+    pool.dispatcher.send(task).unwrap();
 
     Ok(BufferedSource::new(
         reply_rx,
         recycle_tx,
         channels,
         sample_rate,
+        control,
         suspended_task,
-        dispatcher.clone(),
+        pool.dispatcher.clone(),
     ))
 }
 
+/// Interleaved samples `at` into a stream.
+fn samples_at(at: Duration, sample_rate: u32, channels: u16) -> u64 {
+    (at.as_secs_f64() * sample_rate as f64) as u64 * channels as u64
+}
+
+/// Decoded audio on its way to the frontend.
+pub struct Chunk {
+    pub samples: Vec<f32>,
+    /// Frames into the file of the first sample.
+    pub start_frame: u64,
+    /// The seek the samples were decoded after, see [`StreamControl::seek`].
+    pub generation: u64,
+}
+
 pub struct DecodeTask {
     pub decoder: Box<dyn Source<Item = f32> + Send>,
-    pub factory: Box<dyn Fn() -> Result<Box<dyn Source<Item = f32> + Send>> + Send>,
-    pub reply_tx: Sender<Vec<f32>>,
+    pub factory: Box<dyn FnMut() -> Result<Box<dyn Source<Item = f32> + Send>> + Send>,
+    pub reply_tx: Sender<Chunk>,
     pub recycle_rx: Receiver<Vec<f32>>,
     pub chunk_size: usize,
+    pub channels: u16,
+    pub sample_rate: u32,
+    /// Samples of each pass blended into the next, zero for a hard loop.
+    pub crossfade_len: usize,
+    /// The most recently decoded `crossfade_len` samples, held back until we know
+    /// whether they are the tail of the pass.
+    pub tail: VecDeque<f32>,
+    pub control: Arc<StreamControl>,
+    pub generation: u64,
+    /// Samples into the file where each pass starts.
+    pub pass_start: u64,
+    /// Samples into the file of the next sample to go into a chunk.
+    pub position: u64,
+    /// The decoder has run dry, the next chunk starts a new pass.
+    pub ended: bool,
     pub suspended_task: Weak<Mutex<Option<DecodeTask>>>,
     pub global_task_tx: Sender<DecodeTask>,
     pub next_buffer: Option<Vec<f32>>,
 }
 
 impl DecodeTask {
-    pub fn process_chunk(mut self) {
-        let mut chunk = if let Some(buf) = self.next_buffer.take() {
-            buf
-        } else {
-            self.recycle_rx
-                .try_recv()
-                .expect("State machine violation: Task woke up but no buffer is available!")
-        };
-        
-        chunk.clear(); 
-        
-        let decode_result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-            chunk.extend(self.decoder.by_ref().take(self.chunk_size));
+    /// Moves up to `count` decoded samples into `chunk`, stopping early if the
+    /// decoder runs dry. When crossfading, output lags the decoder by the tail.
+    fn pull(&mut self, chunk: &mut Vec<f32>, count: usize) {
+        if self.crossfade_len == 0 {
+            chunk.extend(self.decoder.by_ref().take(count));
+            return;
+        }
+        let target = chunk.len() + count;
+        while chunk.len() < target {
+            let Some(sample) = self.decoder.next() else {
+                break;
+            };
+            self.tail.push_back(sample);
+            if self.tail.len() > self.crossfade_len {
+                chunk.extend(self.tail.pop_front());
+            }
+        }
+    }
+
+    /// Fades the held back tail of the finished pass out while fading the head
+    /// of the new decoder in. Equal power, as ambience rarely correlates.
+    fn blend_tail(&mut self) {
+        let channels = self.channels.max(1) as usize;
+        let frames = (self.tail.len() / channels).max(1) as f32;
+        for (i, sample) in self.tail.iter_mut().enumerate() {
+            let t = ((i / channels) as f32 + 0.5) / frames * FRAC_PI_2;
+            let head = self.decoder.next().unwrap_or(0.0);
+            *sample = *sample * t.cos() + head * t.sin();
+        }
+    }
+
+    /// Moves the decoder to `target`, dropping whatever was decoded before. A
+    /// failed seek leaves the stream playing on where it was.
+    fn seek(&mut self, target: Duration) {
+        let pass_start = Duration::from_secs_f64(
+            self.pass_start as f64 / self.channels.max(1) as f64 / self.sample_rate.max(1) as f64,
+        );
+        let target = target.max(pass_start);
+        let seek_result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            self.decoder.try_seek(target)
         }));
+        match seek_result {
+            Ok(Ok(())) => {
+                self.position = samples_at(target, self.sample_rate, self.channels);
+                self.tail.clear();
+                self.ended = false;
+                self.generation = self.control.finish_seek(target);
+            }
+            Ok(Err(e)) => log::warn!("Cannot seek this sound: {}", e),
+            Err(_) => log::error!("Decoder panicked while seeking."),
+        }
+    }
 
-        if decode_result.is_err() {
-            log::error!("Decoder panicked mid-stream. Terminating.");
-            return;
+    /// Starts the next pass. Returns false if the stream has to end.
+    fn restart(&mut self) -> bool {
+        match (self.factory)() {
+            Ok(new_decoder) => {
+                self.decoder = new_decoder;
+                self.position = self.pass_start;
+                self.ended = false;
+                let blend = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    self.blend_tail();
+                }));
+                if blend.is_err() {
+                    log::error!("Decoder panicked during gapless loop. Terminating.");
+                    return false;
+                }
+                true
+            }
+            Err(e) => {
+                log::error!("Gapless loop factory failed: {}. Terminating stream.", e);
+                false
+            }
         }
+    }
 
-        if chunk.len() < self.chunk_size {
-            match (self.factory)() {
-                Ok(new_decoder) => {
-                    self.decoder = new_decoder;
-                    let remaining = self.chunk_size - chunk.len();
-                    let loop_decode = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                        chunk.extend(self.decoder.by_ref().take(remaining));
-                    }));
-                    if loop_decode.is_err() {
-                        log::error!("Decoder panicked during gapless loop. Terminating.");
-                        return;
-                    }
+    pub fn process_chunk(mut self) {
+        // Before waiting on a buffer, the frontend wakes us early for seeks
+        if let Some(target) = self.control.take_seek() {
+            self.seek(target);
+        }
+
+        let mut chunk = match self.next_buffer.take() {
+            Some(buf) => buf,
+            None => match self.recycle_rx.try_recv() {
+                Ok(buf) => buf,
+                Err(_) => return self.park(),
+            },
+        };
+        chunk.clear();
+
+        // A chunk never spans two passes, so its start says where all of it is.
+        // Only an empty pass makes us go round twice.
+        let mut restarted = false;
+        let start = loop {
+            if self.ended {
+                if restarted {
+                    log::error!("Sound produced no audio after a loop. Terminating.");
+                    return;
                 }
-                Err(e) => {
-                    log::error!("Gapless loop factory failed: {}. Terminating stream.", e);
-                    if !chunk.is_empty() {
-                        let _ = self.reply_tx.send(chunk);
-                    }
+                if !self.restart() {
                     return;
                 }
+                restarted = true;
             }
-        }
 
-        if chunk.is_empty() { return; }
+            let start = self.position;
+            let chunk_size = self.chunk_size;
+            let decode_result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                self.pull(&mut chunk, chunk_size);
+            }));
+            if decode_result.is_err() {
+                log::error!("Decoder panicked mid-stream. Terminating.");
+                return;
+            }
+            self.position += chunk.len() as u64;
+            self.ended = chunk.len() < self.chunk_size;
+            if !chunk.is_empty() {
+                break start;
+            }
+        };
+
+        let chunk = Chunk {
+            samples: chunk,
+            start_frame: start / self.channels.max(1) as u64,
+            generation: self.generation,
+        };
         if self.reply_tx.send(chunk).is_err() {
             return;
         }
 
+        self.park();
+    }
+
+    /// Queues the task again if a buffer is free, otherwise leaves it for the
+    /// frontend to wake when it hands one back.
+    fn park(mut self) {
         if let Some(suspended_arc) = self.suspended_task.upgrade() {
             let mut suspended = suspended_arc.lock().unwrap();
             match self.recycle_rx.try_recv() {
@@ -165,4 +339,3 @@ impl DecodeTask {
         }
     }
 }
-
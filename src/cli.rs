@@ -0,0 +1,483 @@
+//! Non-interactive subcommands for scripts, cron jobs and window manager bindings.
+//!
+//! Commands that change playback are forwarded to a running `tanin daemon` when
+//! one is listening, otherwise they play in the foreground until interrupted.
+
+use crate::app::download::{analyze_download, download_sound, yt_dlp_available};
+use crate::app::DownloadEvent;
+use crate::audio::AudioEngine;
+use crate::config::Config;
+use crate::daemon::protocol::Request;
+use crate::daemon::{self, DaemonClient};
+use crate::duration::parse_duration;
+use crate::loudness;
+use crate::output::{self, OutputArgs};
+use crate::presets::{Preset, PresetsConfig};
+use crate::render;
+use crate::session::Session;
+use crate::share::SharedPreset;
+use crate::static_data::{add_custom_sound, load_all_sounds, Sound};
+use anyhow::{anyhow, Result};
+use clap::{Subcommand, ValueEnum};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const TICK_RATE: Duration = Duration::from_millis(30);
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run headless, controlled through a Unix socket in the runtime directory
+    Daemon,
+    /// Play one or more sounds by id or name
+    Play {
+        #[arg(required = true)]
+        sounds: Vec<String>,
+        /// Volume between 0.0 and 1.0 (defaults to the last used volume)
+        #[arg(short, long)]
+        volume: Option<f32>,
+        /// Stop after this long when playing in the foreground, e.g. `30m`
+        #[arg(long, value_parser = parse_duration)]
+        duration: Option<Duration>,
+    },
+    /// Stop sounds on the running daemon (all sounds if none are given)
+    Stop { sounds: Vec<String> },
+    /// Work with saved presets
+    #[command(subcommand)]
+    Preset(PresetCommand),
+    /// List available sounds or presets
+    List {
+        #[arg(value_enum)]
+        target: ListTarget,
+        /// Print machine readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Render a mix to a .wav, .flac or .opus file instead of playing it
+    Render {
+        /// Sounds to mix, by id or name, at their last used settings
+        #[arg(conflicts_with = "preset", required_unless_present = "preset")]
+        sounds: Vec<String>,
+        /// Render a saved preset
+        #[arg(short, long)]
+        preset: Option<String>,
+        /// Length of the rendered file, e.g. `1h`
+        #[arg(short, long, value_parser = parse_duration)]
+        duration: Duration,
+        /// Output file; the extension picks the format
+        #[arg(short, long)]
+        out: PathBuf,
+    },
+    /// Download a sound and add it to the custom sounds.toml
+    Add {
+        name: String,
+        category: String,
+        url: String,
+        #[arg(long, default_value = "🎵")]
+        icon: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PresetCommand {
+    /// Replace the current mix with a saved preset
+    Load {
+        name: String,
+        /// Stop after this long when playing in the foreground, e.g. `30m`
+        #[arg(long, value_parser = parse_duration)]
+        duration: Option<Duration>,
+    },
+    /// Print a preset as a shareable code, or write it to a file
+    Export {
+        name: String,
+        /// Write a preset file instead of printing a code
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Add a preset from a file or code made with `export`
+    Import {
+        /// Path of a preset file, or a `tanin:` code
+        source: String,
+        /// Download sounds the preset uses that are missing here
+        #[arg(long)]
+        download: bool,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ListTarget {
+    Sounds,
+    Presets,
+}
+
+pub fn run(command: Command, output: &OutputArgs) -> Result<()> {
+    match command {
+        Command::Daemon => daemon::run(output),
+        Command::Play {
+            sounds,
+            volume,
+            duration,
+        } => play(&sounds, volume, duration, output),
+        Command::Stop { sounds } => stop(&sounds),
+        Command::Preset(PresetCommand::Load { name, duration }) => {
+            load_preset(&name, duration, output)
+        }
+        Command::Preset(PresetCommand::Export { name, out }) => {
+            export_preset(&name, out.as_deref())
+        }
+        Command::Preset(PresetCommand::Import { source, download }) => {
+            import_preset(&source, download)
+        }
+        Command::List { target, json } => list(target, json),
+        Command::Render {
+            sounds,
+            preset,
+            duration,
+            out,
+        } => render(&sounds, preset.as_deref(), duration, &out),
+        Command::Add {
+            name,
+            category,
+            url,
+            icon,
+        } => add(&name, &category, &url, &icon),
+    }
+}
+
+/// Sounds with their cached loudness; files not measured yet play untrimmed.
+fn load_sounds() -> Result<Vec<Sound>> {
+    let config = Config::load()?;
+    let mut sounds = load_all_sounds(config.general.enable_bundled_sounds);
+    loudness::annotate(&mut sounds, &config);
+    Ok(sounds)
+}
+
+fn find_sound<'a>(sounds: &'a [Sound], query: &str) -> Result<&'a Sound> {
+    sounds
+        .iter()
+        .find(|s| s.id == query || s.name.eq_ignore_ascii_case(query))
+        .ok_or_else(|| anyhow!("Unknown sound '{}'. Try `tanin list sounds`.", query))
+}
+
+fn play(
+    queries: &[String],
+    volume: Option<f32>,
+    duration: Option<Duration>,
+    output: &OutputArgs,
+) -> Result<()> {
+    let session = Session::load()?;
+    let selected = session_sounds(queries, volume, &session)?;
+
+    if let Ok(mut client) = DaemonClient::connect() {
+        for sound in &selected {
+            client.set_tone(&sound.id, sound.tone);
+            client.set_spatial(&sound.id, sound.spatial);
+            client.set_drift(&sound.id, sound.drift);
+            client.play(&sound.id, sound.volume_linear)?;
+        }
+        return Ok(());
+    }
+
+    play_foreground(&selected, session.global_volume, duration, output)
+}
+
+/// Resolves sound queries, with their settings from the last session.
+fn session_sounds(
+    queries: &[String],
+    volume: Option<f32>,
+    session: &Session,
+) -> Result<Vec<Sound>> {
+    let all_sounds = load_sounds()?;
+    let mut selected = Vec::new();
+    for query in queries {
+        let mut sound = find_sound(&all_sounds, query)?.clone();
+        sound.volume_linear = volume
+            .or_else(|| session.sounds.get(&sound.id).map(|s| s.volume))
+            .unwrap_or(sound.volume_linear)
+            .clamp(0.0, 1.0);
+        if let Some(state) = session.sounds.get(&sound.id) {
+            sound.tone = state.tone;
+            sound.spatial = state.spatial;
+            sound.drift = state.drift;
+        }
+        selected.push(sound);
+    }
+    Ok(selected)
+}
+
+fn stop(queries: &[String]) -> Result<()> {
+    let mut client = DaemonClient::connect()?;
+    if queries.is_empty() {
+        client.request(&Request::StopAll)?;
+        return Ok(());
+    }
+
+    let all_sounds = load_sounds()?;
+    for query in queries {
+        let id = find_sound(&all_sounds, query)?.id.clone();
+        client.request(&Request::Stop { id })?;
+    }
+    Ok(())
+}
+
+fn load_preset(name: &str, duration: Option<Duration>, output: &OutputArgs) -> Result<()> {
+    if let Ok(mut client) = DaemonClient::connect() {
+        client.request(&Request::LoadPreset(name.to_string()))?;
+        return Ok(());
+    }
+
+    let (sounds, master) = preset_sounds(name)?;
+    PresetsConfig::record_use(name)?;
+    play_foreground(
+        &sounds,
+        master.unwrap_or(Session::load()?.global_volume),
+        duration,
+        output,
+    )
+}
+
+fn find_preset<'a>(presets: &'a PresetsConfig, name: &str) -> Result<&'a Preset> {
+    presets
+        .presets
+        .iter()
+        .find(|p| p.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| anyhow!("Unknown preset '{}'. Try `tanin list presets`.", name))
+}
+
+/// The sounds of a saved preset, with the preset's settings applied, and its
+/// master volume.
+fn preset_sounds(name: &str) -> Result<(Vec<Sound>, Option<f32>)> {
+    let presets = PresetsConfig::load()?;
+    let preset = find_preset(&presets, name)?;
+
+    let sounds = load_sounds()?
+        .into_iter()
+        .filter_map(|mut sound| {
+            preset.sounds.get(&sound.id)?.apply_to(&mut sound);
+            Some(sound)
+        })
+        .collect();
+    Ok((sounds, preset.master_volume))
+}
+
+fn export_preset(name: &str, out: Option<&Path>) -> Result<()> {
+    let presets = PresetsConfig::load()?;
+    let shared = SharedPreset::new(find_preset(&presets, name)?, &load_sounds()?);
+    match out {
+        Some(path) => std::fs::write(path, shared.to_toml()?)?,
+        None => println!("{}", shared.to_code()?),
+    }
+    Ok(())
+}
+
+fn import_preset(source: &str, download: bool) -> Result<()> {
+    let shared = SharedPreset::read(source)?;
+    let missing = shared.missing(&load_sounds()?);
+    let mut presets = PresetsConfig::load()?;
+    let name = shared.add_to(&mut presets);
+    presets.save()?;
+    eprintln!("Imported preset '{}'", name);
+
+    for id in &missing.unavailable {
+        eprintln!("Missing '{}', which has no download source", id);
+    }
+    if !download {
+        for source in &missing.downloadable {
+            eprintln!("Missing '{}', download it with --download", source.id);
+        }
+        return Ok(());
+    }
+    let yt_dlp = yt_dlp_available();
+    for source in missing.downloadable {
+        eprintln!("Downloading '{}'", source.id);
+        download_and_add(
+            source.download_name(),
+            source.category,
+            source.icon,
+            source.url,
+            source.file,
+            yt_dlp,
+        )?;
+    }
+    Ok(())
+}
+
+fn play_foreground(
+    sounds: &[Sound],
+    master: f32,
+    duration: Option<Duration>,
+    output: &OutputArgs,
+) -> Result<()> {
+    let mut engine = AudioEngine::new(&output.apply(&Config::load()?.audio))?;
+    engine.set_master_volume(master);
+    for sound in sounds {
+        engine.set_tone(&sound.id, sound.tone);
+        engine.set_spatial(&sound.id, sound.spatial);
+        engine.set_drift(&sound.id, sound.drift);
+        engine.play(sound, sound.volume_linear)?;
+    }
+
+    let names: Vec<&str> = sounds.iter().map(|s| s.name.as_str()).collect();
+    eprintln!("Playing {} (Ctrl-C to stop)", names.join(", "));
+
+    let start = Instant::now();
+    let mut last_tick = start;
+    while duration.is_none_or(|d| start.elapsed() < d) {
+        thread::sleep(TICK_RATE);
+        let now = Instant::now();
+        engine.update(now.duration_since(last_tick));
+        last_tick = now;
+    }
+    Ok(())
+}
+
+fn render(queries: &[String], preset: Option<&str>, duration: Duration, out: &Path) -> Result<()> {
+    let session = Session::load()?;
+    let (mut sounds, master) = match preset {
+        Some(name) => preset_sounds(name)?,
+        None => (session_sounds(queries, None, &session)?, None),
+    };
+    if sounds.is_empty() {
+        return Err(anyhow!("Nothing to render"));
+    }
+    // Measure now so the file is trimmed like playback will be once measured
+    for sound in &mut sounds {
+        if sound.has_file() && sound.loudness.is_none() {
+            sound.loudness = loudness::measure_cached(&sound.file_path);
+        }
+    }
+
+    let config = Config::load()?;
+    let mut shown = None;
+    render::render(
+        &sounds,
+        master.unwrap_or(session.global_volume),
+        &config.audio.loudness,
+        duration,
+        out,
+        |done| {
+            let percent = (done * 100.0) as u32;
+            if shown != Some(percent) {
+                shown = Some(percent);
+                eprint!("\rRendering {}: {}%", out.display(), percent);
+            }
+        },
+    )?;
+    eprintln!();
+    Ok(())
+}
+
+fn list(target: ListTarget, json: bool) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    match target {
+        ListTarget::Sounds => {
+            let sounds = load_sounds()?;
+            if json {
+                serde_json::to_writer_pretty(&mut stdout, &sounds)?;
+                writeln!(stdout)?;
+            } else {
+                for sound in &sounds {
+                    writeln!(stdout, "{}\t{}\t{}", sound.id, sound.category, sound.name)?;
+                }
+            }
+        }
+        ListTarget::Presets => {
+            let presets = PresetsConfig::load()?;
+            if json {
+                serde_json::to_writer_pretty(&mut stdout, &presets.presets)?;
+                writeln!(stdout)?;
+            } else {
+                for preset in &presets.presets {
+                    writeln!(stdout, "{}\t({} sounds)", preset.name, preset.sounds.len())?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints every output device as `host<TAB>name`, usable as `audio.host` and
+/// `audio.device` in config.toml.
+pub fn list_devices() -> Result<()> {
+    let audio = Config::load()?.audio;
+    let mut stdout = std::io::stdout().lock();
+    let devices = output::list_devices();
+    if devices.is_empty() {
+        eprintln!("No output devices found");
+    }
+    for device in &devices {
+        let mut notes = Vec::new();
+        if device.is_default {
+            notes.push("default");
+        }
+        if device.is_chosen(&audio) {
+            notes.push("selected");
+        }
+        if notes.is_empty() {
+            writeln!(stdout, "{}\t{}", device.host, device.name)?;
+        } else {
+            writeln!(
+                stdout,
+                "{}\t{}\t({})",
+                device.host,
+                device.name,
+                notes.join(", ")
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn add(name: &str, category: &str, url: &str, icon: &str) -> Result<()> {
+    if name.trim().is_empty() || category.trim().is_empty() || url.trim().is_empty() {
+        return Err(anyhow!("Name, category and URL must not be empty"));
+    }
+
+    let path = download_and_add(
+        name.to_string(),
+        category.to_string(),
+        icon.to_string(),
+        url.trim().to_string(),
+        None,
+        yt_dlp_available(),
+    )?;
+    println!("{}", path);
+    Ok(())
+}
+
+/// Downloads a sound with progress on stderr and adds it to sounds.toml,
+/// returning the path of the file.
+fn download_and_add(
+    name: String,
+    category: String,
+    icon: String,
+    url: String,
+    target_filename: Option<String>,
+    yt_dlp: bool,
+) -> Result<String> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || download_sound(name, category, icon, url, target_filename, yt_dlp, tx));
+
+    for event in rx {
+        match event {
+            DownloadEvent::Progress(p) => eprint!("\rDownloading {:.1}%", p),
+            DownloadEvent::Success(name, category, path, icon, url) => {
+                eprintln!();
+                eprintln!("Finding loop points...");
+                let loop_points = analyze_download(&path);
+                add_custom_sound(&name, &category, &path, &icon, Some(&url), loop_points)?;
+                return Ok(path);
+            }
+            DownloadEvent::Error(e) => {
+                eprintln!();
+                return Err(anyhow!(e));
+            }
+            // Only sent by the TUI's own analysis task
+            DownloadEvent::Analyzed(_) => {}
+        }
+    }
+
+    Err(anyhow!("Download stopped without a result"))
+}
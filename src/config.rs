@@ -1,9 +1,12 @@
+use crate::dsp::Drift;
+use crate::output::BackendKind;
 use anyhow::Result;
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -11,6 +14,14 @@ pub struct Config {
     pub general: GeneralConfig,
     pub audio: AudioConfig,
     pub sounds: HashMap<String, SoundConfig>,
+    #[serde(default)]
+    pub sleep: SleepConfig,
+    #[serde(default)]
+    pub drift: DriftConfig,
+    #[serde(default)]
+    pub transition: TransitionConfig,
+    #[serde(default)]
+    pub pomodoro: PomodoroConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,14 +45,203 @@ impl Default for GeneralConfig {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioConfig {
+    /// Rate the mix and the output stream run at; sounds at other rates are resampled.
     pub sample_rate: u32,
+    /// Output buffer length in milliseconds, `0` for the device default.
+    /// Smaller is more responsive, larger survives a busy CPU.
     pub buffer_size: u32,
+    /// `cpal` for the sound card, `null` to discard audio or `wav` to record it.
+    #[serde(default)]
+    pub backend: BackendKind,
+    /// File written by the `wav` backend, `tanin.wav` if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wav_file: Option<PathBuf>,
+    /// cpal host of the chosen output device, any host if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// Name of the chosen output device; unset picks the default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    #[serde(default)]
+    pub decoder: DecoderConfig,
+    #[serde(default)]
+    pub loudness: LoudnessConfig,
+}
+
+/// Background decoding of sound files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecoderConfig {
+    /// Threads decoding sound files.
+    pub workers: usize,
+    /// Decoded chunks kept ready per playing file, at least 2.
+    pub prefetch_chunks: usize,
+    /// Length of a decoded chunk in milliseconds.
+    pub chunk_ms: u32,
+}
+
+impl Default for DecoderConfig {
+    fn default() -> Self {
+        Self {
+            workers: 2,
+            prefetch_chunks: 3,
+            chunk_ms: 1000,
+        }
+    }
+}
+
+/// Gain trims that bring every sound file to a common loudness.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoudnessConfig {
+    pub enabled: bool,
+    /// Reference level in LUFS that sounds are trimmed to.
+    pub target_lufs: f32,
+    /// Largest boost given to quiet files, so their noise floor stays down.
+    pub max_boost_db: f32,
+}
+
+impl Default for LoudnessConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            target_lufs: -27.0,
+            max_boost_db: 12.0,
+        }
+    }
+}
+
+impl LoudnessConfig {
+    /// Trim in dB for a file measured at `lufs`.
+    pub fn trim_db(&self, lufs: f32) -> f32 {
+        (self.target_lufs - lufs).min(self.max_boost_db)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SleepConfig {
+    /// Length of the final master volume fade, in seconds.
+    pub fade_out_secs: u64,
+    /// Exit Tanin once the timer has stopped every sound.
+    pub quit_on_finish: bool,
+    /// Durations (in minutes) cycled through by the sleep timer key.
+    pub steps_minutes: Vec<u64>,
+}
+
+impl Default for SleepConfig {
+    fn default() -> Self {
+        Self {
+            fade_out_secs: 120,
+            quit_on_finish: false,
+            steps_minutes: vec![15, 30, 45, 60, 90],
+        }
+    }
+}
+
+/// Bounds used when drift is switched on from the TUI.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DriftConfig {
+    /// Lowest level, as a fraction of the sound's own volume.
+    pub min: f32,
+    /// Highest level, as a fraction of the sound's own volume.
+    pub max: f32,
+    /// Seconds per wander step or LFO cycle.
+    pub period_secs: f32,
+}
+
+impl Default for DriftConfig {
+    fn default() -> Self {
+        Self {
+            min: Drift::DEFAULT_MIN,
+            max: Drift::DEFAULT_MAX,
+            period_secs: Drift::DEFAULT_PERIOD_SECS,
+        }
+    }
+}
+
+/// How switching presets blends the old mix into the new one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransitionConfig {
+    /// Length of the crossfade in seconds; 0 switches instantly.
+    pub crossfade_secs: f32,
+}
+
+impl Default for TransitionConfig {
+    fn default() -> Self {
+        Self {
+            crossfade_secs: 3.0,
+        }
+    }
+}
+
+impl TransitionConfig {
+    pub fn crossfade(&self) -> Duration {
+        Duration::try_from_secs_f32(self.crossfade_secs).unwrap_or_default()
+    }
+}
+
+/// Focus sessions started with `p`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PomodoroConfig {
+    pub work_minutes: u64,
+    pub short_break_minutes: u64,
+    pub long_break_minutes: u64,
+    /// Work phases before a long break instead of a short one.
+    pub cycles_before_long_break: u32,
+    /// Preset loaded when each phase begins, or `"mute"` to fade everything
+    /// out. Unset leaves the mix as it is.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub work_preset: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub short_break_preset: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub long_break_preset: Option<String>,
+    /// Ring the terminal bell when a phase begins.
+    pub bell: bool,
+    /// Show a desktop notification (via `notify-send`) when a phase begins.
+    pub notify: bool,
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        Self {
+            work_minutes: 25,
+            short_break_minutes: 5,
+            long_break_minutes: 15,
+            cycles_before_long_break: 4,
+            work_preset: None,
+            short_break_preset: None,
+            long_break_preset: Some("mute".to_string()),
+            bell: true,
+            notify: false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SoundConfig {
     #[serde(default)]
     pub hidden: bool,
+    /// Apply the loudness trim to this sound.
+    #[serde(default = "default_true")]
+    pub normalize: bool,
+}
+
+impl Default for SoundConfig {
+    fn default() -> Self {
+        Self {
+            hidden: false,
+            normalize: true,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
 }
 
 impl Default for Config {
@@ -51,8 +251,18 @@ impl Default for Config {
             audio: AudioConfig {
                 sample_rate: 44100,
                 buffer_size: 100,
+                backend: BackendKind::default(),
+                wav_file: None,
+                host: None,
+                device: None,
+                decoder: DecoderConfig::default(),
+                loudness: LoudnessConfig::default(),
             },
             sounds: HashMap::new(),
+            sleep: SleepConfig::default(),
+            drift: DriftConfig::default(),
+            transition: TransitionConfig::default(),
+            pomodoro: PomodoroConfig::default(),
         }
     }
 }
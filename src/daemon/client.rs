@@ -0,0 +1,228 @@
+use super::protocol::{Request, Status};
+use crate::dsp::{Drift, Spatial, Tone};
+use anyhow::{anyhow, Context, Result};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+/// How often an attached TUI refreshes its view of the daemon's mixer.
+const STATUS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A connection to a running `tanin daemon`.
+///
+/// Keeps a cached [`Status`] so the TUI can query playback state every frame
+/// without a round trip per sound.
+pub struct DaemonClient {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+    status: Status,
+    since_poll: Duration,
+    /// `slept` count of the daemon already acted on.
+    slept_seen: u64,
+}
+
+impl DaemonClient {
+    pub fn connect() -> Result<Self> {
+        let path = super::socket_path();
+        let stream = UnixStream::connect(&path)
+            .with_context(|| format!("No Tanin daemon listening on {}", path.display()))?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+        let writer = stream
+            .try_clone()
+            .context("Failed to clone daemon socket")?;
+        let mut client = Self {
+            reader: BufReader::new(stream),
+            writer,
+            status: Status::default(),
+            since_poll: Duration::ZERO,
+            slept_seen: 0,
+        };
+        client.refresh_status()?;
+        client.slept_seen = client.status.slept;
+        Ok(client)
+    }
+
+    /// Sends a request and returns the payload of the `ok` reply.
+    pub fn request(&mut self, request: &Request) -> Result<String> {
+        writeln!(self.writer, "{}", request).context("Failed to write to daemon socket")?;
+
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(anyhow!("Daemon closed the connection"));
+        }
+
+        let line = line.trim_end();
+        if let Some(err) = line.strip_prefix("err") {
+            return Err(anyhow!("Daemon error: {}", err.trim()));
+        }
+        match line.strip_prefix("ok") {
+            Some(payload) => Ok(payload.trim().to_string()),
+            None => Err(anyhow!("Unexpected daemon reply: {}", line)),
+        }
+    }
+
+    pub fn refresh_status(&mut self) -> Result<&Status> {
+        let payload = self.request(&Request::Status)?;
+        self.status = payload.parse()?;
+        self.since_poll = Duration::ZERO;
+        Ok(&self.status)
+    }
+
+    pub fn status(&self) -> &Status {
+        &self.status
+    }
+
+    pub fn update(&mut self, dt: Duration) {
+        self.since_poll += dt;
+        if self.since_poll >= STATUS_POLL_INTERVAL {
+            if let Err(e) = self.refresh_status() {
+                log::error!("Failed to poll daemon status: {}", e);
+            }
+        }
+    }
+
+    pub fn play(&mut self, id: &str, volume: f32) -> Result<()> {
+        self.request(&Request::Play {
+            id: id.to_string(),
+            volume: Some(volume),
+        })?;
+        if !self.is_playing(id) {
+            self.status.playing.push((id.to_string(), volume));
+        }
+        Ok(())
+    }
+
+    pub fn stop(&mut self, id: &str) {
+        let request = Request::Stop { id: id.to_string() };
+        if let Err(e) = self.request(&request) {
+            log::error!("Failed to stop '{}' on daemon: {}", id, e);
+        }
+        self.status.playing.retain(|(playing, _)| playing != id);
+    }
+
+    pub fn set_volume(&mut self, id: &str, volume: f32) {
+        let request = Request::Volume {
+            id: id.to_string(),
+            volume,
+        };
+        if let Err(e) = self.request(&request) {
+            log::error!("Failed to set volume of '{}' on daemon: {}", id, e);
+        }
+        if let Some((_, vol)) = self.status.playing.iter_mut().find(|(p, _)| p == id) {
+            *vol = volume;
+        }
+    }
+
+    pub fn set_tone(&mut self, id: &str, tone: Tone) {
+        let request = Request::Tone {
+            id: id.to_string(),
+            tone,
+        };
+        if let Err(e) = self.request(&request) {
+            log::error!("Failed to set tone of '{}' on daemon: {}", id, e);
+        }
+    }
+
+    pub fn set_spatial(&mut self, id: &str, spatial: Spatial) {
+        let request = Request::Pan {
+            id: id.to_string(),
+            spatial,
+        };
+        if let Err(e) = self.request(&request) {
+            log::error!("Failed to set pan of '{}' on daemon: {}", id, e);
+        }
+    }
+
+    pub fn set_drift(&mut self, id: &str, drift: Option<Drift>) {
+        let request = Request::Drift {
+            id: id.to_string(),
+            drift,
+        };
+        if let Err(e) = self.request(&request) {
+            log::error!("Failed to set drift of '{}' on daemon: {}", id, e);
+        }
+        if drift.is_none() {
+            self.status.drift.retain(|(drifting, _)| drifting != id);
+        }
+    }
+
+    pub fn set_normalize(&mut self, id: &str, enabled: bool) {
+        let request = Request::Normalize {
+            id: id.to_string(),
+            enabled,
+        };
+        if let Err(e) = self.request(&request) {
+            log::error!("Failed to set normalisation of '{}' on daemon: {}", id, e);
+        }
+    }
+
+    /// Drift level as of the last status poll.
+    pub fn drift_level(&self, id: &str) -> Option<f32> {
+        self.status
+            .drift
+            .iter()
+            .find(|(drifting, _)| drifting == id)
+            .map(|(_, level)| *level)
+    }
+
+    pub fn seek(&mut self, id: &str, position: Duration) {
+        let request = Request::Seek {
+            id: id.to_string(),
+            position,
+        };
+        if let Err(e) = self.request(&request) {
+            log::error!("Failed to seek '{}' on daemon: {}", id, e);
+        }
+        if let Some((_, elapsed, _)) = self.status.position.iter_mut().find(|(p, ..)| p == id) {
+            *elapsed = position;
+        }
+    }
+
+    /// Playback position as of the last status poll.
+    pub fn position(&self, id: &str) -> Option<(Duration, Option<Duration>)> {
+        self.status
+            .position
+            .iter()
+            .find(|(playing, ..)| playing == id)
+            .map(|(_, elapsed, total)| (*elapsed, *total))
+    }
+
+    pub fn set_master_volume(&mut self, volume: f32) {
+        if let Err(e) = self.request(&Request::Master(volume)) {
+            log::error!("Failed to set daemon master volume: {}", e);
+        }
+        self.status.master = volume;
+    }
+
+    pub fn set_sleep_timer(&mut self, duration: Option<Duration>) {
+        if let Err(e) = self.request(&Request::Sleep(duration)) {
+            log::error!("Failed to set daemon sleep timer: {}", e);
+        }
+        self.status.sleep = duration;
+    }
+
+    /// Whether a sleep timer of the daemon ran out since the last call. A
+    /// cancelled timer doesn't count.
+    pub fn take_sleep_finished(&mut self) -> bool {
+        let finished = self.status.slept > self.slept_seen;
+        self.slept_seen = self.status.slept;
+        finished
+    }
+
+    /// Time left on the daemon's sleep timer as of the last status poll.
+    pub fn sleep_remaining(&self) -> Option<Duration> {
+        self.status.sleep
+    }
+
+    pub fn is_playing(&self, id: &str) -> bool {
+        self.status.playing.iter().any(|(playing, _)| playing == id)
+    }
+
+    pub fn stop_all(&mut self) {
+        if let Err(e) = self.request(&Request::StopAll) {
+            log::error!("Failed to stop daemon sounds: {}", e);
+        }
+        self.status.playing.clear();
+    }
+}
@@ -0,0 +1,482 @@
+//! Headless mode: owns the audio engine without a terminal and is driven over a
+//! Unix socket (see [`protocol`] for the command set).
+
+pub mod client;
+pub mod protocol;
+
+pub use client::DaemonClient;
+
+use crate::alarm::{Alarms, WakeFade};
+use crate::audio::AudioEngine;
+use crate::config::{Config, SleepConfig};
+use crate::loudness::{self, Analyzer};
+use crate::output::OutputArgs;
+use crate::presets::{Preset, PresetsConfig};
+use crate::schedule::Schedule;
+use crate::session::{Session, SoundState};
+use crate::sleep::SleepTimer;
+use crate::static_data::{load_all_sounds, Sound};
+use anyhow::{anyhow, Context, Result};
+use directories::ProjectDirs;
+use nix::sys::stat::{umask, Mode};
+use protocol::{Request, Status};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const TICK_RATE: Duration = Duration::from_millis(30);
+
+type Envelope = (Request, Sender<String>);
+
+pub fn socket_path() -> PathBuf {
+    ProjectDirs::from("com", "tanin", "tanin")
+        .and_then(|dirs| dirs.runtime_dir().map(|dir| dir.join("tanin.sock")))
+        // The temp dir is shared between users, so each gets a socket of their own
+        .unwrap_or_else(|| {
+            std::env::temp_dir().join(format!("tanin-{}.sock", nix::unistd::getuid()))
+        })
+}
+
+struct Daemon {
+    engine: AudioEngine,
+    sounds: Vec<Sound>,
+    presets: PresetsConfig,
+    session: Session,
+    active_preset: Option<String>,
+    schedule: Schedule,
+    alarms: Alarms,
+    wake_fade: Option<WakeFade>,
+    sleep_timer: Option<SleepTimer>,
+    /// Sleep timers that ran out, reported to clients as `slept`.
+    sleeps_finished: u64,
+    sleep: SleepConfig,
+    crossfade: Duration,
+    loudness: Analyzer,
+    running: bool,
+}
+
+impl Daemon {
+    fn new(output: &OutputArgs) -> Result<Self> {
+        let config = Config::load()?;
+        let session = Session::load()?;
+        let presets = PresetsConfig::load().unwrap_or_default();
+        let engine = AudioEngine::new(&output.apply(&config.audio))?;
+        let mut sounds = load_all_sounds(config.general.enable_bundled_sounds);
+        log::info!("Daemon loaded {} sounds", sounds.len());
+        let loudness = Analyzer::spawn();
+        for path in loudness::annotate(&mut sounds, &config) {
+            loudness.queue(path);
+        }
+
+        let mut daemon = Self {
+            engine,
+            sounds,
+            presets,
+            session,
+            active_preset: None,
+            schedule: Schedule::load().unwrap_or_else(|e| {
+                log::error!("Failed to load schedule: {}", e);
+                Schedule::default()
+            }),
+            alarms: Alarms::load().unwrap_or_else(|e| {
+                log::error!("Failed to load alarms: {}", e);
+                Alarms::default()
+            }),
+            wake_fade: None,
+            sleep_timer: None,
+            sleeps_finished: 0,
+            sleep: config.sleep.clone(),
+            crossfade: config.transition.crossfade(),
+            loudness,
+            running: true,
+        };
+        daemon.restore_session();
+        Ok(daemon)
+    }
+
+    fn restore_session(&mut self) {
+        self.sleep_timer = self
+            .session
+            .sleep_until
+            .and_then(|deadline| SleepTimer::resume(deadline, &self.sleep));
+        self.apply_master_volume();
+        for sound in &mut self.sounds {
+            if let Some(state) = self.session.sounds.get(&sound.id) {
+                sound.volume_linear = state.volume;
+                sound.tone = state.tone;
+                sound.spatial = state.spatial;
+                sound.drift = state.drift;
+                self.engine.set_tone(&sound.id, state.tone);
+                self.engine.set_spatial(&sound.id, state.spatial);
+                self.engine.set_drift(&sound.id, state.drift);
+                if state.enabled {
+                    if let Some(secs) = state.position {
+                        match Duration::try_from_secs_f64(secs) {
+                            Ok(position) => self.engine.seek(&sound.id, position),
+                            Err(_) => {
+                                log::warn!("Ignoring saved position {} of '{}'", secs, sound.id)
+                            }
+                        }
+                    }
+                    if let Err(e) = self.engine.play(sound, state.volume) {
+                        log::error!("Failed to auto-play sound '{}': {}", sound.id, e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn save_session(&mut self) {
+        for sound in &self.sounds {
+            self.session.sounds.insert(
+                sound.id.clone(),
+                SoundState {
+                    enabled: self.engine.is_playing(&sound.id),
+                    volume: sound.volume_linear,
+                    tone: sound.tone,
+                    spatial: sound.spatial,
+                    drift: sound.drift,
+                    position: self
+                        .engine
+                        .position(&sound.id)
+                        .map(|(elapsed, _)| elapsed.as_secs_f64()),
+                },
+            );
+        }
+        self.session.sleep_until = self.sleep_timer.as_ref().map(SleepTimer::deadline);
+        if let Err(e) = self.session.save() {
+            log::error!("Failed to save session: {}", e);
+        }
+    }
+
+    fn sound_mut(&mut self, id: &str) -> Result<&mut Sound> {
+        self.sounds
+            .iter_mut()
+            .find(|s| s.id == id)
+            .ok_or_else(|| anyhow!("unknown sound '{}'", id))
+    }
+
+    fn handle(&mut self, request: Request) -> Result<String> {
+        log::debug!("Daemon request: {}", request);
+        match request {
+            Request::Play { id, volume } => {
+                let sound = self.sound_mut(&id)?;
+                if let Some(v) = volume {
+                    sound.volume_linear = v;
+                }
+                let sound = sound.clone();
+                self.engine.play(&sound, sound.volume_linear)?;
+            }
+            Request::Stop { id } => {
+                self.sound_mut(&id)?;
+                self.engine.stop(&id);
+            }
+            Request::Volume { id, volume } => {
+                self.sound_mut(&id)?.volume_linear = volume;
+                self.engine.set_volume(&id, volume);
+            }
+            Request::Tone { id, tone } => {
+                self.sound_mut(&id)?.tone = tone;
+                self.engine.set_tone(&id, tone);
+            }
+            Request::Pan { id, spatial } => {
+                self.sound_mut(&id)?.spatial = spatial;
+                self.engine.set_spatial(&id, spatial);
+            }
+            Request::Drift { id, drift } => {
+                self.sound_mut(&id)?.drift = drift;
+                self.engine.set_drift(&id, drift);
+            }
+            Request::Normalize { id, enabled } => {
+                let sound = self.sound_mut(&id)?;
+                sound.normalize = enabled;
+                let sound = sound.clone();
+                self.engine.set_loudness(&sound);
+                // Same place the TUI keeps it when running without a daemon
+                let mut config = Config::load()?;
+                config.sounds.entry(id).or_default().normalize = enabled;
+                config.save()?;
+            }
+            Request::Seek { id, position } => {
+                if !self.sound_mut(&id)?.has_file() {
+                    return Err(anyhow!("'{}' does not play a file", id));
+                }
+                self.engine.seek(&id, position);
+            }
+            Request::Master(volume) => {
+                self.session.global_volume = volume;
+                self.apply_master_volume();
+            }
+            Request::Sleep(duration) => {
+                self.sleep_timer =
+                    duration.map(|remaining| SleepTimer::new(remaining, &self.sleep));
+                match duration {
+                    Some(remaining) => log::info!("Sleep timer set for {:?}", remaining),
+                    None => log::info!("Sleep timer cancelled"),
+                }
+                self.apply_master_volume();
+            }
+            Request::LoadPreset(name) => self.load_preset(&name)?,
+            Request::StopAll => self.engine.stop_all(),
+            Request::Status => return Ok(self.status().to_string()),
+            Request::Quit => self.running = false,
+        }
+
+        self.save_session();
+        Ok(String::new())
+    }
+
+    fn find_preset(&self, name: &str) -> Result<Preset> {
+        self.presets
+            .presets
+            .iter()
+            .find(|p| p.name.eq_ignore_ascii_case(name))
+            .cloned()
+            .ok_or_else(|| anyhow!("unknown preset '{}'", name))
+    }
+
+    /// Crossfades into a preset, see `App::switch_to_preset`.
+    fn load_preset(&mut self, name: &str) -> Result<()> {
+        let preset = self.find_preset(name)?;
+        for sound in &mut self.sounds {
+            let Some(entry) = preset.sounds.get(&sound.id) else {
+                self.engine.stop_with_fade(&sound.id, self.crossfade);
+                continue;
+            };
+
+            entry.apply_to(sound);
+            let vol = entry.volume;
+            self.engine.set_tone(&sound.id, sound.tone);
+            self.engine.set_spatial(&sound.id, sound.spatial);
+            self.engine.set_drift(&sound.id, sound.drift);
+
+            if self.engine.is_playing(&sound.id) {
+                self.engine.ramp_volume(&sound.id, vol, self.crossfade);
+            } else if let Err(e) = self.engine.play_with_fade(sound, vol, self.crossfade) {
+                log::error!("Failed to play preset sound '{}': {}", sound.id, e);
+            }
+        }
+        // Without a mute of its own the daemon only takes the volume
+        if let Some(volume) = preset.master_volume {
+            self.session.global_volume = volume;
+            self.apply_master_volume();
+        }
+        if let Err(e) = PresetsConfig::record_use(&preset.name) {
+            log::error!("Failed to save preset use: {}", e);
+        }
+        self.active_preset = Some(preset.name);
+        Ok(())
+    }
+
+    fn update_loudness(&mut self) {
+        for measurement in self.loudness.poll() {
+            for sound in &mut self.sounds {
+                if sound.file_path == measurement.path {
+                    sound.loudness = measurement.lufs;
+                    self.engine.set_loudness(sound);
+                }
+            }
+        }
+    }
+
+    fn update_schedule(&mut self) {
+        let Some(name) = self.schedule.poll() else {
+            return;
+        };
+        log::info!("Schedule switching to preset '{}'", name);
+        match self.load_preset(&name) {
+            Ok(()) => self.save_session(),
+            Err(e) => log::error!("Scheduled preset failed: {}", e),
+        }
+    }
+
+    fn update_alarms(&mut self, dt: Duration) {
+        if let Some(fade) = &mut self.wake_fade {
+            fade.elapsed += dt;
+            let done = fade.is_done();
+            self.apply_master_volume();
+            if done {
+                self.wake_fade = None;
+            }
+        }
+
+        let Some(alarm) = self.alarms.poll() else {
+            return;
+        };
+        log::info!("Alarm at {} starting preset '{}'", alarm.at, alarm.preset);
+        self.wake_fade = Some(WakeFade::new(alarm.fade()));
+        self.sleep_timer = None;
+        self.apply_master_volume();
+        match self.load_preset(&alarm.preset) {
+            Ok(()) => self.save_session(),
+            Err(e) => log::error!("Alarm preset failed: {}", e),
+        }
+    }
+
+    /// Stops every sound once the sleep timer runs out. Unlike the TUI the
+    /// daemon keeps running afterwards, whatever `quit_on_finish` says.
+    fn update_sleep_timer(&mut self, dt: Duration) {
+        let Some(timer) = &mut self.sleep_timer else {
+            return;
+        };
+        if !timer.tick(dt) {
+            if timer.is_fading() {
+                self.apply_master_volume();
+            }
+            return;
+        }
+
+        log::info!("Sleep timer finished, stopping all sounds");
+        self.sleep_timer = None;
+        self.sleeps_finished += 1;
+        self.engine.stop_all();
+        self.apply_master_volume();
+        self.save_session();
+    }
+
+    /// The session's master volume, scaled down while an alarm fades in or the
+    /// sleep timer fades out.
+    fn apply_master_volume(&mut self) {
+        let wake = self.wake_fade.as_ref().map_or(1.0, WakeFade::volume_factor);
+        let sleep = self
+            .sleep_timer
+            .as_ref()
+            .map_or(1.0, SleepTimer::volume_factor);
+        let factor = wake * sleep;
+        self.engine
+            .set_master_volume(self.session.global_volume * factor);
+    }
+
+    fn status(&self) -> Status {
+        Status {
+            master: self.session.global_volume,
+            playing: self
+                .sounds
+                .iter()
+                .filter(|s| self.engine.is_playing(&s.id))
+                .map(|s| (s.id.clone(), s.volume_linear))
+                .collect(),
+            drift: self
+                .sounds
+                .iter()
+                .filter(|s| self.engine.is_playing(&s.id))
+                .filter_map(|s| Some((s.id.clone(), self.engine.drift_level(&s.id)?)))
+                .collect(),
+            position: self
+                .sounds
+                .iter()
+                .filter_map(|s| {
+                    let (elapsed, total) = self.engine.position(&s.id)?;
+                    Some((s.id.clone(), elapsed, total))
+                })
+                .collect(),
+            sleep: self.sleep_timer.as_ref().map(|timer| timer.remaining),
+            slept: self.sleeps_finished,
+            preset: self.active_preset.clone(),
+        }
+    }
+}
+
+pub fn run(output: &OutputArgs) -> Result<()> {
+    let path = socket_path();
+    if UnixStream::connect(&path).is_ok() {
+        return Err(anyhow!(
+            "A Tanin daemon is already listening on {}",
+            path.display()
+        ));
+    }
+    // A socket file nobody answers on is left over from a daemon that was killed.
+    let _ = std::fs::remove_file(&path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut daemon = Daemon::new(output)?;
+    // Anyone who can connect controls playback, so the socket is created
+    // owner-only rather than restricted once other users could already connect
+    let previous_umask = umask(Mode::from_bits_truncate(0o077));
+    let bound = UnixListener::bind(&path);
+    umask(previous_umask);
+    let listener =
+        bound.with_context(|| format!("Failed to bind daemon socket {}", path.display()))?;
+    log::info!("Daemon listening on {}", path.display());
+
+    let (tx, rx) = mpsc::channel::<Envelope>();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let tx = tx.clone();
+                    thread::spawn(move || serve_connection(stream, tx));
+                }
+                Err(e) => log::error!("Failed to accept daemon connection: {}", e),
+            }
+        }
+    });
+
+    let mut last_tick = Instant::now();
+    while daemon.running {
+        match rx.recv_timeout(TICK_RATE) {
+            Ok((request, reply_tx)) => {
+                let reply = match daemon.handle(request) {
+                    Ok(payload) if payload.is_empty() => "ok".to_string(),
+                    Ok(payload) => format!("ok {}", payload),
+                    Err(e) => format!("err {}", e),
+                };
+                let _ = reply_tx.send(reply);
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        daemon.update_schedule();
+        daemon.update_loudness();
+        let now = Instant::now();
+        let dt = now.duration_since(last_tick);
+        daemon.update_alarms(dt);
+        daemon.update_sleep_timer(dt);
+        daemon.engine.update(dt);
+        last_tick = now;
+    }
+
+    log::info!("Daemon shutting down");
+    daemon.save_session();
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+fn serve_connection(stream: UnixStream, tx: Sender<Envelope>) {
+    let mut writer = match stream.try_clone() {
+        Ok(w) => w,
+        Err(e) => {
+            log::error!("Failed to clone daemon connection: {}", e);
+            return;
+        }
+    };
+
+    for line in BufReader::new(stream).lines().map_while(Result::ok) {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let reply = match line.parse::<Request>() {
+            Ok(request) => {
+                let (reply_tx, reply_rx) = mpsc::channel();
+                if tx.send((request, reply_tx)).is_err() {
+                    return;
+                }
+                reply_rx
+                    .recv()
+                    .unwrap_or_else(|_| "err daemon stopped".to_string())
+            }
+            Err(e) => format!("err {}", e),
+        };
+
+        if writeln!(writer, "{}", reply).is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,415 @@
+//! Line based command protocol spoken over the daemon's Unix socket.
+//!
+//! Every request is a single line, e.g. `play rain 0.6`, `tone rain lp=4000 hp=120`,
+//! `pan rain -0.5 1.2`, `drift birds wander 0.3 1.0 20`, `normalize rain off`,
+//! `seek rain 90.5`, `sleep 45m` or `load-preset Deep Focus`.
+//! The daemon answers each request with exactly one line, either `ok [payload]`
+//! or `err <message>`.
+
+use crate::dsp::{Drift, DriftMode, Spatial, Tone};
+use crate::duration::parse_duration;
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Request {
+    Play { id: String, volume: Option<f32> },
+    Stop { id: String },
+    Volume { id: String, volume: f32 },
+    Tone { id: String, tone: Tone },
+    Pan { id: String, spatial: Spatial },
+    Drift { id: String, drift: Option<Drift> },
+    Normalize { id: String, enabled: bool },
+    Seek { id: String, position: Duration },
+    Master(f32),
+    Sleep(Option<Duration>),
+    LoadPreset(String),
+    StopAll,
+    Status,
+    Quit,
+}
+
+impl FromStr for Request {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        let line = line.trim();
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        let mut args = rest.split_whitespace();
+
+        let request = match command {
+            "play" => Request::Play {
+                id: required(args.next(), "sound id")?,
+                volume: args.next().map(parse_volume).transpose()?,
+            },
+            "stop" => Request::Stop {
+                id: required(args.next(), "sound id")?,
+            },
+            "volume" => Request::Volume {
+                id: required(args.next(), "sound id")?,
+                volume: parse_volume(&required(args.next(), "volume")?)?,
+            },
+            "tone" => Request::Tone {
+                id: required(args.next(), "sound id")?,
+                tone: parse_tone(args)?,
+            },
+            "pan" => Request::Pan {
+                id: required(args.next(), "sound id")?,
+                spatial: Spatial {
+                    pan: parse_number(&required(args.next(), "pan")?, "pan")?,
+                    width: args
+                        .next()
+                        .map(|w| parse_number(w, "width"))
+                        .transpose()?
+                        .unwrap_or(1.0),
+                }
+                .clamped(),
+            },
+            "drift" => Request::Drift {
+                id: required(args.next(), "sound id")?,
+                drift: parse_drift(args)?,
+            },
+            "normalize" => Request::Normalize {
+                id: required(args.next(), "sound id")?,
+                enabled: match required(args.next(), "on or off")?.as_str() {
+                    "on" => true,
+                    "off" => false,
+                    other => return Err(anyhow!("expected on or off, got '{}'", other)),
+                },
+            },
+            "seek" => Request::Seek {
+                id: required(args.next(), "sound id")?,
+                position: parse_seconds(&required(args.next(), "position")?)?,
+            },
+            "master" => Request::Master(parse_volume(&required(args.next(), "volume")?)?),
+            "sleep" => Request::Sleep(match required(args.next(), "duration or off")?.as_str() {
+                "off" => None,
+                duration => Some(parse_duration(duration).map_err(|e| anyhow!(e))?),
+            }),
+            "load-preset" => Request::LoadPreset(required(Some(rest), "preset name")?),
+            "stop-all" => Request::StopAll,
+            "status" => Request::Status,
+            "quit" => Request::Quit,
+            "" => return Err(anyhow!("empty command")),
+            other => return Err(anyhow!("unknown command '{}'", other)),
+        };
+
+        Ok(request)
+    }
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Request::Play {
+                id,
+                volume: Some(v),
+            } => write!(f, "play {} {:.3}", id, v),
+            Request::Play { id, volume: None } => write!(f, "play {}", id),
+            Request::Stop { id } => write!(f, "stop {}", id),
+            Request::Volume { id, volume } => write!(f, "volume {} {:.3}", id, volume),
+            Request::Tone { id, tone } => {
+                write!(f, "tone {}", id)?;
+                if let Some(hz) = tone.low_pass {
+                    write!(f, " lp={}", hz)?;
+                }
+                if let Some(hz) = tone.high_pass {
+                    write!(f, " hp={}", hz)?;
+                }
+                for (key, db) in [
+                    ("bass", tone.bass_db),
+                    ("mid", tone.mid_db),
+                    ("treble", tone.treble_db),
+                ] {
+                    if db != 0.0 {
+                        write!(f, " {}={}", key, db)?;
+                    }
+                }
+                Ok(())
+            }
+            Request::Pan { id, spatial } => {
+                write!(f, "pan {} {:.3} {:.3}", id, spatial.pan, spatial.width)
+            }
+            Request::Drift { id, drift: None } => write!(f, "drift {} off", id),
+            Request::Drift {
+                id,
+                drift: Some(drift),
+            } => {
+                let mode = match drift.mode {
+                    DriftMode::Wander => "wander",
+                    DriftMode::Lfo => "lfo",
+                };
+                write!(
+                    f,
+                    "drift {} {} {:.3} {:.3} {}",
+                    id, mode, drift.min, drift.max, drift.period_secs
+                )
+            }
+            Request::Normalize { id, enabled } => {
+                write!(
+                    f,
+                    "normalize {} {}",
+                    id,
+                    if *enabled { "on" } else { "off" }
+                )
+            }
+            Request::Seek { id, position } => {
+                write!(f, "seek {} {:.3}", id, position.as_secs_f64())
+            }
+            Request::Master(v) => write!(f, "master {:.3}", v),
+            Request::Sleep(Some(duration)) => write!(f, "sleep {}s", duration.as_secs()),
+            Request::Sleep(None) => write!(f, "sleep off"),
+            Request::LoadPreset(name) => write!(f, "load-preset {}", name),
+            Request::StopAll => write!(f, "stop-all"),
+            Request::Status => write!(f, "status"),
+            Request::Quit => write!(f, "quit"),
+        }
+    }
+}
+
+/// Snapshot of the daemon's mixer, sent as the payload of a `status` reply:
+/// `master=0.500 playing=rain:0.500,wind:0.300 drift=wind:0.640
+/// position=rain:95.2/3600.0 sleep=1740.0 slept=1 preset=Deep Focus`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Status {
+    pub master: f32,
+    pub playing: Vec<(String, f32)>,
+    /// Live drift multiplier of drifting sounds, omitted when none drift.
+    pub drift: Vec<(String, f32)>,
+    /// Playback position of playing file-backed sounds and the length of their
+    /// file if known, omitted when there are none.
+    pub position: Vec<(String, Duration, Option<Duration>)>,
+    /// Time left on the sleep timer, omitted when none is running.
+    pub sleep: Option<Duration>,
+    /// Sleep timers that ran out since the daemon started, omitted when none
+    /// did. Lets clients tell a finished timer from a cancelled one.
+    pub slept: u64,
+    pub preset: Option<String>,
+}
+
+impl FromStr for Status {
+    type Err = anyhow::Error;
+
+    fn from_str(payload: &str) -> Result<Self> {
+        // The preset name may contain spaces, so it is always the last field.
+        let (fields, preset) = match payload.split_once("preset=") {
+            Some((fields, name)) => (fields, Some(name.trim().to_string())),
+            None => (payload, None),
+        };
+
+        let mut status = Status {
+            preset: preset.filter(|name| !name.is_empty()),
+            ..Default::default()
+        };
+
+        for field in fields.split_whitespace() {
+            match field.split_once('=') {
+                Some(("master", v)) => status.master = parse_volume(v)?,
+                Some(("playing", list)) => status.playing = parse_levels(list)?,
+                Some(("drift", list)) => status.drift = parse_levels(list)?,
+                Some(("position", list)) => status.position = parse_positions(list)?,
+                Some(("sleep", secs)) => status.sleep = Some(parse_seconds(secs)?),
+                Some(("slept", count)) => {
+                    status.slept = count
+                        .parse()
+                        .map_err(|_| anyhow!("invalid sleep count '{}'", count))?
+                }
+                _ => return Err(anyhow!("unexpected status field '{}'", field)),
+            }
+        }
+
+        Ok(status)
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "master={:.3} playing={}",
+            self.master,
+            format_levels(&self.playing)
+        )?;
+        if !self.drift.is_empty() {
+            write!(f, " drift={}", format_levels(&self.drift))?;
+        }
+        if !self.position.is_empty() {
+            write!(f, " position={}", format_positions(&self.position))?;
+        }
+        if let Some(remaining) = self.sleep {
+            write!(f, " sleep={:.1}", remaining.as_secs_f64())?;
+        }
+        if self.slept > 0 {
+            write!(f, " slept={}", self.slept)?;
+        }
+        if let Some(name) = &self.preset {
+            write!(f, " preset={}", name)?;
+        }
+        Ok(())
+    }
+}
+
+fn format_levels(levels: &[(String, f32)]) -> String {
+    levels
+        .iter()
+        .map(|(id, vol)| format!("{}:{:.3}", id, vol))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn parse_levels(list: &str) -> Result<Vec<(String, f32)>> {
+    list.split(',')
+        .filter(|e| !e.is_empty())
+        .map(|entry| {
+            let (id, vol) = entry
+                .split_once(':')
+                .ok_or_else(|| anyhow!("malformed level entry '{}'", entry))?;
+            Ok((id.to_string(), parse_volume(vol)?))
+        })
+        .collect()
+}
+
+/// `id:elapsed/total` in seconds, without the total when it isn't known.
+fn format_positions(positions: &[(String, Duration, Option<Duration>)]) -> String {
+    positions
+        .iter()
+        .map(|(id, elapsed, total)| match total {
+            Some(total) => format!(
+                "{}:{:.1}/{:.1}",
+                id,
+                elapsed.as_secs_f64(),
+                total.as_secs_f64()
+            ),
+            None => format!("{}:{:.1}", id, elapsed.as_secs_f64()),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn parse_positions(list: &str) -> Result<Vec<(String, Duration, Option<Duration>)>> {
+    list.split(',')
+        .filter(|e| !e.is_empty())
+        .map(|entry| {
+            let (id, times) = entry
+                .split_once(':')
+                .ok_or_else(|| anyhow!("malformed position entry '{}'", entry))?;
+            let (elapsed, total) = match times.split_once('/') {
+                Some((elapsed, total)) => (elapsed, Some(parse_seconds(total)?)),
+                None => (times, None),
+            };
+            Ok((id.to_string(), parse_seconds(elapsed)?, total))
+        })
+        .collect()
+}
+
+fn required(value: Option<&str>, what: &str) -> Result<String> {
+    match value {
+        Some(v) if !v.is_empty() => Ok(v.to_string()),
+        _ => Err(anyhow!("missing {}", what)),
+    }
+}
+
+/// Parses `key=value` tone settings; anything not given is left flat.
+fn parse_tone<'a>(args: impl Iterator<Item = &'a str>) -> Result<Tone> {
+    let mut tone = Tone::default();
+    for arg in args {
+        let (key, value) = arg
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected key=value, got '{}'", arg))?;
+        let number = || -> Result<f32> {
+            value
+                .parse()
+                .ok()
+                .filter(|v: &f32| v.is_finite())
+                .ok_or_else(|| anyhow!("invalid {} value '{}'", key, value))
+        };
+        let cutoff = || -> Result<Option<f32>> {
+            if value == "off" {
+                Ok(None)
+            } else {
+                number().map(Some)
+            }
+        };
+        match key {
+            "lp" => tone.low_pass = cutoff()?,
+            "hp" => tone.high_pass = cutoff()?,
+            "bass" => tone.bass_db = number()?,
+            "mid" => tone.mid_db = number()?,
+            "treble" => tone.treble_db = number()?,
+            _ => return Err(anyhow!("unknown tone setting '{}'", key)),
+        }
+    }
+    Ok(tone)
+}
+
+/// `off`, or a mode followed by optional `min max period_secs`.
+fn parse_drift<'a>(mut args: impl Iterator<Item = &'a str>) -> Result<Option<Drift>> {
+    let mode = match required(args.next(), "drift mode")?.as_str() {
+        "off" => return Ok(None),
+        "wander" => DriftMode::Wander,
+        "lfo" => DriftMode::Lfo,
+        other => return Err(anyhow!("unknown drift mode '{}'", other)),
+    };
+    let mut drift = Drift::new(mode);
+    if let Some(min) = args.next() {
+        drift.min = parse_volume(min)?;
+    }
+    if let Some(max) = args.next() {
+        drift.max = parse_volume(max)?;
+    }
+    if let Some(period) = args.next() {
+        drift.period_secs = parse_number(period, "period")?;
+    }
+    Ok(Some(drift.clamped()))
+}
+
+fn parse_number(value: &str, what: &str) -> Result<f32> {
+    value
+        .parse()
+        .map_err(|_| anyhow!("invalid {} '{}'", what, value))
+}
+
+fn parse_seconds(value: &str) -> Result<Duration> {
+    let secs: f64 = value
+        .parse()
+        .map_err(|_| anyhow!("invalid position '{}'", value))?;
+    Duration::try_from_secs_f64(secs).map_err(|_| anyhow!("invalid position '{}'", value))
+}
+
+fn parse_volume(value: &str) -> Result<f32> {
+    let volume: f32 = value
+        .parse()
+        .map_err(|_| anyhow!("invalid volume '{}'", value))?;
+    Ok(volume.clamp(0.0, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tone_settings_are_parsed() {
+        let request: Request = "tone rain lp=4000 hp=off bass=-3.5".parse().unwrap();
+        let Request::Tone { id, tone } = request else {
+            panic!("not a tone request: {:?}", request);
+        };
+        assert_eq!(id, "rain");
+        assert_eq!(tone.low_pass, Some(4000.0));
+        assert_eq!(tone.high_pass, None);
+        assert_eq!(tone.bass_db, -3.5);
+    }
+
+    #[test]
+    fn non_finite_tone_settings_are_refused() {
+        for request in [
+            "tone rain lp=NaN",
+            "tone rain hp=inf",
+            "tone rain treble=-inf",
+        ] {
+            assert!(request.parse::<Request>().is_err(), "{} accepted", request);
+        }
+    }
+}
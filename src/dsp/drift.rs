@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DriftMode {
+    /// Glides between random levels at irregular intervals.
+    Wander,
+    /// A slow sine sweep between the bounds.
+    Lfo,
+}
+
+/// Slow volume modulation that makes a static mix "breathe".
+///
+/// `min` and `max` scale the sound's own volume, so the slider keeps working
+/// as the overall level of a drifting sound.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Drift {
+    pub mode: DriftMode,
+    pub min: f32,
+    pub max: f32,
+    /// Average time between wander targets, or the length of one LFO cycle.
+    pub period_secs: f32,
+}
+
+impl Drift {
+    pub const DEFAULT_MIN: f32 = 0.4;
+    pub const DEFAULT_MAX: f32 = 1.0;
+    pub const DEFAULT_PERIOD_SECS: f32 = 20.0;
+
+    pub fn new(mode: DriftMode) -> Self {
+        Self {
+            mode,
+            min: Self::DEFAULT_MIN,
+            max: Self::DEFAULT_MAX,
+            period_secs: Self::DEFAULT_PERIOD_SECS,
+        }
+    }
+
+    pub fn clamped(self) -> Self {
+        let min = self.min.clamp(0.0, 1.0);
+        Self {
+            mode: self.mode,
+            min,
+            max: self.max.clamp(min, 1.0),
+            period_secs: self.period_secs.max(1.0),
+        }
+    }
+}
+
+/// Running state of a [`Drift`], advanced from the engine's update tick.
+#[derive(Debug, Clone)]
+pub struct DriftState {
+    drift: Drift,
+    rng: fastrand::Rng,
+    elapsed: f32,
+    from: f32,
+    to: f32,
+    segment: f32,
+    level: f32,
+}
+
+impl DriftState {
+    /// `seed` picks the wander path, so the same seed always drifts the same way.
+    pub fn new(drift: Drift, seed: u64) -> Self {
+        let drift = drift.clamped();
+        // Start at the top so enabling drift never causes an audible drop
+        let mut state = Self {
+            drift,
+            rng: fastrand::Rng::with_seed(seed),
+            elapsed: 0.0,
+            from: drift.max,
+            to: drift.max,
+            segment: 0.0,
+            level: drift.max,
+        };
+        state.next_segment();
+        state
+    }
+
+    pub fn drift(&self) -> Drift {
+        self.drift
+    }
+
+    /// Current volume multiplier, between `min` and `max`.
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    pub fn advance(&mut self, dt: Duration) {
+        self.elapsed += dt.as_secs_f32();
+        let Drift {
+            mode,
+            min,
+            max,
+            period_secs,
+        } = self.drift;
+
+        self.level = match mode {
+            DriftMode::Lfo => {
+                // Starts at the top of the cycle, matching the initial level
+                let phase = (self.elapsed / period_secs).fract();
+                min + (max - min) * (0.5 + 0.5 * (phase * TAU).cos())
+            }
+            DriftMode::Wander => {
+                while self.elapsed >= self.segment {
+                    self.elapsed -= self.segment;
+                    self.next_segment();
+                }
+                // Cosine easing keeps the level's slope continuous between segments
+                let t = self.elapsed / self.segment;
+                let eased = 0.5 - 0.5 * (t * std::f32::consts::PI).cos();
+                self.from + (self.to - self.from) * eased
+            }
+        };
+    }
+
+    fn next_segment(&mut self) {
+        let Drift {
+            min,
+            max,
+            period_secs,
+            ..
+        } = self.drift;
+        self.from = self.to;
+        self.to = min + (max - min) * self.rng.f32();
+        self.segment = period_secs * (0.5 + self.rng.f32());
+    }
+}
@@ -0,0 +1,171 @@
+use super::pan::Spatial;
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const SAMPLE_RATE: u32 = 44100;
+pub const CHANNELS: u16 = 2;
+
+/// A decoded clip, interleaved stereo at [`SAMPLE_RATE`].
+pub type Clip = Arc<Vec<f32>>;
+
+/// Floor on the interval, so a typo in sounds.toml can't start an event every frame.
+const MIN_INTERVAL_SECS: f32 = 0.5;
+
+/// Short clips played now and then instead of a continuous loop, used via
+/// `events = ["thunder.ogg", ...]` in `sounds.toml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Events {
+    /// Paths of the clips, one is picked at random for each event.
+    pub clips: Vec<String>,
+    /// Seconds between the starts of two events.
+    pub min_interval: f32,
+    pub max_interval: f32,
+    /// Linear gain range of a single event.
+    pub min_gain: f32,
+    pub max_gain: f32,
+    /// Pan range of a single event, -1.0 is hard left and 1.0 hard right.
+    pub min_pan: f32,
+    pub max_pan: f32,
+}
+
+impl Events {
+    pub const DEFAULT_MIN_INTERVAL: f32 = 10.0;
+    pub const DEFAULT_MAX_INTERVAL: f32 = 60.0;
+    pub const DEFAULT_MIN_GAIN: f32 = 0.5;
+    pub const DEFAULT_MAX_GAIN: f32 = 1.0;
+    pub const DEFAULT_MIN_PAN: f32 = -0.5;
+    pub const DEFAULT_MAX_PAN: f32 = 0.5;
+
+    /// An endless stereo source. `clips` delivers the decoded
+    /// [`clips`](Self::clips); the source stays silent until they arrive and
+    /// schedules its events from then on. The same `seed` always schedules the
+    /// same events.
+    pub fn source(&self, clips: Receiver<Vec<Clip>>, seed: u64) -> EventSource {
+        let mut source = EventSource {
+            events: self.clone(),
+            clips: Vec::new(),
+            pending: Some(clips),
+            rng: fastrand::Rng::with_seed(seed),
+            voices: Vec::new(),
+            countdown: 0,
+            frame: [0.0; CHANNELS as usize],
+            channel: 0,
+        };
+        // Start partway through an interval rather than with an event
+        source.countdown = source.next_interval() / 2;
+        source
+    }
+}
+
+/// An event that is playing.
+struct Voice {
+    clip: usize,
+    /// Next sample of the clip.
+    position: usize,
+    gains: (f32, f32),
+}
+
+pub struct EventSource {
+    events: Events,
+    clips: Vec<Clip>,
+    /// Where the clips come from while they are still being decoded.
+    pending: Option<Receiver<Vec<Clip>>>,
+    rng: fastrand::Rng,
+    /// Events overlap when the interval is shorter than the clips.
+    voices: Vec<Voice>,
+    /// Frames until the next event starts.
+    countdown: u64,
+    frame: [f32; CHANNELS as usize],
+    channel: usize,
+}
+
+impl EventSource {
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.rng.f32()
+    }
+
+    fn next_interval(&mut self) -> u64 {
+        let secs = self.range(self.events.min_interval, self.events.max_interval);
+        (secs.max(MIN_INTERVAL_SECS) * SAMPLE_RATE as f32) as u64
+    }
+
+    fn start_event(&mut self) {
+        if self.clips.is_empty() {
+            return;
+        }
+        let gain = self.range(self.events.min_gain, self.events.max_gain);
+        let pan = self.range(self.events.min_pan, self.events.max_pan);
+        let (left, right) = Spatial { pan, width: 1.0 }.gains();
+        self.voices.push(Voice {
+            clip: self.rng.usize(..self.clips.len()),
+            position: 0,
+            gains: (left * gain, right * gain),
+        });
+    }
+
+    fn mix_frame(&mut self) {
+        if let Some(pending) = &self.pending {
+            match pending.try_recv() {
+                Ok(clips) => {
+                    self.clips = clips;
+                    self.pending = None;
+                }
+                Err(TryRecvError::Empty) => return,
+                // Decoding failed; nothing will ever play
+                Err(TryRecvError::Disconnected) => self.pending = None,
+            }
+        }
+
+        if self.countdown == 0 {
+            self.start_event();
+            self.countdown = self.next_interval();
+        }
+        self.countdown -= 1;
+
+        let mut frame = [0.0; CHANNELS as usize];
+        for voice in &mut self.voices {
+            let clip = &self.clips[voice.clip];
+            if let Some(samples) = clip.get(voice.position..voice.position + 2) {
+                frame[0] += samples[0] * voice.gains.0;
+                frame[1] += samples[1] * voice.gains.1;
+            }
+            voice.position += CHANNELS as usize;
+        }
+        let clips = &self.clips;
+        self.voices
+            .retain(|voice| voice.position < clips[voice.clip].len());
+        self.frame = frame;
+    }
+}
+
+impl Iterator for EventSource {
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        if self.channel == 0 {
+            self.mix_frame();
+        }
+        let sample = self.frame[self.channel];
+        self.channel = (self.channel + 1) % CHANNELS as usize;
+        Some(sample)
+    }
+}
+
+impl Source for EventSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+    fn channels(&self) -> u16 {
+        CHANNELS
+    }
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
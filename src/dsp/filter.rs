@@ -0,0 +1,278 @@
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How many samples pass between checks for updated tone settings.
+const CONTROL_INTERVAL: usize = 512;
+
+const BASS_SHELF_HZ: f32 = 200.0;
+const MID_PEAK_HZ: f32 = 1000.0;
+const TREBLE_SHELF_HZ: f32 = 4000.0;
+const BUTTERWORTH_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// Per-sound tone shaping: optional low/high-pass cutoffs plus a 3-band shelf EQ.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Tone {
+    /// Low-pass cutoff in Hz, `None` to disable.
+    pub low_pass: Option<f32>,
+    /// High-pass cutoff in Hz, `None` to disable.
+    pub high_pass: Option<f32>,
+    pub bass_db: f32,
+    pub mid_db: f32,
+    pub treble_db: f32,
+}
+
+impl Tone {
+    pub fn is_flat(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether every setting is a real number. NaN would get past the cutoff
+    /// clamp and silence the sound for good.
+    pub fn is_finite(&self) -> bool {
+        [self.low_pass, self.high_pass]
+            .into_iter()
+            .flatten()
+            .chain([self.bass_db, self.mid_db, self.treble_db])
+            .all(f32::is_finite)
+    }
+
+    pub fn has_eq(&self) -> bool {
+        self.bass_db != 0.0 || self.mid_db != 0.0 || self.treble_db != 0.0
+    }
+
+    fn stages(&self, sample_rate: u32) -> Vec<Biquad> {
+        let fs = sample_rate as f32;
+        // Keep cutoffs safely below Nyquist so the coefficients stay stable
+        let clamp = |hz: f32| hz.clamp(10.0, fs * 0.45);
+
+        let mut stages = Vec::new();
+        if let Some(hz) = self.high_pass {
+            stages.push(Biquad::high_pass(clamp(hz), fs));
+        }
+        if let Some(hz) = self.low_pass {
+            stages.push(Biquad::low_pass(clamp(hz), fs));
+        }
+        if self.bass_db != 0.0 {
+            stages.push(Biquad::low_shelf(clamp(BASS_SHELF_HZ), self.bass_db, fs));
+        }
+        if self.mid_db != 0.0 {
+            stages.push(Biquad::peaking(clamp(MID_PEAK_HZ), self.mid_db, fs));
+        }
+        if self.treble_db != 0.0 {
+            stages.push(Biquad::high_shelf(
+                clamp(TREBLE_SHELF_HZ),
+                self.treble_db,
+                fs,
+            ));
+        }
+        stages
+    }
+}
+
+/// Shared handle used by the engine to retune a playing [`ToneFilter`].
+#[derive(Default)]
+pub struct ToneControl {
+    tone: Mutex<Tone>,
+    version: AtomicU64,
+}
+
+impl ToneControl {
+    pub fn set(&self, tone: Tone) {
+        *self.tone.lock().unwrap() = tone;
+        self.version.fetch_add(1, Ordering::Release);
+    }
+
+    pub fn get(&self) -> Tone {
+        *self.tone.lock().unwrap()
+    }
+}
+
+/// Normalised biquad coefficients (RBJ audio EQ cookbook).
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+}
+
+impl Biquad {
+    pub(super) fn normalised(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> Self {
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        }
+    }
+
+    fn low_pass(hz: f32, fs: f32) -> Self {
+        let (cos, alpha) = Self::omega(hz, fs, BUTTERWORTH_Q);
+        Self::normalised(
+            (1.0 - cos) / 2.0,
+            1.0 - cos,
+            (1.0 - cos) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        )
+    }
+
+    fn high_pass(hz: f32, fs: f32) -> Self {
+        let (cos, alpha) = Self::omega(hz, fs, BUTTERWORTH_Q);
+        Self::normalised(
+            (1.0 + cos) / 2.0,
+            -(1.0 + cos),
+            (1.0 + cos) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        )
+    }
+
+    fn peaking(hz: f32, gain_db: f32, fs: f32) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        let (cos, alpha) = Self::omega(hz, fs, BUTTERWORTH_Q);
+        Self::normalised(
+            1.0 + alpha * a,
+            -2.0 * cos,
+            1.0 - alpha * a,
+            1.0 + alpha / a,
+            -2.0 * cos,
+            1.0 - alpha / a,
+        )
+    }
+
+    fn low_shelf(hz: f32, gain_db: f32, fs: f32) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        let (cos, alpha) = Self::omega(hz, fs, BUTTERWORTH_Q);
+        let k = 2.0 * a.sqrt() * alpha;
+        Self::normalised(
+            a * ((a + 1.0) - (a - 1.0) * cos + k),
+            2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+            a * ((a + 1.0) - (a - 1.0) * cos - k),
+            (a + 1.0) + (a - 1.0) * cos + k,
+            -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+            (a + 1.0) + (a - 1.0) * cos - k,
+        )
+    }
+
+    fn high_shelf(hz: f32, gain_db: f32, fs: f32) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        let (cos, alpha) = Self::omega(hz, fs, BUTTERWORTH_Q);
+        let k = 2.0 * a.sqrt() * alpha;
+        Self::normalised(
+            a * ((a + 1.0) + (a - 1.0) * cos + k),
+            -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+            a * ((a + 1.0) + (a - 1.0) * cos - k),
+            (a + 1.0) - (a - 1.0) * cos + k,
+            2.0 * ((a - 1.0) - (a + 1.0) * cos),
+            (a + 1.0) - (a - 1.0) * cos - k,
+        )
+    }
+
+    fn omega(hz: f32, fs: f32, q: f32) -> (f32, f32) {
+        let w0 = 2.0 * PI * hz / fs;
+        (w0.cos(), w0.sin() / (2.0 * q))
+    }
+
+    /// Transposed direct form II, `state` holds the two delay elements.
+    #[inline(always)]
+    pub(super) fn process(&self, x: f32, state: &mut [f32; 2]) -> f32 {
+        let y = self.b0 * x + state[0];
+        state[0] = self.b1 * x - self.a1 * y + state[1];
+        state[1] = self.b2 * x - self.a2 * y;
+        y
+    }
+}
+
+/// A `Source` adaptor applying the tone settings of a [`ToneControl`].
+/// Settings are re-read every few hundred samples so they can change while playing.
+pub struct ToneFilter<S> {
+    input: S,
+    control: Arc<ToneControl>,
+    version: u64,
+    stages: Vec<Biquad>,
+    /// Filter memory, `stages.len() * channels` entries.
+    state: Vec<[f32; 2]>,
+    channels: usize,
+    channel: usize,
+    until_check: usize,
+}
+
+impl<S: Source<Item = f32>> ToneFilter<S> {
+    pub fn new(input: S, control: Arc<ToneControl>) -> Self {
+        let mut filter = Self {
+            channels: input.channels().max(1) as usize,
+            input,
+            control,
+            version: u64::MAX,
+            stages: Vec::new(),
+            state: Vec::new(),
+            channel: 0,
+            until_check: 0,
+        };
+        filter.refresh();
+        filter
+    }
+
+    fn refresh(&mut self) {
+        let version = self.control.version.load(Ordering::Acquire);
+        if version == self.version {
+            return;
+        }
+        self.version = version;
+        self.stages = self.control.get().stages(self.input.sample_rate());
+        // Only reset memory when the number of stages changes to avoid clicks while tweaking
+        if self.state.len() != self.stages.len() * self.channels {
+            self.state = vec![[0.0; 2]; self.stages.len() * self.channels];
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for ToneFilter<S> {
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        let mut sample = self.input.next()?;
+
+        // Only swap coefficients on frame boundaries so channels stay aligned
+        if self.channel == 0 {
+            if self.until_check == 0 {
+                self.refresh();
+                self.until_check = CONTROL_INTERVAL;
+            }
+            self.until_check -= 1;
+        }
+
+        for (i, stage) in self.stages.iter().enumerate() {
+            sample = stage.process(sample, &mut self.state[i * self.channels + self.channel]);
+        }
+
+        self.channel = (self.channel + 1) % self.channels;
+        Some(sample)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for ToneFilter<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
@@ -0,0 +1,194 @@
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::TAU;
+use std::time::Duration;
+
+const SAMPLE_RATE: u32 = 44100;
+const CHANNELS: u16 = 2;
+
+/// Peak level of the tonal generators, kept well below full scale to match the recordings.
+const TONE_LEVEL: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GeneratorKind {
+    White,
+    Pink,
+    #[serde(alias = "red")]
+    Brown,
+    Blue,
+    Violet,
+    Binaural,
+    Isochronic,
+}
+
+/// A synthesized sound, used instead of a file via `generator = "brown"` in `sounds.toml`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Generator {
+    pub kind: GeneratorKind,
+    /// Tone frequency for binaural and isochronic generators.
+    pub carrier_hz: f32,
+    /// Beat (binaural) or pulse (isochronic) frequency.
+    pub beat_hz: f32,
+}
+
+impl Generator {
+    pub const DEFAULT_CARRIER_HZ: f32 = 200.0;
+    pub const DEFAULT_BEAT_HZ: f32 = 10.0;
+
+    /// An endless stereo source. The same `seed` always produces the same samples.
+    pub fn source(&self, seed: u64) -> GeneratorSource {
+        GeneratorSource {
+            generator: *self,
+            rng: Rng::new(seed),
+            channels: [ChannelState::default(); CHANNELS as usize],
+            channel: 0,
+            phase: [0.0; CHANNELS as usize],
+            pulse_phase: 0.0,
+        }
+    }
+}
+
+/// xorshift64*, plenty for audio noise and avoids pulling in a dependency.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // Zero is a fixed point of xorshift
+        Self(seed | 1)
+    }
+
+    /// Uniform sample in [-1, 1).
+    #[inline]
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        let bits = self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 40;
+        bits as f32 / (1u64 << 23) as f32 - 1.0
+    }
+}
+
+/// Filter memory for one channel of coloured noise.
+#[derive(Debug, Clone, Copy, Default)]
+struct ChannelState {
+    /// Paul Kellet's pink noise filter poles.
+    pink: [f32; 7],
+    brown: f32,
+    previous: f32,
+}
+
+impl ChannelState {
+    #[inline]
+    fn pink(&mut self, white: f32) -> f32 {
+        let b = &mut self.pink;
+        b[0] = 0.99886 * b[0] + white * 0.055518;
+        b[1] = 0.99332 * b[1] + white * 0.075076;
+        b[2] = 0.96900 * b[2] + white * 0.153852;
+        b[3] = 0.86650 * b[3] + white * 0.310486;
+        b[4] = 0.55000 * b[4] + white * 0.532952;
+        b[5] = -0.7616 * b[5] - white * 0.016898;
+        let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+        b[6] = white * 0.115926;
+        pink * 0.11
+    }
+
+    #[inline]
+    fn brown(&mut self, white: f32) -> f32 {
+        // Leaky integrator so the signal cannot wander off to DC
+        self.brown = (self.brown + 0.02 * white) / 1.02;
+        self.brown * 3.0
+    }
+
+    /// First difference, tilting the spectrum up by 6 dB/octave.
+    #[inline]
+    fn differentiate(&mut self, input: f32) -> f32 {
+        let out = input - self.previous;
+        self.previous = input;
+        out
+    }
+}
+
+pub struct GeneratorSource {
+    generator: Generator,
+    rng: Rng,
+    channels: [ChannelState; CHANNELS as usize],
+    channel: usize,
+    /// Carrier phase per channel, in cycles.
+    phase: [f64; CHANNELS as usize],
+    pulse_phase: f64,
+}
+
+impl GeneratorSource {
+    #[inline]
+    fn advance(phase: &mut f64, hz: f64) {
+        *phase = (*phase + hz / SAMPLE_RATE as f64).fract();
+    }
+}
+
+impl Iterator for GeneratorSource {
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        let ch = self.channel;
+        let state = &mut self.channels[ch];
+        let carrier = self.generator.carrier_hz as f64;
+        let beat = self.generator.beat_hz as f64;
+
+        let sample = match self.generator.kind {
+            GeneratorKind::White => self.rng.next() * 0.35,
+            GeneratorKind::Pink => {
+                let white = self.rng.next();
+                state.pink(white)
+            }
+            GeneratorKind::Brown => {
+                let white = self.rng.next();
+                state.brown(white)
+            }
+            GeneratorKind::Blue => {
+                let white = self.rng.next();
+                let pink = state.pink(white);
+                state.differentiate(pink) * 1.5
+            }
+            GeneratorKind::Violet => {
+                let white = self.rng.next();
+                state.differentiate(white) * 0.2
+            }
+            GeneratorKind::Binaural => {
+                // Each ear gets a slightly detuned carrier; the brain hears the difference
+                let offset = if ch == 0 { -beat / 2.0 } else { beat / 2.0 };
+                let out = (self.phase[ch] * TAU).sin() as f32 * TONE_LEVEL;
+                Self::advance(&mut self.phase[ch], carrier + offset);
+                out
+            }
+            GeneratorKind::Isochronic => {
+                let gain = 0.5 - 0.5 * (self.pulse_phase * TAU).cos();
+                let out = ((self.phase[ch] * TAU).sin() * gain) as f32 * TONE_LEVEL;
+                Self::advance(&mut self.phase[ch], carrier);
+                if ch == CHANNELS as usize - 1 {
+                    Self::advance(&mut self.pulse_phase, beat);
+                }
+                out
+            }
+        };
+
+        self.channel = (ch + 1) % CHANNELS as usize;
+        Some(sample.clamp(-1.0, 1.0))
+    }
+}
+
+impl Source for GeneratorSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+    fn channels(&self) -> u16 {
+        CHANNELS
+    }
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
@@ -0,0 +1,107 @@
+//! Loop point detection: trims leading and trailing silence, then picks the
+//! loop end whose following audio best matches what follows the loop start,
+//! so jumping back is as seamless as the recording allows.
+
+use anyhow::Result;
+use rodio::Source;
+
+/// Windows quieter than this RMS (-60 dBFS) count as silence.
+const SILENCE_RMS: f32 = 0.001;
+const WINDOW_MS: usize = 10;
+/// How far back from the end of the sound the loop end may move.
+const SEARCH_MS: usize = 1000;
+/// Audio compared after each candidate loop end.
+const MATCH_MS: usize = 10;
+
+/// Suggested loop region, in seconds into the file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoopPoints {
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Analyses the source made by `open`, which is called twice: once to find
+/// where the sound starts and stops, once to search for the loop end. Returns
+/// `None` for a silent source.
+pub fn find_loop_points<S, F>(mut open: F) -> Result<Option<LoopPoints>>
+where
+    S: Source<Item = f32>,
+    F: FnMut() -> Result<S>,
+{
+    let source = open()?;
+    let rate = source.sample_rate().max(1) as usize;
+    let window = (rate * WINDOW_MS / 1000).max(1);
+
+    // Both in frames, bounded by the first and last windows that aren't silent
+    let mut onset = None;
+    let mut offset = 0;
+    let mut energy = 0.0;
+    for (i, sample) in mono(source).enumerate() {
+        energy += sample * sample;
+        let frames = i + 1;
+        if frames % window == 0 {
+            if (energy / window as f32).sqrt() > SILENCE_RMS {
+                onset.get_or_insert(frames - window);
+                offset = frames;
+            }
+            energy = 0.0;
+        }
+    }
+    let Some(onset) = onset else {
+        return Ok(None);
+    };
+
+    let matched = (rate * MATCH_MS / 1000).max(1);
+    // Never cut away more than half the sound
+    let search = (rate * SEARCH_MS / 1000).min((offset - onset) / 2);
+    let mut end = offset;
+    if search > matched {
+        let first = offset - search;
+        let mut head = Vec::with_capacity(matched);
+        let mut tail = Vec::with_capacity(search);
+        for (i, sample) in mono(open()?).enumerate().take(offset) {
+            if i >= onset && i < onset + matched {
+                head.push(sample);
+            }
+            if i >= first {
+                tail.push(sample);
+            }
+        }
+        if head.len() == matched && tail.len() == search {
+            let head_energy: f32 = head.iter().map(|x| x * x).sum();
+            // Squared difference relative to the energy compared, so quiet
+            // stretches don't win just for being quiet
+            let cost = |at: usize| {
+                let candidate = &tail[at..at + matched];
+                let diff: f32 = candidate
+                    .iter()
+                    .zip(&head)
+                    .map(|(a, b)| (a - b) * (a - b))
+                    .sum();
+                let energy: f32 = candidate.iter().map(|x| x * x).sum();
+                diff / (energy + head_energy + f32::EPSILON)
+            };
+            if let Some(best) = (0..=search - matched).min_by(|&a, &b| cost(a).total_cmp(&cost(b)))
+            {
+                end = first + best;
+            }
+        }
+    }
+
+    Ok(Some(LoopPoints {
+        start: onset as f64 / rate as f64,
+        end: end as f64 / rate as f64,
+    }))
+}
+
+/// Averages each frame of `source` down to one sample.
+fn mono<S: Source<Item = f32>>(mut source: S) -> impl Iterator<Item = f32> {
+    let channels = source.channels().max(1) as usize;
+    std::iter::from_fn(move || {
+        let mut sum = 0.0;
+        for _ in 0..channels {
+            sum += source.next()?;
+        }
+        Some(sum / channels as f32)
+    })
+}
@@ -0,0 +1,126 @@
+//! Integrated loudness as defined by ITU-R BS.1770 / EBU R128: K-weighted mean
+//! square over 400 ms blocks, gated at -70 LUFS and again 10 LU below the mean.
+
+use super::filter::Biquad;
+use rodio::Source;
+use std::f64::consts::PI;
+use std::time::Duration;
+
+const BLOCK: Duration = Duration::from_millis(400);
+/// Blocks overlap by 75%, so a new one starts every quarter block.
+const STEPS_PER_BLOCK: usize = 4;
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const RELATIVE_GATE_LU: f64 = -10.0;
+
+/// Measures `source` up to `limit`, returning its integrated loudness in LUFS,
+/// or `None` if it is too short or too quiet to measure.
+pub fn integrated_loudness<S: Source<Item = f32>>(mut source: S, limit: Duration) -> Option<f32> {
+    let channels = source.channels().max(1) as usize;
+    let rate = source.sample_rate();
+    let step_frames = (rate as usize * BLOCK.as_millis() as usize / 1000 / STEPS_PER_BLOCK).max(1);
+    let max_frames = (limit.as_secs_f64() * rate as f64) as usize;
+
+    let mut filters: Vec<KWeighting> = (0..channels).map(|_| KWeighting::new(rate)).collect();
+    // Sum of squares of each quarter block, all channels weighted equally
+    let mut steps = Vec::new();
+    let mut energy = 0.0;
+    let mut frames = 0;
+    let mut frame = Vec::with_capacity(channels);
+
+    while frames < max_frames {
+        frame.clear();
+        frame.extend(source.by_ref().take(channels));
+        if frame.len() < channels {
+            break;
+        }
+        for (filter, &sample) in filters.iter_mut().zip(&frame) {
+            let weighted = filter.process(sample);
+            energy += weighted * weighted;
+        }
+        frames += 1;
+        if frames % step_frames == 0 {
+            steps.push(energy);
+            energy = 0.0;
+        }
+    }
+
+    let block_frames = (step_frames * STEPS_PER_BLOCK) as f64;
+    let blocks: Vec<f64> = steps
+        .windows(STEPS_PER_BLOCK)
+        .map(|w| w.iter().sum::<f64>() / block_frames)
+        .collect();
+
+    let absolute = gated_mean(&blocks, energy_of(ABSOLUTE_GATE_LUFS))?;
+    let relative = energy_of(loudness_of(absolute) + RELATIVE_GATE_LU);
+    let integrated = gated_mean(&blocks, relative.max(energy_of(ABSOLUTE_GATE_LUFS)))?;
+    Some(loudness_of(integrated) as f32)
+}
+
+fn gated_mean(blocks: &[f64], gate: f64) -> Option<f64> {
+    let passed: Vec<f64> = blocks.iter().copied().filter(|&e| e > gate).collect();
+    (!passed.is_empty()).then(|| passed.iter().sum::<f64>() / passed.len() as f64)
+}
+
+fn loudness_of(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+fn energy_of(loudness: f64) -> f64 {
+    10f64.powf((loudness + 0.691) / 10.0)
+}
+
+/// The K-weighting pre-filter: a high shelf modelling the head, then a high-pass.
+/// Coefficients are derived for the source's rate rather than taken from the
+/// 48 kHz table in the spec.
+struct KWeighting {
+    stages: [Biquad; 2],
+    state: [[f32; 2]; 2],
+}
+
+impl KWeighting {
+    fn new(rate: u32) -> Self {
+        let rate = rate as f64;
+
+        let f0 = 1_681.974_450_955_533;
+        let gain_db = 3.999_843_853_973_347;
+        let q = 0.707_175_236_955_419_6;
+        let k = (PI * f0 / rate).tan();
+        let vh = 10f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.499_666_774_154_541_6);
+        let shelf = Biquad::normalised(
+            (vh + vb * k / q + k * k) as f32,
+            (2.0 * (k * k - vh)) as f32,
+            (vh - vb * k / q + k * k) as f32,
+            (1.0 + k / q + k * k) as f32,
+            (2.0 * (k * k - 1.0)) as f32,
+            (1.0 - k / q + k * k) as f32,
+        );
+
+        let f0 = 38.135_470_876_024_44;
+        let q = 0.500_327_037_323_877_3;
+        let k = (PI * f0 / rate).tan();
+        // Only the feedback side is normalised here, the feed-forward side stays 1, -2, 1
+        let a0 = 1.0 + k / q + k * k;
+        let high_pass = Biquad::normalised(
+            a0 as f32,
+            (-2.0 * a0) as f32,
+            a0 as f32,
+            a0 as f32,
+            (2.0 * (k * k - 1.0)) as f32,
+            (1.0 - k / q + k * k) as f32,
+        );
+
+        Self {
+            stages: [shelf, high_pass],
+            state: [[0.0; 2]; 2],
+        }
+    }
+
+    fn process(&mut self, x: f32) -> f64 {
+        let mut y = x;
+        for (stage, state) in self.stages.iter().zip(&mut self.state) {
+            y = stage.process(y, state);
+        }
+        y as f64
+    }
+}
@@ -0,0 +1,15 @@
+//! Signal processing stages inserted into each sound's playback chain.
+
+pub mod drift;
+pub mod events;
+pub mod filter;
+pub mod generator;
+pub mod loop_points;
+pub mod loudness;
+pub mod pan;
+
+pub use drift::{Drift, DriftMode, DriftState};
+pub use events::Events;
+pub use filter::{Tone, ToneControl, ToneFilter};
+pub use generator::{Generator, GeneratorKind};
+pub use pan::{Panner, Spatial, SpatialControl};
@@ -0,0 +1,170 @@
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{FRAC_PI_4, SQRT_2};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Per-sample smoothing factor for gain changes, avoids zipper noise while panning.
+const SMOOTHING: f32 = 0.002;
+
+/// Stereo placement of a sound.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Spatial {
+    /// -1.0 is hard left, 0.0 centre, 1.0 hard right.
+    pub pan: f32,
+    /// Stereo width: 0.0 collapses to mono, 1.0 is unchanged, up to 2.0 widens.
+    pub width: f32,
+}
+
+impl Default for Spatial {
+    fn default() -> Self {
+        Self {
+            pan: 0.0,
+            width: 1.0,
+        }
+    }
+}
+
+impl Spatial {
+    pub fn is_centered(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn clamped(self) -> Self {
+        Self {
+            pan: self.pan.clamp(-1.0, 1.0),
+            width: self.width.clamp(0.0, 2.0),
+        }
+    }
+
+    /// Constant power pan law, normalised so the centre position is unity gain.
+    pub(super) fn gains(&self) -> (f32, f32) {
+        let angle = (self.pan.clamp(-1.0, 1.0) + 1.0) * FRAC_PI_4;
+        (
+            (angle.cos() * SQRT_2).min(1.0),
+            (angle.sin() * SQRT_2).min(1.0),
+        )
+    }
+}
+
+/// Shared handle used by the engine to move a playing [`Panner`].
+pub struct SpatialControl {
+    pan: AtomicU32,
+    width: AtomicU32,
+}
+
+impl Default for SpatialControl {
+    fn default() -> Self {
+        let spatial = Spatial::default();
+        Self {
+            pan: AtomicU32::new(spatial.pan.to_bits()),
+            width: AtomicU32::new(spatial.width.to_bits()),
+        }
+    }
+}
+
+impl SpatialControl {
+    pub fn set(&self, spatial: Spatial) {
+        self.pan.store(spatial.pan.to_bits(), Ordering::Relaxed);
+        self.width.store(spatial.width.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> Spatial {
+        Spatial {
+            pan: f32::from_bits(self.pan.load(Ordering::Relaxed)),
+            width: f32::from_bits(self.width.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// A `Source` adaptor that always outputs stereo: mono input is upmixed, stereo
+/// input gets its width adjusted, and the result is placed with the pan law.
+pub struct Panner<S> {
+    input: S,
+    control: Arc<SpatialControl>,
+    input_channels: u16,
+    spatial: Spatial,
+    /// (left gain, right gain, width) the smoothed values glide towards.
+    target: (f32, f32, f32),
+    current: (f32, f32, f32),
+    /// Right sample waiting to be emitted after the left one.
+    pending: Option<f32>,
+}
+
+impl<S: Source<Item = f32>> Panner<S> {
+    pub fn new(input: S, control: Arc<SpatialControl>) -> Self {
+        let spatial = control.get();
+        let (left, right) = spatial.gains();
+        Self {
+            input_channels: input.channels().max(1),
+            input,
+            control,
+            spatial,
+            target: (left, right, spatial.width),
+            current: (left, right, spatial.width),
+            pending: None,
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for Panner<S> {
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        if let Some(right) = self.pending.take() {
+            return Some(right);
+        }
+
+        let left_in = self.input.next()?;
+        let right_in = if self.input_channels >= 2 {
+            self.input.next().unwrap_or(left_in)
+        } else {
+            left_in
+        };
+        // Surround layouts keep only their front pair
+        for _ in 2..self.input_channels {
+            self.input.next();
+        }
+
+        let spatial = self.control.get();
+        if spatial != self.spatial {
+            let (left, right) = spatial.gains();
+            self.spatial = spatial;
+            self.target = (left, right, spatial.width.clamp(0.0, 2.0));
+        }
+        let (target_l, target_r, target_width) = self.target;
+        let (gain_l, gain_r, width) = &mut self.current;
+        *gain_l += (target_l - *gain_l) * SMOOTHING;
+        *gain_r += (target_r - *gain_r) * SMOOTHING;
+        *width += (target_width - *width) * SMOOTHING;
+
+        // Mid/side width, then pan
+        let mid = (left_in + right_in) * 0.5;
+        let side = (left_in - right_in) * 0.5 * *width;
+        let left = (mid + side) * *gain_l;
+        let right = (mid - side) * *gain_r;
+
+        self.pending = Some(right);
+        Some(left)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for Panner<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        // Output frames are a different size from the input ones; a frame
+        // boundary would only matter if the format changed mid-stream.
+        None
+    }
+    fn channels(&self) -> u16 {
+        2
+    }
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+/// Longest duration accepted, so adding one to a timestamp can't overflow.
+const MAX_SECS: u64 = 365 * 24 * 3600;
+
+/// Parses human friendly durations such as `45m`, `1h30m`, `90s` or a bare
+/// number of minutes (`45`).
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("empty duration".to_string());
+    }
+
+    if input.chars().all(|c| c.is_ascii_digit()) {
+        let minutes: u64 = input.parse().map_err(|_| too_long())?;
+        return minutes
+            .checked_mul(60)
+            .ok_or_else(too_long)
+            .and_then(capped);
+    }
+
+    let mut total = 0u64;
+    let mut number = String::new();
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        if number.is_empty() {
+            return Err(format!("invalid duration '{}'", input));
+        }
+        let value: u64 = number.parse().map_err(|_| too_long())?;
+        number.clear();
+
+        let secs = match c.to_ascii_lowercase() {
+            'h' => value.checked_mul(3600),
+            'm' => value.checked_mul(60),
+            's' => Some(value),
+            _ => return Err(format!("invalid duration unit '{}' in '{}'", c, input)),
+        };
+        total = secs
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or_else(too_long)?;
+    }
+
+    if !number.is_empty() {
+        return Err(format!("missing unit after '{}' in '{}'", number, input));
+    }
+
+    capped(total)
+}
+
+fn capped(secs: u64) -> Result<Duration, String> {
+    if secs > MAX_SECS {
+        return Err(too_long());
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+fn too_long() -> String {
+    "duration too long, the limit is a year".to_string()
+}
+
+/// Formats a countdown as `M:SS` or `H:MM:SS`.
+pub fn format_countdown(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (h, m, s) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_units_and_bare_minutes() {
+        assert_eq!(parse_duration("45"), Ok(Duration::from_secs(45 * 60)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("10m5").is_err());
+    }
+
+    #[test]
+    fn caps_at_a_year() {
+        assert_eq!(parse_duration("8760h"), Ok(Duration::from_secs(MAX_SECS)));
+        assert!(parse_duration("8761h").is_err());
+        assert!(parse_duration("525601").is_err());
+        assert!(parse_duration("18446744073709551615s").is_err());
+        assert!(parse_duration("99999999999999999999s").is_err());
+    }
+}
@@ -0,0 +1,183 @@
+//! Loudness normalisation: measures each sound file once in the background and
+//! caches the result in `loudness.toml`, keyed by path and modification time.
+
+use crate::audio::create_decoder_from_path;
+use crate::config::Config;
+use crate::dsp::loudness::integrated_loudness;
+use crate::static_data::Sound;
+use anyhow::Result;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Long ambience tracks are steady, so their start is representative and
+/// hour-long downloads don't keep the analyser busy for minutes.
+const MEASURE_LIMIT: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LoudnessCache {
+    #[serde(default)]
+    files: HashMap<String, CacheEntry>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct CacheEntry {
+    mtime: u64,
+    /// Missing for files that were silent or could not be decoded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lufs: Option<f32>,
+}
+
+impl LoudnessCache {
+    fn load() -> Self {
+        let Ok(path) = get_cache_path() else {
+            return Self::default();
+        };
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = get_cache_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The cached measurement if the file hasn't changed since; `Some(None)`
+    /// means it was measured but has no usable loudness.
+    fn lookup(&self, path: &str) -> Option<Option<f32>> {
+        let entry = self.files.get(path)?;
+        (Some(entry.mtime) == mtime(path)).then_some(entry.lufs)
+    }
+}
+
+fn mtime(path: &str) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+fn get_cache_path() -> Result<PathBuf> {
+    if let Some(proj_dirs) = ProjectDirs::from("com", "tanin", "tanin") {
+        Ok(proj_dirs.cache_dir().join("loudness.toml"))
+    } else {
+        Ok(PathBuf::from("loudness.toml"))
+    }
+}
+
+/// Fills in each sound's cached loudness and its `sounds.<id>.normalize`
+/// setting. Returns the files that still need measuring.
+pub fn annotate(sounds: &mut [Sound], config: &Config) -> Vec<String> {
+    let cache = LoudnessCache::load();
+    let mut pending = Vec::new();
+    for sound in sounds {
+        sound.normalize = config.sounds.get(&sound.id).is_none_or(|sc| sc.normalize);
+        if !sound.has_file() {
+            continue;
+        }
+        match cache.lookup(&sound.file_path) {
+            Some(lufs) => sound.loudness = lufs,
+            None => {
+                sound.loudness = None;
+                if Path::new(&sound.file_path).exists() {
+                    pending.push(sound.file_path.clone());
+                }
+            }
+        }
+    }
+    pending
+}
+
+/// A finished measurement.
+pub struct Measurement {
+    pub path: String,
+    pub lufs: Option<f32>,
+}
+
+/// Background thread measuring queued files one at a time. Kept apart from
+/// the decode workers so a long measurement never starves playback.
+pub struct Analyzer {
+    queue: Sender<String>,
+    results: Receiver<Measurement>,
+}
+
+impl Analyzer {
+    pub fn spawn() -> Self {
+        let (queue, jobs) = mpsc::channel::<String>();
+        let (done, results) = mpsc::channel();
+
+        thread::Builder::new()
+            .name("LoudnessAnalyzer".to_string())
+            .spawn(move || {
+                for path in jobs {
+                    let lufs = measure_cached(&path);
+                    if done.send(Measurement { path, lufs }).is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("Failed to spawn loudness analyzer thread");
+
+        Self { queue, results }
+    }
+
+    pub fn queue(&self, path: String) {
+        let _ = self.queue.send(path);
+    }
+
+    /// Measurements finished since the last call.
+    pub fn poll(&self) -> Vec<Measurement> {
+        self.results.try_iter().collect()
+    }
+}
+
+/// Measures `path` unless it is already cached, caching the result.
+pub fn measure_cached(path: &str) -> Option<f32> {
+    // Another Tanin process may have measured it in the meantime
+    let mut cache = LoudnessCache::load();
+    if let Some(lufs) = cache.lookup(path) {
+        return lufs;
+    }
+    let lufs = measure(path);
+    if let Some(mtime) = mtime(path) {
+        cache
+            .files
+            .insert(path.to_string(), CacheEntry { mtime, lufs });
+        if let Err(e) = cache.save() {
+            log::error!("Failed to save loudness cache: {}", e);
+        }
+    }
+    lufs
+}
+
+fn measure(path: &str) -> Option<f32> {
+    log::info!("Measuring loudness of {}", path);
+    let decoder = match create_decoder_from_path(path) {
+        Ok(decoder) => decoder,
+        Err(e) => {
+            log::error!("Cannot measure loudness of {}: {}", path, e);
+            return None;
+        }
+    };
+    let lufs = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        integrated_loudness(decoder, MEASURE_LIMIT)
+    }))
+    .unwrap_or_else(|_| {
+        log::error!("Decoder panicked while measuring {}", path);
+        None
+    });
+    match lufs {
+        Some(lufs) => log::info!("{}: {:.1} LUFS", path, lufs),
+        None => log::warn!("{} is too short or quiet to measure", path),
+    }
+    lufs
+}
@@ -1,14 +1,31 @@
+mod alarm;
 mod app;
 mod audio;
+mod cli;
 mod config;
+mod daemon;
+mod dsp;
+mod duration;
+mod loudness;
+#[cfg(feature = "mpris")]
+mod mpris;
+mod opus;
+mod output;
+mod player;
+mod pomodoro;
 mod presets;
+mod render;
+mod schedule;
 mod session;
+mod share;
+mod sleep;
 mod static_data;
 mod ui;
 mod buffered;
 
 use anyhow::Result;
-use app::{App, CurrentView};
+use app::seek::{LONG_SEEK_STEP, SEEK_STEP};
+use app::{App, CurrentView, PresetField};
 use clap::Parser;
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
@@ -16,6 +33,7 @@ use crossterm::{
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use log::LevelFilter;
+use output::OutputArgs;
 use ratatui::{backend::CrosstermBackend, Terminal};
 use simplelog::{Config, WriteLogger};
 use std::io;
@@ -30,6 +48,20 @@ struct Args {
     /// Enable debug logging to tanin.log
     #[arg(short, long)]
     debug: bool,
+
+    /// Start a sleep timer, e.g. `45m`, `1h30m` or `90` (minutes)
+    #[arg(long, value_parser = duration::parse_duration)]
+    sleep: Option<Duration>,
+
+    /// List audio output devices and exit
+    #[arg(long)]
+    list_devices: bool,
+
+    #[command(flatten)]
+    output: OutputArgs,
+
+    #[command(subcommand)]
+    command: Option<cli::Command>,
 }
 
 fn main() -> Result<()> {
@@ -45,6 +77,13 @@ fn main() -> Result<()> {
 
         let _ = WriteLogger::init(LevelFilter::Debug, Config::default(), log_file_clone);
         log::info!("Starting Tanin in debug mode");
+    } else if let Some(command) = &args.command {
+        // Headless commands have no TUI to corrupt, so log straight to stderr
+        let level = match command {
+            cli::Command::Daemon => LevelFilter::Info,
+            _ => LevelFilter::Warn,
+        };
+        let _ = WriteLogger::init(level, Config::default(), io::stderr());
     } else {
         // Redirect stderr to /dev/null to suppress errors in TUI
         if let Ok(dev_null) = File::open("/dev/null") {
@@ -53,6 +92,14 @@ fn main() -> Result<()> {
         }
     }
 
+    if args.list_devices {
+        return cli::list_devices();
+    }
+
+    if let Some(command) = args.command {
+        return cli::run(command, &args.output);
+    }
+
     // Register panic hook to restore terminal and log panic
     panic::set_hook(Box::new(|info| {
         // Attempt to restore terminal state first so user can see output
@@ -90,7 +137,10 @@ fn main() -> Result<()> {
     let mut terminal = Terminal::new(backend)?;
 
     // Create app
-    let mut app = App::new()?;
+    let mut app = App::new(&args.output)?;
+    if let Some(sleep) = args.sleep {
+        app.set_sleep_timer(Some(sleep));
+    }
 
     // Run loop
     let res = run_app(&mut terminal, &mut app);
@@ -150,12 +200,22 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App)
                         } else if app.preset_input_mode {
                             match key.code {
                                 KeyCode::Enter => {
-                                    app.confirm_preset_input();
+                                    if app.preset_import_input {
+                                        app.confirm_preset_import();
+                                    } else if let Some(field) = app.preset_edit_field {
+                                        app.confirm_preset_field(field);
+                                    } else {
+                                        app.confirm_preset_input();
+                                    }
                                     app.preset_input_mode = false;
+                                    app.preset_import_input = false;
+                                    app.preset_edit_field = None;
                                     app.preset_input_buffer.clear();
                                 }
                                 KeyCode::Esc => {
                                     app.preset_input_mode = false;
+                                    app.preset_import_input = false;
+                                    app.preset_edit_field = None;
                                     app.preset_rename_target = None;
                                     app.preset_input_buffer.clear();
                                 }
@@ -167,6 +227,25 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App)
                                 }
                                 _ => {}
                             }
+                        } else if app.alarm_input_mode {
+                            match key.code {
+                                KeyCode::Enter => {
+                                    app.confirm_alarm_input();
+                                    app.alarm_input_mode = false;
+                                    app.alarm_input_buffer.clear();
+                                }
+                                KeyCode::Esc => {
+                                    app.alarm_input_mode = false;
+                                    app.alarm_input_buffer.clear();
+                                }
+                                KeyCode::Backspace => {
+                                    app.alarm_input_buffer.pop();
+                                }
+                                KeyCode::Char(c) => {
+                                    app.alarm_input_buffer.push(c);
+                                }
+                                _ => {}
+                            }
                         } else if app.search_mode {
                             match key.code {
                                 KeyCode::Enter => {
@@ -187,6 +266,24 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App)
                                 }
                                 _ => {}
                             }
+                        } else if app.view == CurrentView::Devices {
+                            match key.code {
+                                KeyCode::Char('q') => {
+                                    app.quitting = true;
+                                    return Ok(());
+                                }
+                                _ => handle_devices_keys(app, key.code),
+                            }
+                        } else if app.view == CurrentView::Details {
+                            match key.code {
+                                KeyCode::Char('q') => {
+                                    app.quitting = true;
+                                    return Ok(());
+                                }
+                                KeyCode::Char('n') => app.toggle_current_normalize(),
+                                KeyCode::Esc | KeyCode::Char('i') => app.view = CurrentView::Main,
+                                _ => {}
+                            }
                         } else if app.view == CurrentView::Downloads {
                             if key.code == KeyCode::Tab {
                                 app.view = CurrentView::Main;
@@ -220,7 +317,9 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App)
                                 KeyCode::Tab => {
                                     app.view = match app.view {
                                         CurrentView::Main => CurrentView::Presets,
-                                        CurrentView::Presets => {
+                                        CurrentView::Presets => CurrentView::Schedule,
+                                        CurrentView::Schedule => CurrentView::Alarms,
+                                        CurrentView::Alarms => {
                                             if app.yt_dlp_available {
                                                 CurrentView::Downloads
                                             } else {
@@ -248,12 +347,26 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App)
                                     }
                                 }
 
+                                // Sound Details
+                                KeyCode::Char('i') if app.view == CurrentView::Main => {
+                                    app.open_details()
+                                }
+
+                                // Output Device
+                                KeyCode::Char('o') if app.view == CurrentView::Main => {
+                                    app.open_devices()
+                                }
+
                                 // Master Mute
                                 KeyCode::Char('m') => app.toggle_mute(),
 
                                 _ => match app.view {
                                     CurrentView::Main => handle_main_keys(app, key.code),
                                     CurrentView::Presets => handle_presets_keys(app, key.code),
+                                    CurrentView::Schedule if key.code == KeyCode::Char('r') => {
+                                        app.reload_schedule()
+                                    }
+                                    CurrentView::Alarms => handle_alarms_keys(app, key.code),
                                     CurrentView::Downloads => handle_add_sound_keys(app, key),
                                     CurrentView::AssetMissing => match key.code {
                                         KeyCode::Enter => app.start_asset_download(),
@@ -345,27 +458,87 @@ fn handle_main_keys(app: &mut App, code: KeyCode) {
             app.set_master_volume(app.session.global_volume + 0.1);
         }
 
+        // Tone
+        KeyCode::Char('}') => app.step_low_pass(1),
+        KeyCode::Char('{') => app.step_low_pass(-1),
+        KeyCode::Char(')') => app.step_high_pass(1),
+        KeyCode::Char('(') => app.step_high_pass(-1),
+        KeyCode::Char('e') => app.cycle_eq_profile(),
+        KeyCode::Char('E') => app.reset_current_tone(),
+
+        // Pan & Width
+        KeyCode::Char('H') => app.pan_current(-1.0),
+        KeyCode::Char('L') => app.pan_current(1.0),
+        KeyCode::Char('w') => app.step_current_width(1.0),
+        KeyCode::Char('W') => app.step_current_width(-1.0),
+        KeyCode::Char('C') => app.center_current(),
+
+        // Volume Drift
+        KeyCode::Char('v') => app.cycle_current_drift(),
+
+        // Seeking
+        KeyCode::Char('f') => app.seek_current(SEEK_STEP),
+        KeyCode::Char('b') => app.seek_current(-SEEK_STEP),
+        KeyCode::Char('F') => app.seek_current(LONG_SEEK_STEP),
+        KeyCode::Char('B') => app.seek_current(-LONG_SEEK_STEP),
+
         // Stop All
         KeyCode::Char('s') => app.stop_all(),
+        KeyCode::Char('r') => app.resume_all(),
 
+        // Preset Cycling
+        KeyCode::Char(']') => app.cycle_preset(1),
+        KeyCode::Char('[') => app.cycle_preset(-1),
+
+        // Sleep Timer
+        KeyCode::Char('t') => app.cycle_sleep_timer(),
+
+        // Pomodoro
+        KeyCode::Char('p') => app.toggle_pomodoro(),
+        KeyCode::Char('P') => app.skip_pomodoro_phase(),
+
+        _ => {}
+    }
+}
+
+fn handle_devices_keys(app: &mut App, code: KeyCode) {
+    match code {
+        KeyCode::Up | KeyCode::Char('k') => app.device_up(),
+        KeyCode::Down | KeyCode::Char('j') => app.device_down(),
+        KeyCode::Enter => {
+            app.select_device();
+            app.view = CurrentView::Main;
+        }
+        KeyCode::Esc | KeyCode::Tab | KeyCode::Char('o') => app.view = CurrentView::Main,
         _ => {}
     }
 }
 
 fn handle_presets_keys(app: &mut App, code: KeyCode) {
+    if !app.preset_downloads.is_empty() {
+        match code {
+            KeyCode::Char('y') => return app.queue_preset_downloads(),
+            KeyCode::Char('n') => return app.skip_preset_downloads(),
+            _ => {}
+        }
+    }
     match code {
         KeyCode::Up | KeyCode::Char('k') => {
-            if app.preset_cursor_pos > 0 {
-                app.preset_cursor_pos -= 1;
-            }
+            app.move_preset_cursor(-1);
         }
         KeyCode::Down | KeyCode::Char('j') => {
-            if app.preset_cursor_pos < app.presets_config.presets.len().saturating_sub(1) {
-                app.preset_cursor_pos += 1;
-            }
+            app.move_preset_cursor(1);
+        }
+        KeyCode::Char('K') => {
+            app.move_preset(-1);
+        }
+        KeyCode::Char('J') => {
+            app.move_preset(1);
         }
         KeyCode::Enter => {
-            app.load_preset(app.preset_cursor_pos);
+            if let Some(index) = app.selected_preset() {
+                app.load_preset(index);
+            }
         }
         KeyCode::Char('n') => {
             app.preset_input_mode = true;
@@ -377,8 +550,63 @@ fn handle_presets_keys(app: &mut App, code: KeyCode) {
             app.update_preset_sounds();
         }
         KeyCode::Char('d') => {
-            app.delete_preset(app.preset_cursor_pos);
+            if let Some(index) = app.selected_preset() {
+                app.delete_preset(index);
+            }
+        }
+        KeyCode::Char('x') => {
+            if let Some(index) = app.selected_preset() {
+                app.export_preset(index);
+            }
+        }
+        KeyCode::Char('c') => {
+            if let Some(index) = app.selected_preset() {
+                app.copy_preset_code(index);
+            }
+        }
+        KeyCode::Char('i') => {
+            app.preset_input_mode = true;
+            app.preset_import_input = true;
+        }
+        KeyCode::Char('f') => {
+            app.toggle_preset_favourite();
+        }
+        KeyCode::Char('s') => {
+            app.cycle_preset_sort();
+        }
+        KeyCode::Char('t') => {
+            app.cycle_preset_tag_filter();
+        }
+        KeyCode::Char('D') => {
+            app.start_editing_preset(PresetField::Description);
+        }
+        KeyCode::Char('T') => {
+            app.start_editing_preset(PresetField::Tags);
+        }
+        KeyCode::Char('I') => {
+            app.start_editing_preset(PresetField::Icon);
+        }
+        _ => {}
+    }
+}
+
+fn handle_alarms_keys(app: &mut App, code: KeyCode) {
+    match code {
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.alarm_cursor_pos = app.alarm_cursor_pos.saturating_sub(1);
+        }
+        KeyCode::Down | KeyCode::Char('j') => {
+            app.alarm_cursor_pos =
+                (app.alarm_cursor_pos + 1).min(app.alarms.config.alarms.len().saturating_sub(1));
+        }
+        KeyCode::Char('n') => {
+            app.alarm_input_mode = true;
+            app.alarm_error = None;
         }
+        KeyCode::Enter | KeyCode::Char(' ') => app.toggle_alarm(app.alarm_cursor_pos),
+        KeyCode::Char('+') | KeyCode::Char('=') => app.step_alarm_fade(app.alarm_cursor_pos, 1),
+        KeyCode::Char('-') | KeyCode::Char('_') => app.step_alarm_fade(app.alarm_cursor_pos, -1),
+        KeyCode::Char('d') => app.delete_alarm(app.alarm_cursor_pos),
         _ => {}
     }
 }
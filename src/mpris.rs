@@ -0,0 +1,350 @@
+//! MPRIS (`org.mpris.MediaPlayer2`) integration so media keys, desktop shell
+//! widgets and `playerctl` can control Tanin over the D-Bus session bus.
+//!
+//! D-Bus calls arrive on zbus' own thread; they are forwarded to the app as
+//! [`MprisCommand`]s and the app publishes its state back once per frame.
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
+use zbus::blocking::connection::Builder;
+use zbus::blocking::object_server::InterfaceRef;
+use zbus::blocking::Connection;
+use zbus::interface;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.tanin";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const TRACK_ID: &str = "/com/tanin/Mix";
+/// How far the position may stray from where playback alone would have taken
+/// it before it counts as a seek.
+const SEEK_TOLERANCE: Duration = Duration::from_secs(1);
+
+pub enum MprisCommand {
+    PlayPause,
+    Play,
+    Pause,
+    Stop,
+    Next,
+    Previous,
+    /// Jump the selected sound by this many seconds.
+    Seek(f64),
+    SetPosition(Duration),
+    SetVolume(f32),
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    #[default]
+    Stopped,
+}
+
+/// Everything Tanin exposes through the `Player` interface.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MprisState {
+    pub status: PlaybackStatus,
+    pub volume: f32,
+    pub preset: Option<String>,
+    pub sounds: Vec<String>,
+    pub has_presets: bool,
+    /// How far the selected sound is into its file, if it is playing one.
+    pub position: Option<Duration>,
+    pub length: Option<Duration>,
+}
+
+struct RootInterface {
+    tx: Sender<MprisCommand>,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl RootInterface {
+    fn raise(&self) {}
+
+    fn quit(&self) {
+        let _ = self.tx.send(MprisCommand::Quit);
+    }
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> String {
+        "Tanin".to_string()
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+struct PlayerInterface {
+    tx: Sender<MprisCommand>,
+    state: MprisState,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl PlayerInterface {
+    fn next(&self) {
+        let _ = self.tx.send(MprisCommand::Next);
+    }
+
+    fn previous(&self) {
+        let _ = self.tx.send(MprisCommand::Previous);
+    }
+
+    fn pause(&self) {
+        let _ = self.tx.send(MprisCommand::Pause);
+    }
+
+    fn play_pause(&self) {
+        let _ = self.tx.send(MprisCommand::PlayPause);
+    }
+
+    fn stop(&self) {
+        let _ = self.tx.send(MprisCommand::Stop);
+    }
+
+    fn play(&self) {
+        let _ = self.tx.send(MprisCommand::Play);
+    }
+
+    // Positions are those of the selected sound, the only one a seek can move.
+    fn seek(&self, offset: i64) {
+        if self.state.position.is_some() {
+            let _ = self.tx.send(MprisCommand::Seek(offset as f64 / 1e6));
+        }
+    }
+
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) {
+        if track_id.as_str() != TRACK_ID || self.state.position.is_none() || position < 0 {
+            return;
+        }
+        let position = Duration::from_micros(position as u64);
+        if self.state.length.is_some_and(|length| position > length) {
+            return;
+        }
+        let _ = self.tx.send(MprisCommand::SetPosition(position));
+    }
+
+    fn open_uri(&self, _uri: &str) {}
+
+    #[zbus(property)]
+    fn playback_status(&self) -> String {
+        format!("{:?}", self.state.status)
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        let title = self
+            .state
+            .preset
+            .clone()
+            .unwrap_or_else(|| "Tanin".to_string());
+
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            "mpris:trackid".to_string(),
+            owned(ObjectPath::from_static_str_unchecked(TRACK_ID)),
+        );
+        metadata.insert("xesam:title".to_string(), owned(title));
+        metadata.insert("xesam:artist".to_string(), owned(self.state.sounds.clone()));
+        metadata.insert("xesam:album".to_string(), owned("Tanin".to_string()));
+        if let Some(length) = self.state.length {
+            metadata.insert("mpris:length".to_string(), owned(micros(length)));
+        }
+        metadata
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        self.state.volume as f64
+    }
+
+    #[zbus(property)]
+    fn set_volume(&mut self, volume: f64) {
+        let _ = self
+            .tx
+            .send(MprisCommand::SetVolume(volume.clamp(0.0, 1.0) as f32));
+    }
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        self.state.position.map_or(0, micros)
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        self.state.has_presets
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        self.state.has_presets
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        self.state.position.is_some()
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_control(&self) -> bool {
+        true
+    }
+
+    #[zbus(signal)]
+    async fn seeked(emitter: &SignalEmitter<'_>, position: i64) -> zbus::Result<()>;
+}
+
+fn micros(duration: Duration) -> i64 {
+    duration.as_micros() as i64
+}
+
+fn owned<'a>(value: impl Into<Value<'a>>) -> OwnedValue {
+    value
+        .into()
+        .try_to_owned()
+        .expect("metadata values never contain file descriptors")
+}
+
+pub struct MprisServer {
+    _connection: Connection,
+    player: InterfaceRef<PlayerInterface>,
+    rx: Receiver<MprisCommand>,
+    state: MprisState,
+    published_at: Instant,
+}
+
+impl MprisServer {
+    pub fn start() -> zbus::Result<Self> {
+        let (tx, rx) = mpsc::channel();
+        let connection = Builder::session()?
+            .name(BUS_NAME)?
+            .serve_at(OBJECT_PATH, RootInterface { tx: tx.clone() })?
+            .serve_at(
+                OBJECT_PATH,
+                PlayerInterface {
+                    tx,
+                    state: MprisState::default(),
+                },
+            )?
+            .build()?;
+
+        let player = connection
+            .object_server()
+            .interface::<_, PlayerInterface>(OBJECT_PATH)?;
+
+        log::info!("MPRIS interface registered as {}", BUS_NAME);
+
+        Ok(Self {
+            _connection: connection,
+            player,
+            rx,
+            state: MprisState::default(),
+            published_at: Instant::now(),
+        })
+    }
+
+    pub fn poll(&self) -> Vec<MprisCommand> {
+        self.rx.try_iter().collect()
+    }
+
+    /// Updates the exported state and emits `PropertiesChanged` for whatever
+    /// differs, or `Seeked` if the position jumped.
+    pub fn publish(&mut self, state: MprisState) {
+        let elapsed = self.published_at.elapsed();
+        self.published_at = Instant::now();
+        if state == self.state {
+            return;
+        }
+
+        let previous = std::mem::replace(&mut self.state, state.clone());
+        let seeked = match (previous.position, state.position) {
+            (Some(before), Some(after)) => {
+                let expected = before + elapsed;
+                after.max(expected) - after.min(expected) > SEEK_TOLERANCE
+            }
+            _ => false,
+        };
+        self.player.get_mut().state = state.clone();
+
+        let iface = self.player.get();
+        let emitter = self.player.signal_emitter();
+        let result = zbus::block_on(async {
+            if previous.status != state.status {
+                iface.playback_status_changed(emitter).await?;
+            }
+            if previous.volume != state.volume {
+                iface.volume_changed(emitter).await?;
+            }
+            if previous.preset != state.preset
+                || previous.sounds != state.sounds
+                || previous.length != state.length
+            {
+                iface.metadata_changed(emitter).await?;
+            }
+            if previous.position.is_some() != state.position.is_some() {
+                iface.can_seek_changed(emitter).await?;
+            }
+            if let Some(position) = state.position.filter(|_| seeked) {
+                PlayerInterface::seeked(emitter, micros(position)).await?;
+            }
+            if previous.has_presets != state.has_presets {
+                iface.can_go_next_changed(emitter).await?;
+                iface.can_go_previous_changed(emitter).await?;
+            }
+            zbus::Result::Ok(())
+        });
+
+        if let Err(e) = result {
+            log::error!("Failed to emit MPRIS property changes: {}", e);
+        }
+    }
+}
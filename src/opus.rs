@@ -0,0 +1,293 @@
+//! Opus in an Ogg container (RFC 7845), the format of every sound yt-dlp
+//! downloads. Decoded here rather than by rodio so long tracks can seek and
+//! report their length.
+
+use anyhow::{anyhow, Context, Result};
+use audiopus::coder::Decoder;
+use audiopus::{Channels, SampleRate};
+use ogg::{Packet, PacketReader};
+use rodio::source::SeekError;
+use rodio::Source;
+use std::io::{Read, Seek, SeekFrom};
+use std::time::Duration;
+
+/// Opus always decodes at 48 kHz, whatever the file says it was made from.
+const SAMPLE_RATE: u32 = 48_000;
+/// Frames in the longest Opus packet, 120 ms.
+const MAX_PACKET_FRAMES: usize = 5760;
+/// Frames decoded ahead of a seek target so the decoder has settled by then.
+const SEEK_PREROLL: u64 = 3840;
+/// How far from the end of the file the last page is looked for.
+const TAIL_SCAN_BYTES: u64 = 64 * 1024;
+/// A granule position of -1 marks a page on which no packet ends.
+const NO_GRANULE: u64 = u64::MAX;
+
+pub struct OggOpusSource<R: Read + Seek> {
+    reader: PacketReader<R>,
+    decoder: Decoder,
+    serial: u32,
+    channels: u16,
+    /// Output gain from the header, in Q7.8 dB.
+    gain: i32,
+    /// Frames at the start that only prime the decoder.
+    pre_skip: u64,
+    /// Granule position of the last page, where playback ends.
+    end_granule: Option<u64>,
+    /// Decoded interleaved samples, read from `cursor` on.
+    buffer: Vec<f32>,
+    cursor: usize,
+    /// Granule position at the end of `buffer`.
+    granule: u64,
+}
+
+impl<R: Read + Seek> OggOpusSource<R> {
+    pub fn new(mut input: R) -> Result<Self> {
+        let end_granule = last_granule(&mut input)?;
+        input.seek(SeekFrom::Start(0))?;
+
+        let mut reader = PacketReader::new(input);
+        let head = reader
+            .read_packet_expected()
+            .context("Missing Opus header")?;
+        let data = &head.data;
+        if data.len() < 19 || &data[..8] != b"OpusHead" {
+            return Err(anyhow!("Not an Opus stream"));
+        }
+        let channels = data[9] as u16;
+        // Mapping family 0 is mono or stereo; more channels need a multistream decoder
+        if !(1..=2).contains(&channels) {
+            return Err(anyhow!("Unsupported Opus channel count {}", channels));
+        }
+        let pre_skip = u16::from_le_bytes([data[10], data[11]]) as u64;
+        let gain = i16::from_le_bytes([data[16], data[17]]) as i32;
+        let serial = head.stream_serial();
+        reader.read_packet_expected().context("Missing Opus tags")?;
+
+        let mut source = Self {
+            reader,
+            decoder: new_decoder(channels, gain)?,
+            serial,
+            channels,
+            gain,
+            pre_skip,
+            end_granule,
+            buffer: Vec::new(),
+            cursor: 0,
+            granule: 0,
+        };
+        source.skip_to(pre_skip);
+        Ok(source)
+    }
+
+    fn next_packet(&mut self) -> Result<Option<Packet>> {
+        while let Some(packet) = self.reader.read_packet()? {
+            // Header pages have a granule position of zero and a seek can land
+            // on them. Some encoders end the stream with empty packets.
+            if packet.stream_serial() == self.serial
+                && packet.absgp_page() != 0
+                && !packet.data.is_empty()
+            {
+                return Ok(Some(packet));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Appends the audio of `packet` to `buffer`, returning its frame count.
+    fn decode_packet(&mut self, packet: &Packet) -> Result<u64> {
+        let channels = self.channels as usize;
+        let start = self.buffer.len();
+        self.buffer
+            .resize(start + MAX_PACKET_FRAMES * channels, 0.0);
+        let frames = self
+            .decoder
+            .decode_float(Some(&packet.data), &mut self.buffer[start..], false)
+            .map_err(|e| anyhow!("Opus decode failed: {}", e))?;
+        self.buffer.truncate(start + frames * channels);
+        Ok(frames as u64)
+    }
+
+    /// Decodes the next packet into `buffer`, returning false at the end of
+    /// the stream.
+    fn decode_next(&mut self) -> Result<bool> {
+        let Some(packet) = self.next_packet()? else {
+            return Ok(false);
+        };
+        let before = self.granule;
+        self.granule += self.decode_packet(&packet)?;
+        let end = packet.absgp_page();
+        if packet.last_in_page() && end != NO_GRANULE {
+            // The final page may end part way through its last packet
+            if packet.last_in_stream() && end < self.granule {
+                let padding = (self.granule - end.max(before)) as usize;
+                let len = self
+                    .buffer
+                    .len()
+                    .saturating_sub(padding * self.channels as usize);
+                self.buffer.truncate(len);
+            }
+            self.granule = end;
+        }
+        Ok(true)
+    }
+
+    /// Drops decoded audio up to the granule position `goal`.
+    fn skip_to(&mut self, goal: u64) {
+        let channels = self.channels as usize;
+        loop {
+            let buffered = ((self.buffer.len() - self.cursor) / channels) as u64;
+            if goal < self.granule {
+                let buffer_start = self.granule.saturating_sub(buffered);
+                self.cursor += goal.saturating_sub(buffer_start) as usize * channels;
+                return;
+            }
+            self.buffer.clear();
+            self.cursor = 0;
+            match self.decode_next() {
+                Ok(true) => {}
+                Ok(false) => return,
+                Err(e) => {
+                    log::error!("{}", e);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Goes back to the first audio packet.
+    fn rewind(&mut self) -> Result<()> {
+        self.reader.seek_bytes(SeekFrom::Start(0))?;
+        self.reset()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.decoder = new_decoder(self.channels, self.gain)?;
+        self.buffer.clear();
+        self.cursor = 0;
+        self.granule = 0;
+        Ok(())
+    }
+
+    /// Bisects to a page a little before `goal`, decodes up to the end of a page
+    /// so the granule position of the decoded audio is known, then drops the
+    /// audio before `goal`.
+    fn seek_granule(&mut self, goal: u64) -> Result<()> {
+        let mut landing = goal.saturating_sub(SEEK_PREROLL);
+        loop {
+            if landing <= self.pre_skip {
+                self.rewind()?;
+                break;
+            }
+            if !self.reader.seek_absgp(Some(self.serial), landing)? {
+                return Err(anyhow!("Position is past the end of the stream"));
+            }
+            self.reset()?;
+
+            let mut anchor = None;
+            while let Some(packet) = self.next_packet()? {
+                self.decode_packet(&packet)?;
+                if packet.last_in_page() && packet.absgp_page() != NO_GRANULE {
+                    anchor = Some(packet);
+                    break;
+                }
+            }
+            match anchor {
+                // The final page may be shorter than its packets, so its end
+                // doesn't say where they began; anchor on an earlier page
+                Some(packet) if !packet.last_in_stream() => {
+                    self.granule = packet.absgp_page();
+                    break;
+                }
+                _ => landing = landing.saturating_sub(SAMPLE_RATE as u64),
+            }
+        }
+        self.skip_to(goal);
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> Iterator for OggOpusSource<R> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        while self.cursor >= self.buffer.len() {
+            self.buffer.clear();
+            self.cursor = 0;
+            match self.decode_next() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(e) => {
+                    log::error!("{}", e);
+                    return None;
+                }
+            }
+        }
+        let sample = self.buffer[self.cursor];
+        self.cursor += 1;
+        Some(sample)
+    }
+}
+
+impl<R: Read + Seek> Source for OggOpusSource<R> {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        let frames = self.end_granule?.saturating_sub(self.pre_skip);
+        Some(Duration::from_secs_f64(frames as f64 / SAMPLE_RATE as f64))
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        let goal = self.pre_skip + (pos.as_secs_f64() * SAMPLE_RATE as f64) as u64;
+        self.seek_granule(goal)
+            .map_err(|e| SeekError::Other(e.into_boxed_dyn_error()))
+    }
+}
+
+fn new_decoder(channels: u16, gain: i32) -> Result<Decoder> {
+    let channels = if channels == 1 {
+        Channels::Mono
+    } else {
+        Channels::Stereo
+    };
+    let decoder = Decoder::new(SampleRate::Hz48000, channels)
+        .map_err(|e| anyhow!("Failed to create Opus decoder: {}", e))?;
+    decoder
+        .set_gain(gain)
+        .map_err(|e| anyhow!("Failed to set Opus output gain: {}", e))?;
+    Ok(decoder)
+}
+
+/// Granule position of the last page of the file's first stream, read from the
+/// page headers near the end of the file.
+fn last_granule<R: Read + Seek>(input: &mut R) -> Result<Option<u64>> {
+    let mut first = [0u8; 27];
+    input.seek(SeekFrom::Start(0))?;
+    input.read_exact(&mut first)?;
+    if &first[..4] != b"OggS" {
+        return Err(anyhow!("Not an Ogg file"));
+    }
+    let serial = &first[14..18];
+
+    let len = input.seek(SeekFrom::End(0))?;
+    input.seek(SeekFrom::Start(len.saturating_sub(TAIL_SCAN_BYTES)))?;
+    let mut tail = Vec::new();
+    input.read_to_end(&mut tail)?;
+
+    let granule = (0..tail.len().saturating_sub(26))
+        .rev()
+        .filter(|&i| &tail[i..i + 4] == b"OggS" && tail[i + 4] == 0)
+        .filter(|&i| &tail[i + 14..i + 18] == serial)
+        .map(|i| u64::from_le_bytes(tail[i + 6..i + 14].try_into().unwrap()))
+        .find(|&granule| granule != NO_GRANULE);
+    Ok(granule)
+}
@@ -0,0 +1,495 @@
+//! Where the engine's mixed audio goes: a sound card through cpal, nowhere, or
+//! a WAV file. The last two need no audio device, so Tanin also runs on
+//! headless machines and CI boxes.
+
+use crate::config::AudioConfig;
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{
+    BufferSize, BuildStreamError, FromSample, HostId, SampleRate, SizedSample, StreamConfig,
+    SupportedBufferSize,
+};
+use hound::{SampleFormat, WavSpec, WavWriter};
+use rodio::dynamic_mixer::{self, DynamicMixer, DynamicMixerController};
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const CHANNELS: u16 = 2;
+
+/// How often the null and WAV backends pull from their mixer.
+const PUMP_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How often the WAV backend rewrites the header, so the file stays playable
+/// even if Tanin is killed.
+const WAV_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// The system's sound card
+    #[default]
+    Cpal,
+    /// Discard the audio, consuming it in real time
+    Null,
+    /// Record the audio to a WAV file
+    Wav,
+}
+
+/// Output overrides shared by the TUI and every subcommand.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct OutputArgs {
+    /// Audio output, overriding `audio.backend` in config.toml
+    #[arg(long, global = true, value_enum)]
+    pub backend: Option<BackendKind>,
+    /// File written by the wav backend, overriding `audio.wav_file`
+    #[arg(long, global = true)]
+    pub wav_file: Option<PathBuf>,
+}
+
+impl OutputArgs {
+    /// The audio settings from config.toml with these overrides applied.
+    pub fn apply(&self, audio: &AudioConfig) -> AudioConfig {
+        let mut audio = audio.clone();
+        if let Some(backend) = self.backend {
+            audio.backend = backend;
+        }
+        if let Some(path) = &self.wav_file {
+            audio.wav_file = Some(path.clone());
+        }
+        audio
+    }
+}
+
+/// A destination the engine's sinks can play into.
+pub trait OutputBackend {
+    /// Starts mixing `source` into the output until it ends.
+    fn play(&self, source: Box<dyn Source<Item = f32> + Send>) -> Result<()>;
+}
+
+pub fn open(config: &AudioConfig) -> Result<Box<dyn OutputBackend>> {
+    match config.backend {
+        BackendKind::Cpal => Ok(Box::new(CpalBackend::new(config)?)),
+        BackendKind::Null => {
+            log::info!("Using the null audio backend");
+            Ok(Box::new(MixerBackend::spawn(
+                config.sample_rate,
+                |_| Ok(()),
+            )))
+        }
+        BackendKind::Wav => {
+            let path = config
+                .wav_file
+                .clone()
+                .unwrap_or_else(|| PathBuf::from("tanin.wav"));
+            let spec = WavSpec {
+                channels: CHANNELS,
+                sample_rate: config.sample_rate,
+                bits_per_sample: 32,
+                sample_format: SampleFormat::Float,
+            };
+            let file = File::create(&path)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+            let mut writer = WavWriter::new(BufWriter::new(file), spec)?;
+            log::info!("Recording audio to {}", path.display());
+
+            let mut last_flush = Instant::now();
+            Ok(Box::new(MixerBackend::spawn(
+                config.sample_rate,
+                move |samples| {
+                    for &sample in samples {
+                        writer.write_sample(sample)?;
+                    }
+                    if last_flush.elapsed() >= WAV_FLUSH_INTERVAL {
+                        writer.flush()?;
+                        last_flush = Instant::now();
+                    }
+                    Ok(())
+                },
+            )))
+        }
+    }
+}
+
+/// The sound card, through cpal. The stream is opened by hand rather than
+/// through rodio's `OutputStream` so the configured rate and buffer size apply.
+pub struct CpalBackend {
+    _stream: cpal::Stream,
+    controller: Arc<DynamicMixerController<f32>>,
+}
+
+impl CpalBackend {
+    pub fn new(config: &AudioConfig) -> Result<Self> {
+        let (device, host_name) = choose_device(config)?;
+        let (stream_config, sample_format) = stream_config(&device, config)?;
+
+        let (controller, stream) = match build_stream(&device, &stream_config, sample_format) {
+            Ok(built) => built,
+            Err(e) if stream_config.buffer_size != BufferSize::Default => {
+                log::warn!(
+                    "Device refused the configured buffer size ({}), using its default",
+                    e
+                );
+                let stream_config = StreamConfig {
+                    buffer_size: BufferSize::Default,
+                    ..stream_config.clone()
+                };
+                build_stream(&device, &stream_config, sample_format)
+                    .map_err(|e| anyhow!("Failed to open output stream: {}", e))?
+            }
+            Err(e) => return Err(anyhow!("Failed to open output stream: {}", e)),
+        };
+        stream
+            .play()
+            .map_err(|e| anyhow!("Failed to start output stream: {}", e))?;
+
+        log::info!(
+            "Audio output opened using {}: {} Hz, {} channels, buffer {:?}",
+            host_name,
+            stream_config.sample_rate.0,
+            stream_config.channels,
+            stream_config.buffer_size
+        );
+        Ok(Self {
+            _stream: stream,
+            controller,
+        })
+    }
+}
+
+impl OutputBackend for CpalBackend {
+    fn play(&self, source: Box<dyn Source<Item = f32> + Send>) -> Result<()> {
+        // The mixer converts the source to the stream's rate and channel count
+        self.controller.add(source);
+        Ok(())
+    }
+}
+
+/// The configured device if it exists, otherwise the default device of the
+/// preferred host (JACK, then ALSA), otherwise the system default.
+fn choose_device(config: &AudioConfig) -> Result<(cpal::Device, &'static str)> {
+    if let Some(name) = &config.device {
+        match find_device(config.host.as_deref(), name) {
+            Some(device) => {
+                log::info!("Using configured audio device: {}", name);
+                return Ok((device, "configured device"));
+            }
+            None => log::warn!("Audio device '{}' not found, using the default", name),
+        }
+    }
+
+    let available_hosts = cpal::available_hosts();
+    log::info!("Available audio hosts: {:?}", available_hosts);
+
+    #[allow(unused_mut)]
+    let mut priority_hosts: Vec<(HostId, &'static str)> = Vec::new();
+
+    #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd"))]
+    {
+        #[cfg(feature = "jack")]
+        priority_hosts.push((HostId::Jack, "JACK"));
+        priority_hosts.push((HostId::Alsa, "ALSA"));
+    }
+
+    for &(host_id, name_str) in &priority_hosts {
+        if available_hosts.contains(&host_id) {
+            log::debug!("Attempting to use audio host: {:?}", host_id);
+            if let Ok(host) = cpal::host_from_id(host_id) {
+                if let Some(d) = host.default_output_device() {
+                    log::info!(
+                        "Selected audio device from host {:?}: {}",
+                        host_id,
+                        d.name().unwrap_or_else(|_| "Unknown".to_string())
+                    );
+                    return Ok((d, name_str));
+                }
+            }
+        }
+    }
+
+    log::warn!("No preferred audio host found. Falling back to default.");
+    let device = cpal::default_host()
+        .default_output_device()
+        .context("No audio output device available")?;
+    Ok((device, "Default"))
+}
+
+/// Stream settings for `device` at the configured rate and buffer length, or
+/// the device's own default rate if it cannot run at the configured one.
+fn stream_config(
+    device: &cpal::Device,
+    config: &AudioConfig,
+) -> Result<(StreamConfig, cpal::SampleFormat)> {
+    let default = device
+        .default_output_config()
+        .map_err(|e| anyhow!("Failed to query the output device: {}", e))?;
+    let rate = SampleRate(config.sample_rate);
+
+    let candidates: Vec<_> = device
+        .supported_output_configs()
+        .map(|configs| {
+            configs
+                .filter(|c| c.min_sample_rate() <= rate && rate <= c.max_sample_rate())
+                .collect()
+        })
+        .unwrap_or_default();
+    // Keep the device's preferred layout and format if it has them at this rate
+    let supported = candidates
+        .iter()
+        .find(|c| {
+            c.channels() == default.channels() && c.sample_format() == default.sample_format()
+        })
+        .or_else(|| {
+            candidates
+                .iter()
+                .find(|c| c.sample_format() == cpal::SampleFormat::F32)
+        })
+        .or(candidates.first())
+        .map(|c| c.with_sample_rate(rate))
+        .unwrap_or_else(|| {
+            log::warn!(
+                "Output device cannot run at {} Hz, using {} Hz",
+                config.sample_rate,
+                default.sample_rate().0
+            );
+            default
+        });
+
+    let mut stream_config = supported.config();
+    if config.buffer_size > 0 {
+        let frames = stream_config.sample_rate.0 * config.buffer_size / 1000;
+        let frames = match supported.buffer_size() {
+            SupportedBufferSize::Range { min, max } => frames.clamp(*min, *max),
+            SupportedBufferSize::Unknown => frames,
+        };
+        stream_config.buffer_size = BufferSize::Fixed(frames);
+    }
+    Ok((stream_config, supported.sample_format()))
+}
+
+fn build_stream(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    format: cpal::SampleFormat,
+) -> Result<(Arc<DynamicMixerController<f32>>, cpal::Stream), BuildStreamError> {
+    let (controller, mixer) = dynamic_mixer::mixer::<f32>(config.channels, config.sample_rate.0);
+    let stream = match format {
+        cpal::SampleFormat::F32 => build_typed_stream::<f32>(device, config, mixer),
+        cpal::SampleFormat::F64 => build_typed_stream::<f64>(device, config, mixer),
+        cpal::SampleFormat::I16 => build_typed_stream::<i16>(device, config, mixer),
+        cpal::SampleFormat::I32 => build_typed_stream::<i32>(device, config, mixer),
+        cpal::SampleFormat::U16 => build_typed_stream::<u16>(device, config, mixer),
+        _ => Err(BuildStreamError::StreamConfigNotSupported),
+    }?;
+    Ok((controller, stream))
+}
+
+fn build_typed_stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    mut mixer: DynamicMixer<f32>,
+) -> Result<cpal::Stream, BuildStreamError>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _| {
+            for sample in data.iter_mut() {
+                *sample = T::from_sample(mixer.next().unwrap_or(0.0));
+            }
+        },
+        |e| log::error!("Audio output stream error: {}", e),
+        None,
+    )
+}
+
+/// An output device as listed by cpal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputDevice {
+    pub host: String,
+    pub name: String,
+    /// The host's default output device.
+    pub is_default: bool,
+}
+
+impl OutputDevice {
+    /// Whether `config` asks for this device.
+    pub fn is_chosen(&self, config: &AudioConfig) -> bool {
+        config.device.as_deref() == Some(self.name.as_str())
+            && config
+                .host
+                .as_deref()
+                .is_none_or(|host| host.eq_ignore_ascii_case(&self.host))
+    }
+}
+
+/// Every output device on every available cpal host.
+pub fn list_devices() -> Vec<OutputDevice> {
+    let mut devices = Vec::new();
+    for host_id in cpal::available_hosts() {
+        let Ok(host) = cpal::host_from_id(host_id) else {
+            continue;
+        };
+        let default = host.default_output_device().and_then(|d| d.name().ok());
+        let Ok(outputs) = host.output_devices() else {
+            continue;
+        };
+        for device in outputs {
+            let Ok(name) = device.name() else {
+                continue;
+            };
+            devices.push(OutputDevice {
+                host: host_id.name().to_string(),
+                is_default: default.as_deref() == Some(name.as_str()),
+                name,
+            });
+        }
+    }
+    devices
+}
+
+/// Looks a device up by name, on `host` if given or else on any host.
+fn find_device(host: Option<&str>, name: &str) -> Option<cpal::Device> {
+    cpal::available_hosts()
+        .into_iter()
+        .filter(|id| host.is_none_or(|host| id.name().eq_ignore_ascii_case(host)))
+        .filter_map(|id| cpal::host_from_id(id).ok())
+        .filter_map(|host| host.output_devices().ok())
+        .flatten()
+        .find(|device| device.name().is_ok_and(|n| n == name))
+}
+
+/// Samples pulled from the shared mixer per lock.
+const TAP_BLOCK: usize = 512;
+
+/// Plays the engine's mixer into an output. The mixer is shared so a new tap
+/// can move it to another output without interrupting the sounds in it.
+pub struct MixerTap {
+    mixer: Arc<Mutex<DynamicMixer<f32>>>,
+    channels: u16,
+    sample_rate: u32,
+    block: Vec<f32>,
+    pos: usize,
+}
+
+impl MixerTap {
+    pub fn new(mixer: Arc<Mutex<DynamicMixer<f32>>>) -> Self {
+        let (channels, sample_rate) = {
+            let mixer = mixer.lock().unwrap();
+            (mixer.channels(), mixer.sample_rate())
+        };
+        Self {
+            mixer,
+            channels,
+            sample_rate,
+            block: Vec::with_capacity(TAP_BLOCK),
+            pos: 0,
+        }
+    }
+}
+
+impl Iterator for MixerTap {
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        if self.pos == self.block.len() {
+            let mut mixer = self.mixer.lock().unwrap();
+            self.block.clear();
+            // An empty mixer is silence, not the end of the stream
+            self.block
+                .extend((0..TAP_BLOCK).map(|_| mixer.next().unwrap_or(0.0)));
+            self.pos = 0;
+        }
+        let sample = self.block[self.pos];
+        self.pos += 1;
+        Some(sample)
+    }
+}
+
+impl Source for MixerTap {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// A rodio mixer drained in real time by a background thread, which hands
+/// every block of interleaved stereo samples to a consumer.
+pub struct MixerBackend {
+    controller: Arc<DynamicMixerController<f32>>,
+    running: Arc<AtomicBool>,
+    pump: Option<JoinHandle<()>>,
+}
+
+impl MixerBackend {
+    pub fn spawn<F>(sample_rate: u32, mut consume: F) -> Self
+    where
+        F: FnMut(&[f32]) -> Result<()> + Send + 'static,
+    {
+        let (controller, mut mixer) = dynamic_mixer::mixer::<f32>(CHANNELS, sample_rate);
+        let running = Arc::new(AtomicBool::new(true));
+        let pump_running = running.clone();
+
+        let pump = thread::Builder::new()
+            .name("AudioOutput".to_string())
+            .spawn(move || {
+                let start = Instant::now();
+                let mut written = 0u64;
+                let mut block = Vec::new();
+                while pump_running.load(Ordering::Relaxed) {
+                    thread::sleep(PUMP_INTERVAL);
+                    let due = (start.elapsed().as_secs_f64() * sample_rate as f64) as u64;
+                    let samples = (due - written) as usize * CHANNELS as usize;
+                    written = due;
+
+                    // The mixer runs dry between sounds; that is silence, not the end
+                    block.clear();
+                    block.extend((0..samples).map(|_| mixer.next().unwrap_or(0.0)));
+                    if let Err(e) = consume(&block) {
+                        log::error!("Audio output failed: {}", e);
+                        break;
+                    }
+                }
+            })
+            .expect("Failed to spawn audio output thread");
+
+        Self {
+            controller,
+            running,
+            pump: Some(pump),
+        }
+    }
+}
+
+impl OutputBackend for MixerBackend {
+    fn play(&self, source: Box<dyn Source<Item = f32> + Send>) -> Result<()> {
+        self.controller.add(source);
+        Ok(())
+    }
+}
+
+impl Drop for MixerBackend {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(pump) = self.pump.take() {
+            let _ = pump.join();
+        }
+    }
+}
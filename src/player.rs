@@ -0,0 +1,155 @@
+use crate::audio::AudioEngine;
+use crate::config::AudioConfig;
+use crate::daemon::DaemonClient;
+use crate::dsp::{Drift, Spatial, Tone};
+use crate::static_data::Sound;
+use anyhow::{anyhow, Result};
+use std::time::Duration;
+
+/// The audio backend driven by the TUI: either an engine owned by this process
+/// or a `tanin daemon` we are attached to as a client.
+pub enum Player {
+    Local(Box<AudioEngine>),
+    Remote(DaemonClient),
+}
+
+impl Player {
+    pub fn is_remote(&self) -> bool {
+        matches!(self, Player::Remote(_))
+    }
+
+    pub fn update(&mut self, dt: Duration) {
+        match self {
+            Player::Local(engine) => engine.update(dt),
+            Player::Remote(client) => client.update(dt),
+        }
+    }
+
+    pub fn play(&mut self, sound: &Sound, volume: f32) -> Result<()> {
+        match self {
+            Player::Local(engine) => engine.play(sound, volume),
+            Player::Remote(client) => client.play(&sound.id, volume),
+        }
+    }
+
+    /// The daemon fades new sounds in over its own default length.
+    pub fn play_with_fade(&mut self, sound: &Sound, volume: f32, fade: Duration) -> Result<()> {
+        match self {
+            Player::Local(engine) => engine.play_with_fade(sound, volume, fade),
+            Player::Remote(client) => client.play(&sound.id, volume),
+        }
+    }
+
+    pub fn stop(&mut self, id: &str) {
+        match self {
+            Player::Local(engine) => engine.stop(id),
+            Player::Remote(client) => client.stop(id),
+        }
+    }
+
+    /// The daemon fades sounds out over its own default length.
+    pub fn stop_with_fade(&mut self, id: &str, fade: Duration) {
+        match self {
+            Player::Local(engine) => engine.stop_with_fade(id, fade),
+            Player::Remote(client) => client.stop(id),
+        }
+    }
+
+    pub fn set_volume(&mut self, id: &str, volume: f32) {
+        match self {
+            Player::Local(engine) => engine.set_volume(id, volume),
+            Player::Remote(client) => client.set_volume(id, volume),
+        }
+    }
+
+    /// Volume changes on a daemon are applied immediately.
+    pub fn ramp_volume(&mut self, id: &str, volume: f32, duration: Duration) {
+        match self {
+            Player::Local(engine) => engine.ramp_volume(id, volume, duration),
+            Player::Remote(client) => client.set_volume(id, volume),
+        }
+    }
+
+    pub fn set_tone(&mut self, id: &str, tone: Tone) {
+        match self {
+            Player::Local(engine) => engine.set_tone(id, tone),
+            Player::Remote(client) => client.set_tone(id, tone),
+        }
+    }
+
+    pub fn set_spatial(&mut self, id: &str, spatial: Spatial) {
+        match self {
+            Player::Local(engine) => engine.set_spatial(id, spatial),
+            Player::Remote(client) => client.set_spatial(id, spatial),
+        }
+    }
+
+    pub fn set_drift(&mut self, id: &str, drift: Option<Drift>) {
+        match self {
+            Player::Local(engine) => engine.set_drift(id, drift),
+            Player::Remote(client) => client.set_drift(id, drift),
+        }
+    }
+
+    /// The daemon measures files itself, only the per-sound setting is sent.
+    pub fn set_loudness(&mut self, sound: &Sound) {
+        match self {
+            Player::Local(engine) => engine.set_loudness(sound),
+            Player::Remote(client) => client.set_normalize(&sound.id, sound.normalize),
+        }
+    }
+
+    pub fn drift_level(&self, id: &str) -> Option<f32> {
+        match self {
+            Player::Local(engine) => engine.drift_level(id),
+            Player::Remote(client) => client.drift_level(id),
+        }
+    }
+
+    pub fn seek(&mut self, id: &str, position: Duration) {
+        match self {
+            Player::Local(engine) => engine.seek(id, position),
+            Player::Remote(client) => client.seek(id, position),
+        }
+    }
+
+    /// Position of a playing file-backed sound and the length of its file.
+    pub fn position(&self, id: &str) -> Option<(Duration, Option<Duration>)> {
+        match self {
+            Player::Local(engine) => engine.position(id),
+            Player::Remote(client) => client.position(id),
+        }
+    }
+
+    pub fn set_master_volume(&mut self, volume: f32) {
+        match self {
+            Player::Local(engine) => engine.set_master_volume(volume),
+            Player::Remote(client) => client.set_master_volume(volume),
+        }
+    }
+
+    pub fn is_playing(&self, id: &str) -> bool {
+        match self {
+            Player::Local(engine) => engine.is_playing(id),
+            Player::Remote(client) => client.is_playing(id),
+        }
+    }
+
+    /// The daemon owns its output; it picks up a new device from config.toml
+    /// when restarted.
+    pub fn switch_output(&mut self, config: &AudioConfig) -> Result<()> {
+        match self {
+            Player::Local(engine) => engine.switch_output(config),
+            Player::Remote(_) => Err(anyhow!(
+                "The daemon keeps its output device until it is restarted"
+            )),
+        }
+    }
+
+    pub fn stop_all(&mut self) {
+        match self {
+            Player::Local(engine) => engine.stop_all(),
+            Player::Remote(client) => client.stop_all(),
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! Focus sessions: work and break phases of set lengths, each switching to
+//! its own mix. Set up in the `[pomodoro]` section of `config.toml`.
+
+use crate::config::PomodoroConfig;
+use anyhow::Result;
+use chrono::Local;
+use directories::ProjectDirs;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl Phase {
+    pub fn label(self) -> &'static str {
+        match self {
+            Phase::Work => "Work",
+            Phase::ShortBreak => "Break",
+            Phase::LongBreak => "Long break",
+        }
+    }
+}
+
+/// What a phase does to the mix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PhaseMix {
+    /// Leave whatever is playing alone.
+    Keep,
+    /// Fade every sound out.
+    Mute,
+    Preset(String),
+}
+
+impl PhaseMix {
+    /// `None` keeps the mix and `"mute"` fades it out; anything else names a preset.
+    fn from_setting(setting: Option<&str>) -> Self {
+        match setting.map(str::trim) {
+            None | Some("") => PhaseMix::Keep,
+            Some(name) if name.eq_ignore_ascii_case("mute") => PhaseMix::Mute,
+            Some(name) => PhaseMix::Preset(name.to_string()),
+        }
+    }
+}
+
+/// A running focus session.
+pub struct Pomodoro {
+    pub phase: Phase,
+    pub remaining: Duration,
+    /// Work phases finished so far, the current one not included.
+    pub completed: u32,
+    config: PomodoroConfig,
+}
+
+impl Pomodoro {
+    /// Starts with a work phase.
+    pub fn start(config: &PomodoroConfig) -> Self {
+        let mut pomodoro = Self {
+            phase: Phase::Work,
+            remaining: Duration::ZERO,
+            completed: 0,
+            config: config.clone(),
+        };
+        pomodoro.remaining = pomodoro.length(Phase::Work);
+        pomodoro
+    }
+
+    pub fn length(&self, phase: Phase) -> Duration {
+        let minutes = match phase {
+            Phase::Work => self.config.work_minutes,
+            Phase::ShortBreak => self.config.short_break_minutes,
+            Phase::LongBreak => self.config.long_break_minutes,
+        };
+        Duration::from_secs(minutes.max(1) * 60)
+    }
+
+    pub fn mix(&self, phase: Phase) -> PhaseMix {
+        let setting = match phase {
+            Phase::Work => &self.config.work_preset,
+            Phase::ShortBreak => &self.config.short_break_preset,
+            Phase::LongBreak => &self.config.long_break_preset,
+        };
+        PhaseMix::from_setting(setting.as_deref())
+    }
+
+    /// Number of the current work phase within its set, e.g. 2 of 4.
+    pub fn cycle(&self) -> u32 {
+        let per_set = self.config.cycles_before_long_break.max(1);
+        match self.phase {
+            Phase::Work => self.completed % per_set + 1,
+            // A break belongs to the work phase before it
+            _ => self.completed.saturating_sub(1) % per_set + 1,
+        }
+    }
+
+    pub fn cycles_per_set(&self) -> u32 {
+        self.config.cycles_before_long_break.max(1)
+    }
+
+    /// Counts down, returning the new phase when one begins.
+    pub fn advance(&mut self, dt: Duration) -> Option<Phase> {
+        self.remaining = self.remaining.saturating_sub(dt);
+        if !self.remaining.is_zero() {
+            return None;
+        }
+        Some(self.next_phase())
+    }
+
+    /// Ends the current phase early and starts the next one.
+    pub fn next_phase(&mut self) -> Phase {
+        self.phase = match self.phase {
+            Phase::Work => {
+                self.completed += 1;
+                if self.completed.is_multiple_of(self.cycles_per_set()) {
+                    Phase::LongBreak
+                } else {
+                    Phase::ShortBreak
+                }
+            }
+            Phase::ShortBreak | Phase::LongBreak => Phase::Work,
+        };
+        self.remaining = self.length(self.phase);
+        self.phase
+    }
+}
+
+/// Appends a finished work phase to `pomodoro.log` in the data directory.
+pub fn log_completed(cycle: u32, length: Duration) -> Result<()> {
+    let path = get_log_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(
+        file,
+        "{}\twork\t{}m\tcycle {}",
+        Local::now().format("%Y-%m-%d %H:%M"),
+        length.as_secs() / 60,
+        cycle
+    )?;
+    Ok(())
+}
+
+fn get_log_path() -> Result<PathBuf> {
+    if let Some(proj_dirs) = ProjectDirs::from("com", "tanin", "tanin") {
+        Ok(proj_dirs.data_dir().join("pomodoro.log"))
+    } else {
+        Ok(PathBuf::from("pomodoro.log"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn config() -> PomodoroConfig {
+        PomodoroConfig {
+            work_minutes: 25,
+            short_break_minutes: 5,
+            long_break_minutes: 15,
+            cycles_before_long_break: 2,
+            work_preset: Some("Focus".to_string()),
+            short_break_preset: None,
+            long_break_preset: Some("Mute".to_string()),
+            ..PomodoroConfig::default()
+        }
+    }
+
+    /// Advances a minute at a time until the next phase begins, returning it
+    /// and how many minutes that took.
+    fn run_phase(pomodoro: &mut Pomodoro) -> (Phase, u64) {
+        for minutes in 1..=60 {
+            if let Some(phase) = pomodoro.advance(MINUTE) {
+                return (phase, minutes);
+            }
+        }
+        panic!("{:?} never ended", pomodoro.phase);
+    }
+
+    #[test]
+    fn phases_cycle_into_a_long_break() {
+        let mut pomodoro = Pomodoro::start(&config());
+        assert_eq!((pomodoro.phase, pomodoro.cycle()), (Phase::Work, 1));
+
+        assert_eq!(run_phase(&mut pomodoro), (Phase::ShortBreak, 25));
+        assert_eq!((pomodoro.completed, pomodoro.cycle()), (1, 1));
+        assert_eq!(run_phase(&mut pomodoro), (Phase::Work, 5));
+        assert_eq!(pomodoro.cycle(), 2);
+        assert_eq!(run_phase(&mut pomodoro), (Phase::LongBreak, 25));
+        assert_eq!((pomodoro.completed, pomodoro.cycle()), (2, 2));
+        assert_eq!(run_phase(&mut pomodoro), (Phase::Work, 15));
+        // A new set begins
+        assert_eq!(pomodoro.cycle(), 1);
+        assert_eq!(run_phase(&mut pomodoro), (Phase::ShortBreak, 25));
+    }
+
+    #[test]
+    fn partial_steps_carry_over_within_a_phase() {
+        let mut pomodoro = Pomodoro::start(&config());
+        assert_eq!(pomodoro.advance(24 * MINUTE + MINUTE / 2), None);
+        assert_eq!(pomodoro.remaining, MINUTE / 2);
+        assert_eq!(pomodoro.advance(MINUTE), Some(Phase::ShortBreak));
+        assert_eq!(pomodoro.remaining, 5 * MINUTE);
+    }
+
+    #[test]
+    fn skipping_a_phase_counts_the_work() {
+        let mut pomodoro = Pomodoro::start(&config());
+        assert_eq!(pomodoro.next_phase(), Phase::ShortBreak);
+        assert_eq!(pomodoro.next_phase(), Phase::Work);
+        assert_eq!(pomodoro.next_phase(), Phase::LongBreak);
+        assert_eq!(pomodoro.completed, 2);
+        assert_eq!(pomodoro.remaining, 15 * MINUTE);
+    }
+
+    #[test]
+    fn phase_mixes_follow_the_settings() {
+        let pomodoro = Pomodoro::start(&config());
+        assert_eq!(
+            pomodoro.mix(Phase::Work),
+            PhaseMix::Preset("Focus".to_string())
+        );
+        assert_eq!(pomodoro.mix(Phase::ShortBreak), PhaseMix::Keep);
+        assert_eq!(pomodoro.mix(Phase::LongBreak), PhaseMix::Mute);
+    }
+
+    #[test]
+    fn zero_lengths_and_cycles_are_raised() {
+        let mut pomodoro = Pomodoro::start(&PomodoroConfig {
+            work_minutes: 0,
+            cycles_before_long_break: 0,
+            ..config()
+        });
+        assert_eq!(pomodoro.remaining, MINUTE);
+        assert_eq!(pomodoro.next_phase(), Phase::LongBreak);
+    }
+}
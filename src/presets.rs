@@ -1,37 +1,315 @@
-use anyhow::Result;
+//! Saved mixes, kept in `presets.toml`.
+//!
+//! The file carries a `version`. Version 1 files (which had none) stored
+//! `sounds` as plain volumes with tone, stereo and drift settings in maps of
+//! their own; they still load, are backed up as `presets.v1.toml` and are
+//! written back in the current layout the next time presets are saved.
+
+use crate::dsp::{Drift, Spatial, Tone};
+use crate::static_data::Sound;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, SubsecRound};
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Layout of `presets.toml` written by this version.
+pub const PRESETS_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "StoredPreset")]
 pub struct Preset {
     pub name: String,
-    // sound_id -> volume (if present, sound is active at this volume)
-    pub sounds: HashMap<String, f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub favourite: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<DateTime<Local>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<DateTime<Local>>,
+    // Master volume switched to on load; presets without one leave it alone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub master_volume: Option<f32>,
+    // Whether master was muted, restored along with `master_volume`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub muted: bool,
+    // sound_id -> settings of each sound that plays
+    pub sounds: HashMap<String, PresetSound>,
+}
+
+/// How one sound of a preset plays.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PresetSound {
+    pub volume: f32,
+    #[serde(default, skip_serializing_if = "Tone::is_flat")]
+    pub tone: Tone,
+    #[serde(default, skip_serializing_if = "Spatial::is_centered")]
+    pub spatial: Spatial,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drift: Option<Drift>,
+}
+
+impl PresetSound {
+    pub fn from_sound(sound: &Sound) -> Self {
+        Self {
+            volume: sound.volume_linear,
+            tone: sound.tone,
+            spatial: sound.spatial,
+            drift: sound.drift,
+        }
+    }
+
+    /// Gives `sound` these settings; starting it at the volume is up to the caller.
+    pub fn apply_to(&self, sound: &mut Sound) {
+        sound.volume_linear = self.volume;
+        sound.tone = self.tone;
+        sound.spatial = self.spatial;
+        sound.drift = self.drift;
+    }
+}
+
+/// A preset as found in any version of `presets.toml`.
+#[derive(Deserialize)]
+struct StoredPreset {
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    icon: Option<String>,
+    #[serde(default)]
+    favourite: bool,
+    #[serde(default)]
+    created: Option<DateTime<Local>>,
+    #[serde(default)]
+    last_used: Option<DateTime<Local>>,
+    #[serde(default)]
+    master_volume: Option<f32>,
+    #[serde(default)]
+    muted: bool,
+    sounds: HashMap<String, StoredSound>,
+    // Version 1 kept these apart from the volumes
+    #[serde(default)]
+    tones: HashMap<String, Tone>,
+    #[serde(default)]
+    spatial: HashMap<String, Spatial>,
+    #[serde(default)]
+    drift: HashMap<String, Drift>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredSound {
+    /// Version 1: just the volume.
+    Volume(f32),
+    Sound(PresetSound),
+}
+
+impl From<StoredPreset> for Preset {
+    fn from(stored: StoredPreset) -> Self {
+        let (mut tones, mut spatial, mut drift) = (stored.tones, stored.spatial, stored.drift);
+        let sounds = stored
+            .sounds
+            .into_iter()
+            .map(|(id, sound)| {
+                let sound = match sound {
+                    StoredSound::Volume(volume) => PresetSound {
+                        volume,
+                        tone: tones.remove(&id).unwrap_or_default(),
+                        spatial: spatial.remove(&id).unwrap_or_default(),
+                        drift: drift.remove(&id),
+                    },
+                    StoredSound::Sound(sound) => sound,
+                };
+                (id, sound)
+            })
+            .collect();
+        Self {
+            name: stored.name,
+            description: stored.description,
+            tags: stored.tags,
+            icon: stored.icon,
+            favourite: stored.favourite,
+            created: stored.created,
+            last_used: stored.last_used,
+            master_volume: stored.master_volume,
+            muted: stored.muted,
+            sounds,
+        }
+    }
+}
+
+impl Preset {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    pub fn mark_used(&mut self) {
+        self.last_used = Some(now());
+    }
+}
+
+/// Order of the Presets view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PresetSort {
+    /// As saved, rearranged by hand.
+    #[default]
+    Manual,
+    Name,
+    LastUsed,
+}
+
+impl PresetSort {
+    pub fn label(self) -> &'static str {
+        match self {
+            PresetSort::Manual => "manual",
+            PresetSort::Name => "name",
+            PresetSort::LastUsed => "last used",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            PresetSort::Manual => PresetSort::Name,
+            PresetSort::Name => PresetSort::LastUsed,
+            PresetSort::LastUsed => PresetSort::Manual,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PresetsConfig {
+    #[serde(default = "legacy_version")]
+    pub version: u32,
     #[serde(default)]
     pub presets: Vec<Preset>,
 }
 
+impl Default for PresetsConfig {
+    fn default() -> Self {
+        Self {
+            version: PRESETS_VERSION,
+            presets: Vec::new(),
+        }
+    }
+}
+
+/// Just the version, readable whatever the rest of the file looks like.
+#[derive(Deserialize)]
+pub struct FileVersion {
+    #[serde(default = "legacy_version")]
+    pub version: u32,
+}
+
+impl FileVersion {
+    /// Errors if `what` was written by a newer Tanin than this one.
+    pub fn check(&self, what: &str) -> Result<()> {
+        if self.version > PRESETS_VERSION {
+            return Err(anyhow!(
+                "{} is version {}, but this Tanin only knows up to version {}",
+                what,
+                self.version,
+                PRESETS_VERSION
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Version of files from before presets had one.
+pub fn legacy_version() -> u32 {
+    1
+}
+
 impl PresetsConfig {
     pub fn load() -> Result<Self> {
         let path = get_presets_path()?;
         if path.exists() {
             let content = fs::read_to_string(&path)?;
-            let config: PresetsConfig = toml::from_str(&content)?;
+            check_version(&content)?;
+            let mut config: PresetsConfig = toml::from_str(&content)?;
+            if config.version < PRESETS_VERSION {
+                let backup = path.with_file_name(format!("presets.v{}.toml", config.version));
+                if !backup.exists() {
+                    fs::write(&backup, &content)?;
+                }
+                log::info!(
+                    "Upgrading presets.toml from version {} to {}, the old file is kept as {}",
+                    config.version,
+                    PRESETS_VERSION,
+                    backup.display()
+                );
+                config.version = PRESETS_VERSION;
+            }
             Ok(config)
         } else {
             Ok(Self::default())
         }
     }
 
+    /// Indices of the presets tagged `tag` (all without one), in `sort` order.
+    /// Favourites come first unless sorted by hand.
+    pub fn sorted(&self, sort: PresetSort, tag: Option<&str>) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..self.presets.len())
+            .filter(|&i| tag.is_none_or(|tag| self.presets[i].has_tag(tag)))
+            .collect();
+        let presets = &self.presets;
+        match sort {
+            PresetSort::Manual => {}
+            PresetSort::Name => {
+                indices.sort_by_key(|&i| (!presets[i].favourite, presets[i].name.to_lowercase()))
+            }
+            PresetSort::LastUsed => indices.sort_by_key(|&i| {
+                (
+                    !presets[i].favourite,
+                    std::cmp::Reverse(presets[i].last_used),
+                )
+            }),
+        }
+        indices
+    }
+
+    /// Every tag in use, sorted, spelled as first seen.
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        for tag in self.presets.iter().flat_map(|p| &p.tags) {
+            if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                tags.push(tag.clone());
+            }
+        }
+        tags.sort_by_key(|t| t.to_lowercase());
+        tags
+    }
+
+    /// Stamps a preset as used in `presets.toml`, for processes that don't
+    /// own the presets (the daemon) and so mustn't overwrite other changes.
+    pub fn record_use(name: &str) -> Result<()> {
+        let mut config = Self::load()?;
+        if let Some(preset) = config
+            .presets
+            .iter_mut()
+            .find(|p| p.name.eq_ignore_ascii_case(name))
+        {
+            preset.mark_used();
+            config.save()?;
+        }
+        Ok(())
+    }
+
     pub fn save(&self) -> Result<()> {
         let path = get_presets_path()?;
+        // Don't flatten presets a newer Tanin wrote into a layout it has moved on from
+        if let Ok(content) = fs::read_to_string(&path) {
+            check_version(&content)?;
+        }
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)?;
         }
@@ -41,6 +319,18 @@ impl PresetsConfig {
     }
 }
 
+fn check_version(content: &str) -> Result<()> {
+    match toml::from_str::<FileVersion>(content) {
+        Ok(version) => version.check("presets.toml"),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Timestamps are kept to the second, which is all the view shows.
+pub fn now() -> DateTime<Local> {
+    Local::now().trunc_subsecs(0)
+}
+
 fn get_presets_path() -> Result<PathBuf> {
     if let Some(proj_dirs) = ProjectDirs::from("com", "tanin", "tanin") {
         Ok(proj_dirs.config_dir().join("presets.toml"))
@@ -48,3 +338,160 @@ fn get_presets_path() -> Result<PathBuf> {
         Ok(PathBuf::from("presets.toml"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsp::DriftMode;
+
+    fn sound(config: &PresetsConfig, id: &str) -> PresetSound {
+        config.presets[0].sounds[id]
+    }
+
+    #[test]
+    fn v1_volumes_load_as_sounds() {
+        let config: PresetsConfig = toml::from_str(
+            r#"
+            [[presets]]
+            name = "Rainy"
+            sounds = { rain = 0.5, wind = 1 }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.version, 1);
+        assert_eq!(
+            sound(&config, "rain"),
+            PresetSound {
+                volume: 0.5,
+                ..Default::default()
+            }
+        );
+        assert_eq!(sound(&config, "wind").volume, 1.0);
+        assert_eq!(config.presets[0].master_volume, None);
+        assert!(!config.presets[0].muted);
+    }
+
+    #[test]
+    fn v1_settings_merge_into_sounds() {
+        let config: PresetsConfig = toml::from_str(
+            r#"
+            [[presets]]
+            name = "Night"
+            sounds = { rain = 0.5, wind = 0.25, birds = 1.0 }
+
+            [presets.tones.rain]
+            low_pass = 2000.0
+            bass_db = 3.0
+
+            [presets.spatial.wind]
+            pan = -0.5
+            width = 0.5
+
+            [presets.drift.birds]
+            mode = "wander"
+            min = 0.2
+            max = 0.8
+            period_secs = 30.0
+
+            # Settings of a sound the preset doesn't play are dropped
+            [presets.tones.fire]
+            treble_db = -2.0
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            sound(&config, "rain"),
+            PresetSound {
+                volume: 0.5,
+                tone: Tone {
+                    low_pass: Some(2000.0),
+                    bass_db: 3.0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            sound(&config, "wind"),
+            PresetSound {
+                volume: 0.25,
+                spatial: Spatial {
+                    pan: -0.5,
+                    width: 0.5
+                },
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            sound(&config, "birds"),
+            PresetSound {
+                volume: 1.0,
+                drift: Some(Drift {
+                    mode: DriftMode::Wander,
+                    min: 0.2,
+                    max: 0.8,
+                    period_secs: 30.0,
+                }),
+                ..Default::default()
+            }
+        );
+        assert_eq!(config.presets[0].sounds.len(), 3);
+    }
+
+    #[test]
+    fn v2_round_trips() {
+        let mut config = PresetsConfig::default();
+        config.presets.push(Preset {
+            name: "Focus".to_string(),
+            tags: vec!["work".to_string()],
+            master_volume: Some(0.7),
+            muted: true,
+            sounds: HashMap::from([
+                (
+                    "rain".to_string(),
+                    PresetSound {
+                        volume: 0.4,
+                        tone: Tone {
+                            high_pass: Some(120.0),
+                            treble_db: -3.0,
+                            ..Default::default()
+                        },
+                        spatial: Spatial {
+                            pan: 0.25,
+                            width: 1.5,
+                        },
+                        drift: Some(Drift::new(DriftMode::Lfo)),
+                    },
+                ),
+                (
+                    "brown_noise".to_string(),
+                    PresetSound {
+                        volume: 0.3,
+                        ..Default::default()
+                    },
+                ),
+            ]),
+            ..Default::default()
+        });
+
+        let content = toml::to_string_pretty(&config).unwrap();
+        let loaded: PresetsConfig = toml::from_str(&content).unwrap();
+
+        assert_eq!(loaded.version, PRESETS_VERSION);
+        let (preset, loaded) = (&config.presets[0], &loaded.presets[0]);
+        assert_eq!(loaded.name, preset.name);
+        assert_eq!(loaded.tags, preset.tags);
+        assert_eq!(loaded.master_volume, preset.master_volume);
+        assert_eq!(loaded.muted, preset.muted);
+        assert_eq!(loaded.sounds, preset.sounds);
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        assert!(check_version("").is_ok());
+        assert!(check_version(&format!("version = {}", PRESETS_VERSION)).is_ok());
+        assert!(check_version(&format!("version = {}", PRESETS_VERSION + 1)).is_err());
+    }
+}
@@ -0,0 +1,301 @@
+//! A small FLAC encoder: fixed block size, fixed polynomial predictors and a
+//! single Rice partition per subframe. It compresses less than libFLAC's LPC
+//! search but is lossless and needs no native library.
+
+use super::{to_i16, Encoder, CHANNELS, SAMPLE_RATE};
+use anyhow::Result;
+use std::io::{Seek, SeekFrom, Write};
+
+const BLOCK_SIZE: usize = 4096;
+const BITS_PER_SAMPLE: u32 = 16;
+const MAX_FIXED_ORDER: usize = 4;
+/// Largest parameter the 4 bit Rice field can hold; 15 is the escape code.
+const MAX_RICE_PARAM: u32 = 14;
+
+pub struct FlacEncoder<W: Write + Seek> {
+    out: W,
+    /// Samples waiting for a full block, one buffer per channel.
+    pending: Vec<Vec<i32>>,
+    frame_number: u32,
+    total_frames: u64,
+    min_frame_size: u32,
+    max_frame_size: u32,
+}
+
+impl<W: Write + Seek> FlacEncoder<W> {
+    pub fn new(mut out: W) -> Result<Self> {
+        out.write_all(b"fLaC")?;
+        // Placeholder, rewritten with the real totals once the stream is done
+        out.write_all(&stream_info(0, 0, 0))?;
+        Ok(Self {
+            out,
+            pending: vec![Vec::with_capacity(BLOCK_SIZE); CHANNELS as usize],
+            frame_number: 0,
+            total_frames: 0,
+            min_frame_size: u32::MAX,
+            max_frame_size: 0,
+        })
+    }
+
+    fn write_frame(&mut self, len: usize) -> Result<()> {
+        let channels: Vec<Vec<i32>> = self
+            .pending
+            .iter_mut()
+            .map(|samples| samples.drain(..len).collect())
+            .collect();
+
+        let mut bits = BitWriter::default();
+        bits.put(0b11_1111_1111_1110, 14);
+        // Reserved bit, then fixed block size strategy
+        bits.put(0, 2);
+        let size_code = if len == BLOCK_SIZE { 12 } else { 7 };
+        bits.put(size_code, 4);
+        bits.put(sample_rate_code(SAMPLE_RATE), 4);
+        // Independent channels
+        bits.put(CHANNELS as u32 - 1, 4);
+        // 16 bits per sample, then a reserved bit
+        bits.put(0b100, 3);
+        bits.put(0, 1);
+        bits.put_utf8(self.frame_number);
+        if size_code == 7 {
+            bits.put(len as u32 - 1, 16);
+        }
+        let crc = crc8(&bits.bytes);
+        bits.put(crc as u32, 8);
+
+        for samples in &channels {
+            write_subframe(&mut bits, samples);
+        }
+        bits.align();
+        let crc = crc16(&bits.bytes);
+        bits.put(crc as u32, 16);
+
+        self.out.write_all(&bits.bytes)?;
+        let size = bits.bytes.len() as u32;
+        self.min_frame_size = self.min_frame_size.min(size);
+        self.max_frame_size = self.max_frame_size.max(size);
+        self.frame_number += 1;
+        self.total_frames += len as u64;
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek> Encoder for FlacEncoder<W> {
+    fn write(&mut self, samples: &[f32]) -> Result<()> {
+        for frame in samples.chunks_exact(CHANNELS as usize) {
+            for (channel, &sample) in self.pending.iter_mut().zip(frame) {
+                channel.push(to_i16(sample) as i32);
+            }
+        }
+        while self.pending[0].len() >= BLOCK_SIZE {
+            self.write_frame(BLOCK_SIZE)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        let remaining = self.pending[0].len();
+        if remaining > 0 {
+            self.write_frame(remaining)?;
+        }
+        if self.frame_number == 0 {
+            self.min_frame_size = 0;
+        }
+
+        let info = stream_info(self.min_frame_size, self.max_frame_size, self.total_frames);
+        self.out.seek(SeekFrom::Start(4))?;
+        self.out.write_all(&info)?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// The STREAMINFO metadata block, including its header.
+fn stream_info(min_frame_size: u32, max_frame_size: u32, total_frames: u64) -> Vec<u8> {
+    let mut bits = BitWriter::default();
+    // Last metadata block, type STREAMINFO, 34 bytes long
+    bits.put(1, 1);
+    bits.put(0, 7);
+    bits.put(34, 24);
+    bits.put(BLOCK_SIZE as u32, 16);
+    bits.put(BLOCK_SIZE as u32, 16);
+    bits.put(min_frame_size, 24);
+    bits.put(max_frame_size, 24);
+    bits.put(SAMPLE_RATE, 20);
+    bits.put(CHANNELS as u32 - 1, 3);
+    bits.put(BITS_PER_SAMPLE - 1, 5);
+    bits.put((total_frames >> 32) as u32, 4);
+    bits.put(total_frames as u32, 32);
+    // MD5 of the audio; all zero means "not computed"
+    for _ in 0..4 {
+        bits.put(0, 32);
+    }
+    bits.bytes
+}
+
+fn sample_rate_code(rate: u32) -> u32 {
+    match rate {
+        44_100 => 0b1001,
+        48_000 => 0b1010,
+        96_000 => 0b1011,
+        // Taken from STREAMINFO
+        _ => 0,
+    }
+}
+
+fn write_subframe(bits: &mut BitWriter, samples: &[i32]) {
+    // Subframe header: zero pad bit, 6 bit type, no wasted bits
+    if samples.iter().all(|&s| s == samples[0]) {
+        bits.put(0, 8);
+        bits.put(samples[0] as u32, BITS_PER_SAMPLE);
+        return;
+    }
+
+    let (order, residual) = best_fixed_predictor(samples);
+    let folded: Vec<u32> = residual
+        .iter()
+        .map(|&r| ((r << 1) ^ (r >> 31)) as u32)
+        .collect();
+    let (param, rice_bits) = best_rice_param(&folded);
+    let verbatim_bits = samples.len() as u64 * BITS_PER_SAMPLE as u64;
+
+    if rice_bits >= verbatim_bits {
+        bits.put(0b0000_0010, 8);
+        for &sample in samples {
+            bits.put(sample as u32, BITS_PER_SAMPLE);
+        }
+        return;
+    }
+
+    bits.put((0b00_1000 | order as u32) << 1, 8);
+    for &sample in &samples[..order] {
+        bits.put(sample as u32, BITS_PER_SAMPLE);
+    }
+    // Rice coding with 4 bit parameters, partition order 0
+    bits.put(0, 2);
+    bits.put(0, 4);
+    bits.put(param, 4);
+    for &value in &folded {
+        bits.put_unary(value >> param);
+        bits.put(value, param);
+    }
+}
+
+/// Picks the fixed predictor order with the smallest residual, returning the
+/// order and the residual for the samples after the warm-up.
+fn best_fixed_predictor(samples: &[i32]) -> (usize, Vec<i32>) {
+    let max_order = MAX_FIXED_ORDER.min(samples.len() - 1);
+    let mut best = (0, samples.to_vec());
+    let mut best_cost = abs_sum(&best.1);
+
+    // Each order's residual is the difference of the previous one
+    let mut residual = samples.to_vec();
+    for order in 1..=max_order {
+        residual = residual.windows(2).map(|w| w[1] - w[0]).collect();
+        let cost = abs_sum(&residual);
+        if cost < best_cost {
+            best_cost = cost;
+            best = (order, residual.clone());
+        }
+    }
+    best
+}
+
+fn abs_sum(values: &[i32]) -> u64 {
+    values.iter().map(|v| v.unsigned_abs() as u64).sum()
+}
+
+/// Estimates the cheapest Rice parameter for `values`, returning it along with
+/// the estimated size in bits.
+fn best_rice_param(values: &[u32]) -> (u32, u64) {
+    let count = values.len() as u64;
+    let sum: u64 = values.iter().map(|&v| v as u64).sum();
+    (0..=MAX_RICE_PARAM)
+        .map(|param| (param, count * (param as u64 + 1) + (sum >> param)))
+        .min_by_key(|&(_, bits)| bits)
+        .unwrap_or((0, u64::MAX))
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    bits: u32,
+}
+
+impl BitWriter {
+    /// Appends the low `count` bits of `value`, most significant first. `count` is at most 32.
+    fn put(&mut self, value: u32, count: u32) {
+        let mask = (1u64 << count) - 1;
+        self.acc = (self.acc << count) | (value as u64 & mask);
+        self.bits += count;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.bytes.push((self.acc >> self.bits) as u8);
+        }
+        self.acc &= (1u64 << self.bits) - 1;
+    }
+
+    /// `value` zero bits followed by a one.
+    fn put_unary(&mut self, mut value: u32) {
+        while value >= 32 {
+            self.put(0, 32);
+            value -= 32;
+        }
+        self.put(1, value + 1);
+    }
+
+    /// FLAC's UTF-8 style variable length integer.
+    fn put_utf8(&mut self, value: u32) {
+        if value < 0x80 {
+            self.put(value, 8);
+            return;
+        }
+        let extra = match value {
+            0..=0x7ff => 1,
+            0x800..=0xffff => 2,
+            0x1_0000..=0x1f_ffff => 3,
+            0x20_0000..=0x3ff_ffff => 4,
+            _ => 5,
+        };
+        let lead = (0xff00u32 >> (extra + 1)) & 0xff;
+        self.put(lead | (value >> (6 * extra)), 8);
+        for i in (0..extra).rev() {
+            self.put(0x80 | ((value >> (6 * i)) & 0x3f), 8);
+        }
+    }
+
+    fn align(&mut self) {
+        if self.bits > 0 {
+            self.put(0, 8 - self.bits);
+        }
+    }
+}
+
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |mut crc, &byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0u16, |mut crc, &byte| {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
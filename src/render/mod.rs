@@ -0,0 +1,176 @@
+//! Offline rendering: mixes sounds through the same chain as [`AudioEngine`]
+//! but pulls the samples into a file encoder instead of an output stream.
+//!
+//! Rendering is deterministic: generators are seeded by sound id, drift uses a
+//! fixed seed and looping files are decoded in order, so rendering the same mix
+//! twice produces the same file.
+//!
+//! [`AudioEngine`]: crate::audio::AudioEngine
+
+mod flac;
+mod opus;
+mod wav;
+
+use crate::audio::{decode_clips, sound_source, FADE_DURATION};
+use crate::buffered;
+use crate::config::{DecoderConfig, LoudnessConfig};
+use crate::dsp::{DriftState, SpatialControl, ToneControl};
+use crate::static_data::Sound;
+use anyhow::{anyhow, Context, Result};
+use rodio::source::UniformSourceIterator;
+use rodio::Source;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const SAMPLE_RATE: u32 = 48_000;
+pub const CHANNELS: u16 = 2;
+
+/// Frames mixed between volume updates, roughly the engine's update tick.
+const BLOCK_FRAMES: usize = 1440;
+
+/// Seed for volume drift, fixed so renders are reproducible.
+const DRIFT_SEED: u64 = 0x7a6e_696e;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Wav,
+    Flac,
+    Opus,
+}
+
+impl Format {
+    /// Picks the format from the output file's extension.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_lowercase)
+            .unwrap_or_default();
+        match ext.as_str() {
+            "wav" => Ok(Format::Wav),
+            "flac" => Ok(Format::Flac),
+            "opus" | "ogg" => Ok(Format::Opus),
+            _ => Err(anyhow!(
+                "Cannot tell the format of '{}', use a .wav, .flac or .opus extension",
+                path.display()
+            )),
+        }
+    }
+}
+
+/// Sink for interleaved stereo samples at [`SAMPLE_RATE`].
+trait Encoder {
+    fn write(&mut self, samples: &[f32]) -> Result<()>;
+    fn finish(self: Box<Self>) -> Result<()>;
+}
+
+struct Voice {
+    source: UniformSourceIterator<Box<dyn Source<Item = f32> + Send>, f32>,
+    volume: f32,
+    trim: f32,
+    drift: Option<DriftState>,
+}
+
+/// Renders `duration` of the given sounds, at their own volumes scaled by
+/// `master`, to `out`. `progress` is called with the fraction done.
+pub fn render(
+    sounds: &[Sound],
+    master: f32,
+    loudness: &LoudnessConfig,
+    duration: Duration,
+    out: &Path,
+    mut progress: impl FnMut(f32),
+) -> Result<()> {
+    let format = Format::from_path(out)?;
+
+    // Each drifting sound gets its own seed, drawn in order from a fixed one
+    let mut drift_seeds = fastrand::Rng::with_seed(DRIFT_SEED);
+    // Offline, so the playback buffering settings don't matter
+    let pool = buffered::init_worker_pool(&DecoderConfig::default());
+    let mut voices = Vec::new();
+    for sound in sounds {
+        let tone = Arc::new(ToneControl::default());
+        tone.set(sound.tone);
+        let spatial = Arc::new(SpatialControl::default());
+        spatial.set(sound.spatial);
+
+        // Decoded up front so the events are scheduled from the first frame, the same every time
+        if let Some(events) = &sound.events {
+            decode_clips(&events.clips)
+                .with_context(|| format!("Failed to open sound '{}'", sound.id))?;
+        }
+        let source: Box<dyn Source<Item = f32> + Send> = Box::new(
+            sound_source(
+                sound,
+                &pool,
+                tone,
+                spatial,
+                Arc::default(),
+                seed_from_id(&sound.id),
+            )
+            .with_context(|| format!("Failed to open sound '{}'", sound.id))?
+            .fade_in(FADE_DURATION),
+        );
+        voices.push(Voice {
+            source: UniformSourceIterator::new(source, CHANNELS, SAMPLE_RATE),
+            volume: sound.volume_linear,
+            trim: sound.loudness_gain(loudness),
+            drift: sound
+                .drift
+                .map(|drift| DriftState::new(drift, drift_seeds.u64(..))),
+        });
+    }
+
+    let file = File::create(out).with_context(|| format!("Failed to create {}", out.display()))?;
+    let writer = BufWriter::new(file);
+    let mut encoder: Box<dyn Encoder> = match format {
+        Format::Wav => Box::new(wav::WavEncoder::new(writer)?),
+        Format::Flac => Box::new(flac::FlacEncoder::new(writer)?),
+        Format::Opus => Box::new(opus::OpusEncoder::new(writer)?),
+    };
+
+    let total_frames = (duration.as_secs_f64() * SAMPLE_RATE as f64).round() as u64;
+    let block_duration = Duration::from_secs_f64(BLOCK_FRAMES as f64 / SAMPLE_RATE as f64);
+    let mut mix = vec![0.0f32; BLOCK_FRAMES * CHANNELS as usize];
+    let mut done = 0u64;
+
+    while done < total_frames {
+        let frames = (total_frames - done).min(BLOCK_FRAMES as u64) as usize;
+        let block = &mut mix[..frames * CHANNELS as usize];
+        block.fill(0.0);
+
+        for voice in &mut voices {
+            // Same gain the engine gives a sink: own volume, trim, drift and master
+            let drift = voice.drift.as_ref().map_or(1.0, DriftState::level);
+            let gain = voice.volume * voice.trim * drift * master;
+            for (out, sample) in block.iter_mut().zip(voice.source.by_ref()) {
+                *out += sample * gain;
+            }
+            if let Some(drift) = &mut voice.drift {
+                drift.advance(block_duration);
+            }
+        }
+
+        encoder.write(block)?;
+        done += frames as u64;
+        progress(done as f32 / total_frames as f32);
+    }
+
+    encoder.finish()
+}
+
+/// FNV-1a hash of a sound id. Spelled out rather than using std's hasher, whose
+/// algorithm may change between Rust releases and with it every rendered file.
+fn seed_from_id(id: &str) -> u64 {
+    id.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Converts a mixed sample to 16 bit PCM, clipping anything out of range.
+fn to_i16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16
+}
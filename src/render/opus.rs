@@ -0,0 +1,139 @@
+//! Opus in an Ogg container, as described in RFC 7845.
+
+use super::{Encoder, CHANNELS, SAMPLE_RATE};
+use anyhow::{anyhow, Result};
+use audiopus::coder::Encoder as OpusCoder;
+use audiopus::{Application, Bitrate, Channels, SampleRate};
+use ogg::{PacketWriteEndInfo, PacketWriter};
+use std::io::Write;
+
+/// 20 ms, the frame size most players expect.
+const FRAME_SIZE: usize = 960;
+const BITRATE: i32 = 160_000;
+/// Fixed so renders are reproducible.
+const STREAM_SERIAL: u32 = 0x7461_6e69;
+/// Upper bound from the Opus spec for a single packet.
+const MAX_PACKET_SIZE: usize = 1275 * 3 + 7;
+
+pub struct OpusEncoder<W: Write> {
+    coder: OpusCoder,
+    writer: PacketWriter<W>,
+    /// Interleaved samples waiting for a full frame.
+    pending: Vec<f32>,
+    /// Encoded packet held back until we know whether it ends the stream.
+    last_packet: Option<Vec<u8>>,
+    pre_skip: u64,
+    input_frames: u64,
+    encoded_frames: u64,
+}
+
+impl<W: Write> OpusEncoder<W> {
+    pub fn new(out: W) -> Result<Self> {
+        let mut coder = OpusCoder::new(SampleRate::Hz48000, Channels::Stereo, Application::Audio)
+            .map_err(|e| anyhow!("Failed to create Opus encoder: {}", e))?;
+        coder
+            .set_bitrate(Bitrate::BitsPerSecond(BITRATE))
+            .map_err(|e| anyhow!("Failed to set Opus bitrate: {}", e))?;
+        let pre_skip = coder
+            .lookahead()
+            .map_err(|e| anyhow!("Failed to query Opus lookahead: {}", e))?;
+
+        let mut head = Vec::with_capacity(19);
+        head.extend_from_slice(b"OpusHead");
+        head.push(1);
+        head.push(CHANNELS as u8);
+        head.extend_from_slice(&(pre_skip as u16).to_le_bytes());
+        head.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+        // Output gain, then channel mapping family 0 (mono or stereo)
+        head.extend_from_slice(&0i16.to_le_bytes());
+        head.push(0);
+
+        let vendor = concat!("tanin ", env!("CARGO_PKG_VERSION"));
+        let mut tags = Vec::new();
+        tags.extend_from_slice(b"OpusTags");
+        tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+        tags.extend_from_slice(vendor.as_bytes());
+        tags.extend_from_slice(&0u32.to_le_bytes());
+
+        // Both headers must sit on pages of their own
+        let mut writer = PacketWriter::new(out);
+        writer.write_packet(
+            head.into_boxed_slice(),
+            STREAM_SERIAL,
+            PacketWriteEndInfo::EndPage,
+            0,
+        )?;
+        writer.write_packet(
+            tags.into_boxed_slice(),
+            STREAM_SERIAL,
+            PacketWriteEndInfo::EndPage,
+            0,
+        )?;
+
+        Ok(Self {
+            coder,
+            writer,
+            pending: Vec::with_capacity(FRAME_SIZE * CHANNELS as usize),
+            last_packet: None,
+            pre_skip: pre_skip as u64,
+            input_frames: 0,
+            encoded_frames: 0,
+        })
+    }
+
+    fn encode_frame(&mut self) -> Result<()> {
+        let mut packet = vec![0u8; MAX_PACKET_SIZE];
+        let len = self
+            .coder
+            .encode_float(&self.pending, &mut packet)
+            .map_err(|e| anyhow!("Opus encoding failed: {}", e))?;
+        packet.truncate(len);
+        self.pending.clear();
+
+        if let Some(previous) = self.last_packet.replace(packet) {
+            self.writer.write_packet(
+                previous.into_boxed_slice(),
+                STREAM_SERIAL,
+                PacketWriteEndInfo::NormalPacket,
+                self.encoded_frames,
+            )?;
+        }
+        self.encoded_frames += FRAME_SIZE as u64;
+        Ok(())
+    }
+}
+
+impl<W: Write> Encoder for OpusEncoder<W> {
+    fn write(&mut self, samples: &[f32]) -> Result<()> {
+        let frame_len = FRAME_SIZE * CHANNELS as usize;
+        self.input_frames += (samples.len() / CHANNELS as usize) as u64;
+        for &sample in samples {
+            self.pending.push(sample);
+            if self.pending.len() == frame_len {
+                self.encode_frame()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        // Feed silence until the encoder's lookahead has covered all the input
+        let frame_len = FRAME_SIZE * CHANNELS as usize;
+        while self.encoded_frames < self.input_frames + self.pre_skip || !self.pending.is_empty() {
+            self.pending.resize(frame_len, 0.0);
+            self.encode_frame()?;
+        }
+
+        if let Some(packet) = self.last_packet.take() {
+            // The final granule position tells players where the real audio ends
+            self.writer.write_packet(
+                packet.into_boxed_slice(),
+                STREAM_SERIAL,
+                PacketWriteEndInfo::EndStream,
+                self.input_frames + self.pre_skip,
+            )?;
+        }
+        self.writer.inner_mut().flush()?;
+        Ok(())
+    }
+}
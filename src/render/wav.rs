@@ -0,0 +1,37 @@
+use super::{to_i16, Encoder, CHANNELS, SAMPLE_RATE};
+use anyhow::Result;
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::io::{Seek, Write};
+
+/// 16 bit PCM WAV.
+pub struct WavEncoder<W: Write + Seek> {
+    writer: WavWriter<W>,
+}
+
+impl<W: Write + Seek> WavEncoder<W> {
+    pub fn new(out: W) -> Result<Self> {
+        let spec = WavSpec {
+            channels: CHANNELS,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        Ok(Self {
+            writer: WavWriter::new(out, spec)?,
+        })
+    }
+}
+
+impl<W: Write + Seek> Encoder for WavEncoder<W> {
+    fn write(&mut self, samples: &[f32]) -> Result<()> {
+        for &sample in samples {
+            self.writer.write_sample(to_i16(sample))?;
+        }
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.writer.finalize()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,356 @@
+//! Time-of-day automation: `schedule.toml` rules that switch presets at set times.
+//!
+//! ```toml
+//! [[rule]]
+//! at = "09:00"
+//! preset = "Focus"
+//! days = ["weekdays"]
+//! ```
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Duration, Local, NaiveDateTime, NaiveTime, Weekday};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+/// How often `schedule.toml` is checked for edits.
+const RELOAD_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ScheduleConfig {
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<ScheduleRule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleRule {
+    /// Local time of day, `HH:MM`.
+    pub at: String,
+    pub preset: String,
+    /// Day names (`mon`, `tuesday`, ...) or `weekdays` / `weekends`; empty means every day.
+    #[serde(default)]
+    pub days: Vec<String>,
+}
+
+/// A validated rule.
+#[derive(Debug, Clone)]
+pub struct ScheduleEntry {
+    pub time: NaiveTime,
+    pub days: Vec<Weekday>,
+    pub preset: String,
+}
+
+impl ScheduleEntry {
+    fn parse(rule: &ScheduleRule) -> Result<Self> {
+        Self::new(&rule.at, &rule.days, &rule.preset)
+    }
+
+    /// Validates an `HH:MM` time and day names as written in the config files.
+    pub fn new(at: &str, days: &[String], preset: &str) -> Result<Self> {
+        let time = NaiveTime::parse_from_str(at.trim(), "%H:%M")
+            .map_err(|_| anyhow!("invalid time '{}', expected HH:MM", at))?;
+
+        let mut weekdays = Vec::new();
+        for day in days {
+            match day.to_lowercase().as_str() {
+                "weekdays" => weekdays.extend([
+                    Weekday::Mon,
+                    Weekday::Tue,
+                    Weekday::Wed,
+                    Weekday::Thu,
+                    Weekday::Fri,
+                ]),
+                "weekends" => weekdays.extend([Weekday::Sat, Weekday::Sun]),
+                name => weekdays.push(name.parse().map_err(|_| anyhow!("invalid day '{}'", day))?),
+            }
+        }
+
+        Ok(Self {
+            time,
+            days: weekdays,
+            preset: preset.to_string(),
+        })
+    }
+
+    fn runs_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+
+    /// First time this entry fires strictly after `after`.
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        (0..=7)
+            .map(|offset| (after.date() + Duration::days(offset)).and_time(self.time))
+            .find(|at| *at > after && self.runs_on(at.weekday()))
+    }
+
+    /// Short description of the days this entry runs on.
+    pub fn days_label(&self) -> String {
+        if self.days.is_empty() {
+            "daily".to_string()
+        } else {
+            self.days
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        }
+    }
+}
+
+/// The rules and when they were last checked. Picks up edits to
+/// `schedule.toml` without a restart.
+pub struct Schedule {
+    pub entries: Vec<ScheduleEntry>,
+    last_check: NaiveDateTime,
+    modified: Option<SystemTime>,
+    reload_checked: Instant,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            last_check: Local::now().naive_local(),
+            modified: None,
+            reload_checked: Instant::now(),
+        }
+    }
+}
+
+impl Schedule {
+    /// Loads `schedule.toml`; invalid rules are logged and skipped.
+    pub fn load() -> Result<Self> {
+        let mut schedule = Self::default();
+        schedule.reload()?;
+        Ok(schedule)
+    }
+
+    fn reload(&mut self) -> Result<()> {
+        let path = get_schedule_path()?;
+        self.modified = modified(&path);
+        let config: ScheduleConfig = if path.exists() {
+            toml::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            ScheduleConfig::default()
+        };
+
+        self.entries = config
+            .rules
+            .iter()
+            .filter_map(|rule| match ScheduleEntry::parse(rule) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    log::error!("Skipping schedule rule for '{}': {}", rule.preset, e);
+                    None
+                }
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// Returns the preset of the latest rule that came due since the previous call.
+    /// Rules that were due before the schedule was loaded never fire.
+    pub fn poll(&mut self) -> Option<String> {
+        if self.reload_checked.elapsed() >= RELOAD_INTERVAL {
+            self.reload_checked = Instant::now();
+            if let Ok(path) = get_schedule_path() {
+                if modified(&path) != self.modified {
+                    if let Err(e) = self.reload() {
+                        log::error!("Failed to reload schedule: {}", e);
+                    }
+                }
+            }
+        }
+        self.due(Local::now().naive_local())
+    }
+
+    /// The part of `poll` that doesn't touch the file or the clock.
+    fn due(&mut self, now: NaiveDateTime) -> Option<String> {
+        let since = self.last_check;
+        self.last_check = now;
+        // The clock went backwards (DST, manual change); just start over from now
+        if now <= since {
+            return None;
+        }
+
+        self.entries
+            .iter()
+            .filter_map(|entry| {
+                // Several firings may have passed if the machine was asleep; keep the last
+                let at = std::iter::successors(entry.next_after(since), |at| entry.next_after(*at))
+                    .take_while(|at| *at <= now)
+                    .last()?;
+                Some((at, entry))
+            })
+            .max_by_key(|(at, _)| *at)
+            .map(|(_, entry)| entry.preset.clone())
+    }
+
+    /// The next `limit` firings across all rules, soonest first.
+    pub fn upcoming(&self, limit: usize) -> Vec<(NaiveDateTime, &ScheduleEntry)> {
+        let now = Local::now().naive_local();
+        let mut upcoming: Vec<_> = self
+            .entries
+            .iter()
+            .flat_map(|entry| {
+                std::iter::successors(entry.next_after(now), |at| entry.next_after(*at))
+                    .take(limit)
+                    .map(move |at| (at, entry))
+            })
+            .collect();
+        upcoming.sort_by_key(|(at, _)| *at);
+        upcoming.truncate(limit);
+        upcoming
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).ok()?.modified().ok()
+}
+
+fn get_schedule_path() -> Result<PathBuf> {
+    if let Some(proj_dirs) = ProjectDirs::from("com", "tanin", "tanin") {
+        Ok(proj_dirs.config_dir().join("schedule.toml"))
+    } else {
+        Ok(PathBuf::from("schedule.toml"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        // 2024-01-01 was a Monday
+        NaiveDate::from_ymd_opt(2024, 1, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn schedule(toml: &str, last_check: NaiveDateTime) -> Schedule {
+        let config: ScheduleConfig = toml::from_str(toml).unwrap();
+        Schedule {
+            entries: config
+                .rules
+                .iter()
+                .map(|rule| ScheduleEntry::parse(rule).unwrap())
+                .collect(),
+            last_check,
+            modified: None,
+            reload_checked: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn rules_are_parsed() {
+        let config: ScheduleConfig = toml::from_str(
+            r#"
+            [[rule]]
+            at = "09:00"
+            preset = "Focus"
+            days = ["weekdays", "Sat"]
+
+            [[rule]]
+            at = " 22:30 "
+            preset = "Sleep"
+            "#,
+        )
+        .unwrap();
+        let focus = ScheduleEntry::parse(&config.rules[0]).unwrap();
+        assert_eq!(focus.time, NaiveTime::from_hms_opt(9, 0, 0).unwrap());
+        assert_eq!(focus.days.len(), 6);
+        assert!(!focus.runs_on(Weekday::Sun));
+        let sleep = ScheduleEntry::parse(&config.rules[1]).unwrap();
+        assert_eq!(sleep.time, NaiveTime::from_hms_opt(22, 30, 0).unwrap());
+        assert_eq!(sleep.days_label(), "daily");
+    }
+
+    #[test]
+    fn invalid_rules_are_refused() {
+        assert!(ScheduleEntry::new("9am", &[], "Focus").is_err());
+        assert!(ScheduleEntry::new("24:00", &[], "Focus").is_err());
+        assert!(ScheduleEntry::new("09:00", &["someday".to_string()], "Focus").is_err());
+    }
+
+    #[test]
+    fn next_firing_crosses_midnight() {
+        let entry = ScheduleEntry::new("06:00", &[], "Morning").unwrap();
+        assert_eq!(entry.next_after(at(1, 23, 0)), Some(at(2, 6, 0)));
+        // Strictly after, so a rule isn't found again at its own time
+        assert_eq!(entry.next_after(at(2, 6, 0)), Some(at(3, 6, 0)));
+
+        // Friday evening to Monday morning
+        let weekdays = ScheduleEntry::new("06:00", &["weekdays".to_string()], "Work").unwrap();
+        assert_eq!(weekdays.next_after(at(5, 7, 0)), Some(at(8, 6, 0)));
+    }
+
+    #[test]
+    fn rule_fires_once_after_midnight() {
+        let mut schedule = schedule(
+            r#"
+            [[rule]]
+            at = "00:10"
+            preset = "Night"
+            "#,
+            at(1, 23, 50),
+        );
+        assert_eq!(schedule.due(at(1, 23, 59)), None);
+        assert_eq!(schedule.due(at(2, 0, 15)).as_deref(), Some("Night"));
+        assert_eq!(schedule.due(at(2, 0, 20)), None);
+    }
+
+    #[test]
+    fn latest_due_rule_wins() {
+        let mut schedule = schedule(
+            r#"
+            [[rule]]
+            at = "23:00"
+            preset = "Evening"
+
+            [[rule]]
+            at = "01:00"
+            preset = "Night"
+
+            [[rule]]
+            at = "07:00"
+            preset = "Morning"
+            "#,
+            at(1, 22, 0),
+        );
+        // Asleep over midnight: of the rules that passed, the last one applies
+        assert_eq!(schedule.due(at(2, 2, 0)).as_deref(), Some("Night"));
+    }
+
+    #[test]
+    fn rules_due_before_loading_never_fire() {
+        let mut schedule = schedule(
+            r#"
+            [[rule]]
+            at = "09:00"
+            preset = "Focus"
+            "#,
+            at(1, 10, 0),
+        );
+        assert_eq!(schedule.due(at(1, 11, 0)), None);
+        assert_eq!(schedule.due(at(2, 9, 0)).as_deref(), Some("Focus"));
+    }
+
+    #[test]
+    fn clock_going_back_fires_nothing() {
+        let mut schedule = schedule(
+            r#"
+            [[rule]]
+            at = "02:30"
+            preset = "Night"
+            "#,
+            at(1, 3, 0),
+        );
+        assert_eq!(schedule.due(at(1, 2, 0)), None);
+        // Starts over from the earlier time, so the rule comes due again
+        assert_eq!(schedule.due(at(1, 2, 45)).as_deref(), Some("Night"));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::dsp::{Drift, Spatial, Tone};
 use anyhow::Result;
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
@@ -9,12 +10,24 @@ use std::path::PathBuf;
 pub struct Session {
     pub global_volume: f32,
     pub sounds: HashMap<String, SoundState>,
+    /// Unix timestamp (seconds) at which a running sleep timer expires.
+    #[serde(default)]
+    pub sleep_until: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SoundState {
     pub enabled: bool,
     pub volume: f32,
+    #[serde(default, skip_serializing_if = "Tone::is_flat")]
+    pub tone: Tone,
+    #[serde(default, skip_serializing_if = "Spatial::is_centered")]
+    pub spatial: Spatial,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drift: Option<Drift>,
+    /// Seconds into the file a file-backed sound was at, to resume from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<f64>,
 }
 
 impl Default for Session {
@@ -22,6 +35,7 @@ impl Default for Session {
         Self {
             global_volume: 0.5,
             sounds: HashMap::new(),
+            sleep_until: None,
         }
     }
 }
@@ -33,7 +47,13 @@ impl Session {
             let content = fs::read_to_string(&path)?;
             // If it fails to parse (e.g. empty or corrupted), return default instead of crashing
             // because session state is disposable.
-            let session: Session = toml::from_str(&content).unwrap_or_default();
+            let mut session: Session = toml::from_str(&content).unwrap_or_default();
+            for (id, state) in &mut session.sounds {
+                if !state.tone.is_finite() {
+                    log::warn!("Ignoring saved tone of '{}', it is not a number", id);
+                    state.tone = Tone::default();
+                }
+            }
             Ok(session)
         } else {
             Ok(Self::default())
@@ -0,0 +1,376 @@
+//! Presets packed up for another machine: the mix itself plus where to download
+//! the sounds it uses, as a TOML file or as a one-line `tanin:` code.
+
+use crate::presets::{self, FileVersion, Preset, PresetsConfig, PRESETS_VERSION};
+use crate::static_data::Sound;
+use anyhow::{anyhow, Context, Result};
+use base64::alphabet;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig, STANDARD};
+use base64::engine::DecodePaddingMode;
+use base64::Engine;
+use directories::UserDirs;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Starts every preset code, so pasted text is told apart from a file path.
+pub const CODE_PREFIX: &str = "tanin:";
+
+const FILE_HEADER: &str =
+    "# Tanin preset, add it with `tanin preset import <file>` or `i` on the Presets view\n\n";
+
+/// URL-safe, so codes survive chat apps and links. Codes are written without
+/// padding but read either way.
+const CODE_ENGINE: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new()
+        .with_encode_padding(false)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedPreset {
+    /// Preset format the file or code was written in, see [`PRESETS_VERSION`].
+    #[serde(default = "presets::legacy_version")]
+    pub version: u32,
+    #[serde(flatten)]
+    pub preset: Preset,
+    /// Download sources of the preset's sounds that came from a URL.
+    #[serde(default, rename = "source", skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<SoundSource>,
+}
+
+/// Enough of a sound's sounds.toml entry to download it again under the same id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoundSource {
+    pub id: String,
+    pub name: String,
+    pub category: String,
+    pub icon: String,
+    pub url: String,
+    /// File name to save it as, needed when downloading without yt-dlp.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+impl SoundSource {
+    /// Name to download under; custom sounds get their id from their name.
+    pub fn download_name(&self) -> String {
+        if self.name.to_lowercase().replace(' ', "_") == self.id {
+            self.name.clone()
+        } else {
+            self.id.replace('_', " ")
+        }
+    }
+}
+
+/// What an imported preset needs that this machine doesn't have.
+#[derive(Debug, Default)]
+pub struct MissingSounds {
+    /// Sounds that can be downloaded.
+    pub downloadable: Vec<SoundSource>,
+    /// Ids of sounds without a source, which stay silent.
+    pub unavailable: Vec<String>,
+}
+
+impl SharedPreset {
+    pub fn new(preset: &Preset, sounds: &[Sound]) -> Self {
+        let mut sources: Vec<SoundSource> = sounds
+            .iter()
+            .filter(|s| preset.sounds.contains_key(&s.id) && s.has_file())
+            .filter_map(|s| {
+                let url = s.url.as_ref().filter(|url| !url.trim().is_empty())?;
+                Some(SoundSource {
+                    id: s.id.clone(),
+                    name: s.name.clone(),
+                    category: s.category.clone(),
+                    icon: s.icon.clone(),
+                    url: url.clone(),
+                    file: Path::new(&s.file_path)
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string()),
+                })
+            })
+            .collect();
+        sources.sort_by(|a, b| a.id.cmp(&b.id));
+        // How the preset is used here means nothing to whoever imports it
+        let preset = Preset {
+            favourite: false,
+            created: None,
+            last_used: None,
+            ..preset.clone()
+        };
+        Self {
+            version: PRESETS_VERSION,
+            preset,
+            sources,
+        }
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(format!("{}{}", FILE_HEADER, toml::to_string_pretty(self)?))
+    }
+
+    pub fn to_code(&self) -> Result<String> {
+        Ok(format!(
+            "{}{}",
+            CODE_PREFIX,
+            CODE_ENGINE.encode(serde_json::to_vec(self)?)
+        ))
+    }
+
+    /// Reads a preset code, or the preset file at `input` otherwise.
+    pub fn read(input: &str) -> Result<Self> {
+        let input = input.trim();
+        if let Some(code) = input.strip_prefix(CODE_PREFIX) {
+            let json = decode_code(code).ok_or_else(|| anyhow!("Damaged preset code"))?;
+            // Checked first, a newer layout would otherwise be read half right
+            if let Ok(version) = serde_json::from_slice::<FileVersion>(&json) {
+                version.check("This preset code")?;
+            }
+            let shared: Self = serde_json::from_slice(&json).context("Damaged preset code")?;
+            return shared.checked();
+        }
+        let content = fs::read_to_string(input)
+            .with_context(|| format!("'{}' is neither a preset code nor a file", input))?;
+        if let Ok(version) = toml::from_str::<FileVersion>(&content) {
+            version.check(&format!("'{}'", input))?;
+        }
+        let shared: Self = toml::from_str(&content)
+            .with_context(|| format!("'{}' is not a Tanin preset", input))?;
+        shared.checked()
+    }
+
+    /// Refuses sources whose file name would be saved outside the sounds
+    /// directory, as presets come from other people.
+    fn checked(self) -> Result<Self> {
+        for source in &self.sources {
+            if let Some(file) = source.file.as_deref().filter(|f| !is_plain_file_name(f)) {
+                return Err(anyhow!(
+                    "Sound '{}' has an unsafe file name '{}'",
+                    source.id,
+                    file
+                ));
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn missing(&self, sounds: &[Sound]) -> MissingSounds {
+        let mut missing = MissingSounds::default();
+        let mut ids: Vec<&String> = self
+            .preset
+            .sounds
+            .keys()
+            .filter(|id| !sounds.iter().any(|s| &s.id == *id))
+            .collect();
+        ids.sort();
+        for id in ids {
+            match self.sources.iter().find(|s| &s.id == id) {
+                Some(source) => missing.downloadable.push(source.clone()),
+                None => missing.unavailable.push(id.clone()),
+            }
+        }
+        missing
+    }
+
+    /// Adds the preset, renamed if one of that name already exists. Returns its name.
+    pub fn add_to(self, presets: &mut PresetsConfig) -> String {
+        let mut preset = self.preset;
+        let taken = |name: &str| {
+            presets
+                .presets
+                .iter()
+                .any(|p| p.name.eq_ignore_ascii_case(name))
+        };
+        if taken(&preset.name) {
+            let base = preset.name.clone();
+            preset.name = (2..)
+                .map(|n| format!("{} ({})", base, n))
+                .find(|name| !taken(name))
+                .unwrap_or(base);
+        }
+        preset.created = Some(crate::presets::now());
+        let name = preset.name.clone();
+        presets.presets.push(preset);
+        name
+    }
+}
+
+/// Where the Presets view exports to: the downloads folder, else the home directory.
+pub fn export_path(name: &str) -> PathBuf {
+    let slug: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    let dir = UserDirs::new()
+        .and_then(|dirs| {
+            dirs.download_dir()
+                .map(Path::to_path_buf)
+                .or_else(|| Some(dirs.home_dir().to_path_buf()))
+        })
+        .unwrap_or_default();
+    dir.join(format!("{}.tanin.toml", slug))
+}
+
+/// Puts `text` on the terminal's clipboard with an OSC 52 escape sequence,
+/// which works over SSH too. Terminals without support ignore it.
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    let encoded = STANDARD.encode(text);
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", encoded)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Whether `name` names a file on its own, without any directory, `..` or root.
+fn is_plain_file_name(name: &str) -> bool {
+    let path = Path::new(name);
+    path.file_name()
+        .is_some_and(|file| file == path.as_os_str())
+}
+
+/// Decodes the base64 part of a preset code, ignoring line breaks picked up on the way.
+fn decode_code(code: &str) -> Option<Vec<u8>> {
+    let code: String = code.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    CODE_ENGINE.decode(code).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::PresetSound;
+    use base64::engine::general_purpose::URL_SAFE;
+    use std::collections::HashMap;
+
+    fn sound(id: &str, url: Option<&str>) -> Sound {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": id.replace('_', " "),
+            "category": "Nature",
+            "file_path": format!("/sounds/{}.ogg", id),
+            "url": url,
+        }))
+        .unwrap()
+    }
+
+    fn preset(name: &str, ids: &[&str]) -> Preset {
+        Preset {
+            name: name.to_string(),
+            sounds: ids
+                .iter()
+                .map(|id| (id.to_string(), PresetSound::default()))
+                .collect::<HashMap<_, _>>(),
+            ..Default::default()
+        }
+    }
+
+    fn shared() -> SharedPreset {
+        let sounds = [
+            sound("rain", Some("https://example.com/rain")),
+            sound("wind", None),
+            sound("birds", Some("https://example.com/birds")),
+        ];
+        SharedPreset::new(&preset("Rainy", &["rain", "wind"]), &sounds)
+    }
+
+    fn code_of(shared: &SharedPreset) -> String {
+        let json = serde_json::to_vec(shared).unwrap();
+        format!("{}{}", CODE_PREFIX, CODE_ENGINE.encode(json))
+    }
+
+    #[test]
+    fn code_round_trips() {
+        let code = shared().to_code().unwrap();
+        assert!(code.starts_with(CODE_PREFIX));
+
+        let read = SharedPreset::read(&code).unwrap();
+        assert_eq!(read.version, PRESETS_VERSION);
+        assert_eq!(read.preset.name, "Rainy");
+        let mut ids: Vec<&String> = read.preset.sounds.keys().collect();
+        ids.sort();
+        assert_eq!(ids, ["rain", "wind"]);
+        // Only sounds of the preset that have a URL are shared
+        assert_eq!(read.sources.len(), 1);
+        assert_eq!(read.sources[0].id, "rain");
+        assert_eq!(read.sources[0].file.as_deref(), Some("rain.ogg"));
+    }
+
+    #[test]
+    fn padded_and_wrapped_codes_are_read() {
+        let mut json = serde_json::to_vec(&shared()).unwrap();
+        // Trailing whitespace is still valid JSON and makes the encoding pad
+        while json.len().is_multiple_of(3) {
+            json.push(b' ');
+        }
+        let padded = format!("{}{}", CODE_PREFIX, URL_SAFE.encode(&json));
+        assert!(padded.ends_with('='));
+        assert_eq!(SharedPreset::read(&padded).unwrap().preset.name, "Rainy");
+
+        let code = shared().to_code().unwrap();
+        let base64 = code.strip_prefix(CODE_PREFIX).unwrap().as_bytes();
+        let lines: Vec<&str> = base64
+            .chunks(20)
+            .map(|line| std::str::from_utf8(line).unwrap())
+            .collect();
+        let wrapped = format!("  {}{}\n", CODE_PREFIX, lines.join("\n  "));
+        assert_eq!(SharedPreset::read(&wrapped).unwrap().preset.name, "Rainy");
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let mut newer = shared();
+        newer.version = PRESETS_VERSION + 1;
+        let err = SharedPreset::read(&code_of(&newer)).unwrap_err();
+        assert!(err.to_string().contains("version"), "{}", err);
+    }
+
+    #[test]
+    fn traversing_file_names_are_rejected() {
+        for file in [
+            "../../.bashrc",
+            "/home/me/.bashrc",
+            "sounds/rain.ogg",
+            "..",
+            "",
+        ] {
+            let mut shared = shared();
+            shared.sources[0].file = Some(file.to_string());
+            assert!(
+                SharedPreset::read(&code_of(&shared)).is_err(),
+                "'{}' was accepted",
+                file
+            );
+        }
+    }
+
+    #[test]
+    fn missing_splits_downloadable_from_unavailable() {
+        let shared = shared();
+        assert!(shared
+            .missing(&[sound("rain", None), sound("wind", None)])
+            .downloadable
+            .is_empty());
+
+        let missing = shared.missing(&[]);
+        let downloadable: Vec<&str> = missing.downloadable.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(downloadable, ["rain"]);
+        assert_eq!(missing.unavailable, ["wind"]);
+    }
+
+    #[test]
+    fn add_to_renames_taken_names() {
+        let mut presets = PresetsConfig {
+            presets: vec![preset("Rainy", &[]), preset("rainy (2)", &[])],
+            ..Default::default()
+        };
+        assert_eq!(shared().add_to(&mut presets), "Rainy (3)");
+        assert_eq!(presets.presets.len(), 3);
+        assert!(presets.presets[2].created.is_some());
+
+        let mut empty = PresetsConfig::default();
+        assert_eq!(shared().add_to(&mut empty), "Rainy");
+    }
+}
@@ -0,0 +1,63 @@
+//! Sleep timer: stops every sound after a set time, fading the master volume
+//! out over the final stretch. Run by whichever process owns the audio engine,
+//! the TUI or `tanin daemon`.
+
+use crate::config::SleepConfig;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub struct SleepTimer {
+    pub remaining: Duration,
+    pub fade_window: Duration,
+}
+
+impl SleepTimer {
+    pub fn new(remaining: Duration, config: &SleepConfig) -> Self {
+        Self {
+            remaining,
+            fade_window: Duration::from_secs(config.fade_out_secs),
+        }
+    }
+
+    /// Picks a timer back up from the deadline it was saved with, unless it ran
+    /// out in the meantime.
+    pub fn resume(deadline: u64, config: &SleepConfig) -> Option<Self> {
+        let remaining = Duration::from_secs(deadline.saturating_sub(unix_now()));
+        if remaining.is_zero() {
+            log::info!("Sleep timer expired while Tanin was closed, discarding it");
+            return None;
+        }
+        Some(Self::new(remaining, config))
+    }
+
+    /// Unix timestamp (seconds) at which the timer runs out.
+    pub fn deadline(&self) -> u64 {
+        unix_now().saturating_add(self.remaining.as_secs())
+    }
+
+    /// Multiplier applied to the master volume, ramping from 1.0 down to 0.0
+    /// over the final fade window.
+    pub fn volume_factor(&self) -> f32 {
+        if self.fade_window.is_zero() || self.remaining >= self.fade_window {
+            1.0
+        } else {
+            (self.remaining.as_secs_f32() / self.fade_window.as_secs_f32()).clamp(0.0, 1.0)
+        }
+    }
+
+    pub fn is_fading(&self) -> bool {
+        self.remaining < self.fade_window
+    }
+
+    /// Counts down by `dt`, returning whether the timer has run out.
+    pub fn tick(&mut self, dt: Duration) -> bool {
+        self.remaining = self.remaining.saturating_sub(dt);
+        self.remaining.is_zero()
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -1,8 +1,12 @@
+use crate::config::LoudnessConfig;
+use crate::dsp::loop_points::LoopPoints;
+use crate::dsp::{Drift, Events, Generator, GeneratorKind, Spatial, Tone};
 use anyhow::{Context, Result};
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 pub const REPO_URL_BASE: &str = "https://raw.githubusercontent.com/AnonMiraj/Tanin/main/";
 
@@ -17,14 +21,198 @@ pub struct Sound {
     #[serde(default = "default_icon")]
     pub icon: String,
     pub url: Option<String>,
+    /// Synthesized instead of decoded from `file_path` when set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator: Option<Generator>,
+    /// Clips played at random intervals instead of `file_path` when set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub events: Option<Events>,
+    #[serde(default, skip_serializing_if = "Tone::is_flat")]
+    pub tone: Tone,
+    #[serde(default, skip_serializing_if = "Spatial::is_centered")]
+    pub spatial: Spatial,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drift: Option<Drift>,
+    /// Files played in turn instead of looping `file_path`, which is the first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variants: Option<Variants>,
+    /// Part of the file to loop, from `loop_start`/`loop_end` in sounds.toml.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loop_region: Option<LoopRegion>,
+    /// Integrated loudness of the file in LUFS, once measured.
+    #[serde(skip)]
+    pub loudness: Option<f32>,
+    /// Whether the loudness trim applies, from `sounds.<id>.normalize`.
+    #[serde(skip, default = "default_normalize")]
+    pub normalize: bool,
     #[serde(skip)]
     pub error_state: bool,
 }
 
+impl Sound {
+    /// Whether this sound plays a single file, which can be downloaded,
+    /// measured and looped.
+    pub fn has_file(&self) -> bool {
+        self.generator.is_none() && self.events.is_none()
+    }
+
+    /// Linear gain bringing this sound to the reference level; 1.0 when
+    /// normalisation is off or the file has not been measured.
+    pub fn loudness_gain(&self, config: &LoudnessConfig) -> f32 {
+        match self.loudness {
+            Some(lufs) if config.enabled && self.normalize => {
+                10f32.powf(config.trim_db(lufs) / 20.0)
+            }
+            _ => 1.0,
+        }
+    }
+}
+
+/// Loop points within a sound file, in seconds. Playback starts at `start`
+/// and each pass after the first starts there again, skipping the intro.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LoopRegion {
+    pub start: f64,
+    /// End of each pass, the end of the file when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<f64>,
+    /// How much of each pass's tail fades into the head of the next.
+    #[serde(default)]
+    pub crossfade: f64,
+}
+
+/// How a sound with several files picks the one to play next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VariantOrder {
+    /// Every file once per round, in a new random order each round.
+    #[default]
+    Shuffle,
+    /// Any file each time, so the same one can come up twice in a row.
+    Random,
+    /// The files in the order they are listed.
+    Sequential,
+}
+
+/// Alternative recordings of one sound, from `files = [...]` in sounds.toml.
+/// A new one is picked each time the playing file ends.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Variants {
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub order: VariantOrder,
+}
+
+impl Variants {
+    /// Picks files in this sound's order. The same `seed` always gives the
+    /// same sequence.
+    pub fn picker(&self, seed: u64) -> VariantPicker {
+        VariantPicker {
+            variants: self.clone(),
+            rng: fastrand::Rng::with_seed(seed),
+            round: Vec::with_capacity(self.files.len()),
+            last: None,
+        }
+    }
+}
+
+pub struct VariantPicker {
+    variants: Variants,
+    rng: fastrand::Rng,
+    /// Files left in the current shuffle round, the next one last.
+    round: Vec<usize>,
+    last: Option<usize>,
+}
+
+impl VariantPicker {
+    pub fn next_file(&mut self) -> &str {
+        let count = self.variants.files.len();
+        let index = match self.variants.order {
+            VariantOrder::Sequential => self.last.map_or(0, |last| (last + 1) % count),
+            VariantOrder::Random => self.rng.usize(..count),
+            VariantOrder::Shuffle => {
+                if self.round.is_empty() {
+                    self.round.extend(0..count);
+                    self.rng.shuffle(&mut self.round);
+                    // Don't let a new round start with the file that ended the last one
+                    if count > 1 && self.round.last() == self.last.as_ref() {
+                        self.round.swap(0, count - 1);
+                    }
+                }
+                self.round.pop().unwrap_or(0)
+            }
+        };
+        self.last = Some(index);
+        &self.variants.files[index]
+    }
+}
+
+impl From<LoopPoints> for LoopRegion {
+    fn from(points: LoopPoints) -> Self {
+        Self {
+            start: points.start,
+            end: Some(points.end),
+            crossfade: 0.0,
+        }
+    }
+}
+
+impl LoopRegion {
+    /// Checks the points from a sounds.toml entry, returning `None` when no loop
+    /// fields are set.
+    fn from_entry(entry: &SoundEntry) -> Result<Option<Self>> {
+        if entry.loop_start.is_none() && entry.loop_end.is_none() && entry.loop_crossfade.is_none()
+        {
+            return Ok(None);
+        }
+        let start = entry.loop_start.unwrap_or(0.0);
+        let crossfade = entry.loop_crossfade.unwrap_or(0.0);
+        if !(start >= 0.0 && crossfade >= 0.0) {
+            return Err(anyhow::anyhow!(
+                "loop_start and loop_crossfade cannot be negative"
+            ));
+        }
+        if let Some(end) = entry.loop_end {
+            if end <= start {
+                return Err(anyhow::anyhow!("loop_end must come after loop_start"));
+            }
+            // A pass has to fade out before it starts fading in again
+            if crossfade > (end - start) / 2.0 {
+                return Err(anyhow::anyhow!(
+                    "loop_crossfade cannot be longer than half the loop"
+                ));
+            }
+        }
+        Ok(Some(Self {
+            start,
+            end: entry.loop_end,
+            crossfade,
+        }))
+    }
+
+    pub fn start(&self) -> Duration {
+        Duration::from_secs_f64(self.start)
+    }
+
+    /// Length of a pass, `None` to play to the end of the file.
+    pub fn length(&self) -> Option<Duration> {
+        self.end
+            .map(|end| Duration::from_secs_f64(end - self.start))
+    }
+
+    pub fn crossfade(&self) -> Duration {
+        Duration::from_secs_f64(self.crossfade)
+    }
+}
+
 fn default_volume() -> f32 {
     0.5
 }
 
+fn default_normalize() -> bool {
+    true
+}
+
 fn default_icon() -> String {
     "🎵".to_string()
 }
@@ -38,6 +226,32 @@ struct SoundEntry {
     #[serde(default = "default_icon")]
     pub icon: String,
     pub url: Option<String>,
+    pub generator: Option<GeneratorKind>,
+    /// Carrier frequency in Hz for tonal generators.
+    pub carrier: Option<f32>,
+    /// Beat or pulse frequency in Hz for tonal generators.
+    pub beat: Option<f32>,
+    /// Several recordings of the sound, played in turn instead of `file`.
+    pub files: Option<Vec<String>>,
+    /// How the next of `files` is picked.
+    pub file_order: Option<VariantOrder>,
+    /// Clips played at random intervals instead of looping a file.
+    pub events: Option<Vec<String>>,
+    /// Seconds between events.
+    pub min_interval: Option<f32>,
+    pub max_interval: Option<f32>,
+    /// Linear gain range of each event.
+    pub min_gain: Option<f32>,
+    pub max_gain: Option<f32>,
+    /// Pan range of each event, -1.0 (left) to 1.0 (right).
+    pub min_pan: Option<f32>,
+    pub max_pan: Option<f32>,
+    /// Seconds into the file where looping starts.
+    pub loop_start: Option<f64>,
+    /// Seconds into the file where looping ends.
+    pub loop_end: Option<f64>,
+    /// Seconds of each pass's tail crossfaded into the next.
+    pub loop_crossfade: Option<f64>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -96,6 +310,17 @@ pub fn get_bundled_sounds() -> Vec<Sound> {
     }
 }
 
+/// Bundled (if enabled) plus user sounds, for modes that run without the TUI.
+pub fn load_all_sounds(enable_bundled_sounds: bool) -> Vec<Sound> {
+    let mut sounds = if enable_bundled_sounds {
+        get_bundled_sounds()
+    } else {
+        Vec::new()
+    };
+    sounds.extend(load_custom_sounds());
+    sounds
+}
+
 pub fn load_custom_sounds() -> Vec<Sound> {
     let path = if let Some(proj_dirs) = ProjectDirs::from("com", "tanin", "tanin") {
         proj_dirs.config_dir().join("sounds.toml")
@@ -160,28 +385,70 @@ pub fn load_sounds_from_file<P: AsRef<Path>>(path: P) -> Result<Vec<Sound>> {
                     format!("{}.ogg", slug)
                 });
 
-                let file_path = if Path::new(&filename).is_absolute() {
-                    filename
-                } else if let Some(base) = &base_path_param {
-                    if Path::new(base).is_absolute() {
-                        Path::new(base)
-                            .join(&filename)
-                            .to_string_lossy()
-                            .to_string()
+                let loop_region = LoopRegion::from_entry(&entry).unwrap_or_else(|e| {
+                    eprintln!(
+                        "Warning: Ignoring loop points of sound '{}': {}",
+                        sound_id, e
+                    );
+                    None
+                });
+
+                let generator = entry.generator.map(|kind| Generator {
+                    kind,
+                    carrier_hz: entry.carrier.unwrap_or(Generator::DEFAULT_CARRIER_HZ),
+                    beat_hz: entry.beat.unwrap_or(Generator::DEFAULT_BEAT_HZ),
+                });
+
+                let resolve = |filename: &str| {
+                    if Path::new(filename).is_absolute() {
+                        filename.to_string()
+                    } else if let Some(base) = &base_path_param {
+                        if Path::new(base).is_absolute() {
+                            Path::new(base).join(filename).to_string_lossy().to_string()
+                        } else {
+                            // Default behavior: expect 'sounds' dir sibling to toml
+                            config_dir
+                                .join("sounds")
+                                .join(filename)
+                                .to_string_lossy()
+                                .to_string()
+                        }
                     } else {
-                        // Default behavior: expect 'sounds' dir sibling to toml
                         config_dir
                             .join("sounds")
-                            .join(&filename)
+                            .join(filename)
                             .to_string_lossy()
                             .to_string()
                     }
+                };
+
+                let events = entry.events.as_ref().map(|clips| Events {
+                    clips: clips.iter().map(|clip| resolve(clip)).collect(),
+                    min_interval: entry.min_interval.unwrap_or(Events::DEFAULT_MIN_INTERVAL),
+                    max_interval: entry.max_interval.unwrap_or(Events::DEFAULT_MAX_INTERVAL),
+                    min_gain: entry.min_gain.unwrap_or(Events::DEFAULT_MIN_GAIN),
+                    max_gain: entry.max_gain.unwrap_or(Events::DEFAULT_MAX_GAIN),
+                    min_pan: entry.min_pan.unwrap_or(Events::DEFAULT_MIN_PAN),
+                    max_pan: entry.max_pan.unwrap_or(Events::DEFAULT_MAX_PAN),
+                });
+
+                let variants =
+                    entry
+                        .files
+                        .as_ref()
+                        .filter(|files| !files.is_empty())
+                        .map(|files| Variants {
+                            files: files.iter().map(|file| resolve(file)).collect(),
+                            order: entry.file_order.unwrap_or_default(),
+                        });
+
+                let file_path = if generator.is_some() || events.is_some() {
+                    // Nothing to load or download
+                    String::new()
+                } else if let Some(variants) = &variants {
+                    variants.files[0].clone()
                 } else {
-                    config_dir
-                        .join("sounds")
-                        .join(&filename)
-                        .to_string_lossy()
-                        .to_string()
+                    resolve(&filename)
                 };
 
                 sounds.push(Sound {
@@ -192,6 +459,15 @@ pub fn load_sounds_from_file<P: AsRef<Path>>(path: P) -> Result<Vec<Sound>> {
                     volume_linear: entry.volume,
                     icon: entry.icon,
                     url: entry.url,
+                    generator,
+                    events,
+                    tone: Tone::default(),
+                    spatial: Spatial::default(),
+                    drift: None,
+                    variants,
+                    loop_region,
+                    loudness: None,
+                    normalize: true,
                     error_state: false,
                 });
             }
@@ -217,6 +493,7 @@ pub fn add_custom_sound(
     file_path: &str,
     icon: &str,
     url: Option<&str>,
+    loop_points: Option<LoopPoints>,
 ) -> Result<()> {
     let toml_path = if let Some(proj_dirs) = ProjectDirs::from("com", "tanin", "tanin") {
         let config_dir = proj_dirs.config_dir();
@@ -254,6 +531,13 @@ pub fn add_custom_sound(
             sound_entry.insert("url".to_string(), toml::Value::String(u.to_string()));
         }
 
+        if let Some(points) = loop_points {
+            // Microseconds keep the points sample accurate without noisy floats
+            let round = |secs: f64| toml::Value::Float((secs * 1e6).round() / 1e6);
+            sound_entry.insert("loop_start".to_string(), round(points.start));
+            sound_entry.insert("loop_end".to_string(), round(points.end));
+        }
+
         cat_table.insert(id, toml::Value::Table(sound_entry));
     }
 
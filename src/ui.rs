@@ -1,10 +1,14 @@
+pub mod alarms;
 pub mod assets;
+pub mod details;
+pub mod devices;
 pub mod download;
 pub mod footer;
 pub mod header;
 pub mod help;
 pub mod main_view;
 pub mod presets;
+pub mod schedule;
 
 use crate::app::{App, CurrentView};
 use ratatui::{
@@ -41,7 +45,14 @@ pub fn ui(f: &mut Frame, app: &mut App) {
     match app.view {
         CurrentView::Main => main_view::render_grid(f, app, chunks[1]),
         CurrentView::Presets => presets::render_presets(f, app, chunks[1]),
+        CurrentView::Schedule => schedule::render_schedule(f, app, chunks[1]),
+        CurrentView::Alarms => alarms::render_alarms(f, app, chunks[1]),
         CurrentView::Downloads => download::render_downloads_view(f, app, chunks[1]),
+        CurrentView::Devices => devices::render_devices(f, app, chunks[1]),
+        CurrentView::Details => {
+            main_view::render_grid(f, app, chunks[1]);
+            details::render_details(f, app, size);
+        }
         CurrentView::Help => {
             main_view::render_grid(f, app, chunks[1]);
             help::render_help(f, size);
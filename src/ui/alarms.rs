@@ -0,0 +1,113 @@
+use crate::app::App;
+use chrono::Local;
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+pub fn render_alarms(f: &mut Frame, app: &App, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(area);
+
+    let (input_style, input_text) = if app.alarm_input_mode {
+        (
+            Style::default().fg(Color::Yellow),
+            format!("Time and preset: {}_", app.alarm_input_buffer),
+        )
+    } else if let Some(error) = &app.alarm_error {
+        (Style::default().fg(Color::Red), error.clone())
+    } else {
+        (
+            Style::default().fg(Color::DarkGray),
+            "Press 'n' to add an alarm, e.g. 07:00 Morning".to_string(),
+        )
+    };
+    let p_input = Paragraph::new(input_text)
+        .block(Block::default().borders(Borders::ALL).title("New Alarm"))
+        .style(input_style);
+    f.render_widget(p_input, chunks[0]);
+
+    let alarms = &app.alarms.config.alarms;
+    if alarms.is_empty() {
+        let p_empty = Paragraph::new("No alarms set.")
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::DarkGray));
+        f.render_widget(p_empty, chunks[1]);
+        return;
+    }
+
+    let today = Local::now().date_naive();
+    let list_height = chunks[1].height.saturating_sub(2) as usize;
+    let offset = if app.alarm_cursor_pos >= list_height {
+        app.alarm_cursor_pos - list_height + 1
+    } else {
+        0
+    };
+
+    let mut lines = Vec::new();
+    for (i, alarm) in alarms.iter().enumerate().skip(offset).take(list_height) {
+        let is_selected = i == app.alarm_cursor_pos;
+        let style = if is_selected {
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD)
+        } else if alarm.enabled {
+            Style::default().fg(Color::White)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+        let exists = app
+            .presets_config
+            .presets
+            .iter()
+            .any(|p| p.name.eq_ignore_ascii_case(&alarm.preset));
+
+        let prefix = if is_selected { "> " } else { "  " };
+        let mut spans = vec![
+            Span::styled(prefix, style),
+            Span::styled(if alarm.enabled { "[x] " } else { "[ ] " }, style),
+            Span::styled(format!("{}  ", alarm.at), style),
+            Span::styled(
+                alarm.preset.clone(),
+                if exists {
+                    style
+                } else {
+                    Style::default().fg(Color::Red)
+                },
+            ),
+            Span::styled(
+                format!("  fade {} min", alarm.fade_minutes),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ];
+
+        let next = match (alarm.entry(), app.alarms.next(i)) {
+            (Err(_), _) => Some(("  invalid time or days".to_string(), Color::Red)),
+            (Ok(_), Some(at)) if at.date() == today => Some(("  today".to_string(), Color::Cyan)),
+            (Ok(_), Some(at)) => Some((format!("  next {}", at.format("%a")), Color::Cyan)),
+            (Ok(_), None) => None,
+        };
+        if let Some((label, color)) = next {
+            spans.push(Span::styled(label, Style::default().fg(color)));
+        }
+        if !exists {
+            spans.push(Span::styled(
+                "  missing preset",
+                Style::default().fg(Color::Red),
+            ));
+        }
+        lines.push(Line::from(spans));
+    }
+
+    let p_list = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Alarms (alarms.toml)"),
+    );
+    f.render_widget(p_list, chunks[1]);
+}
@@ -0,0 +1,116 @@
+use crate::app::App;
+use crate::static_data::VariantOrder;
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+fn row(label: &str, value: String, style: Style) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(
+            format!("  {:<12}", label),
+            Style::default().fg(Color::DarkGray),
+        ),
+        Span::styled(value, style),
+    ])
+}
+
+pub fn render_details(f: &mut Frame, app: &App, area: Rect) {
+    let Some(sound) = app.sounds.get(app.cursor_pos) else {
+        return;
+    };
+    let plain = Style::default().fg(Color::White);
+    let loudness = &app.config.audio.loudness;
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("{} {}", sound.icon, sound.name),
+            Style::default()
+                .fg(Color::Blue)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        row("Category", sound.category.clone(), plain),
+        row(
+            "Source",
+            if sound.generator.is_some() {
+                "synthesized".to_string()
+            } else if let Some(events) = &sound.events {
+                format!("{} clips at random", events.clips.len())
+            } else if let Some(variants) = &sound.variants {
+                format!(
+                    "{} files, {}",
+                    variants.files.len(),
+                    match variants.order {
+                        VariantOrder::Shuffle => "shuffled",
+                        VariantOrder::Random => "at random",
+                        VariantOrder::Sequential => "in order",
+                    }
+                )
+            } else {
+                sound.file_path.clone()
+            },
+            plain,
+        ),
+        row(
+            "Volume",
+            format!("{}%", (sound.volume_linear * 100.0).round() as u32),
+            plain,
+        ),
+    ];
+
+    if sound.has_file() {
+        let (measured, trim) = match sound.loudness {
+            Some(lufs) => {
+                let trim = if !loudness.enabled {
+                    "off in config.toml".to_string()
+                } else if sound.normalize {
+                    format!(
+                        "{:+.1} dB to {:.0} LUFS",
+                        loudness.trim_db(lufs),
+                        loudness.target_lufs
+                    )
+                } else {
+                    "off for this sound".to_string()
+                };
+                (format!("{:.1} LUFS", lufs), trim)
+            }
+            None => ("not measured".to_string(), "none".to_string()),
+        };
+        lines.push(row("Loudness", measured, plain));
+        lines.push(row("Trim", trim, plain));
+        lines.push(row(
+            "Normalise",
+            if sound.normalize { "on" } else { "off" }.to_string(),
+            if sound.normalize {
+                Style::default().fg(Color::Green)
+            } else {
+                Style::default().fg(Color::Yellow)
+            },
+        ));
+    }
+
+    let width = 64.min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let area = Rect::new(
+        (area.width - width) / 2,
+        (area.height - height) / 2,
+        width,
+        height,
+    );
+
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Sound Details")
+        .style(Style::default().bg(Color::Black));
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(block)
+            .alignment(Alignment::Left),
+        area,
+    );
+}
@@ -0,0 +1,69 @@
+use crate::app::App;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+pub fn render_devices(f: &mut Frame, app: &App, area: Rect) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Output Device");
+
+    // Row 0 picks the device automatically, the listed devices follow it
+    let mut rows = vec![(
+        "Automatic".to_string(),
+        "default device of the preferred host".to_string(),
+        app.config.audio.device.is_none(),
+    )];
+    for device in &app.devices {
+        let note = if device.is_default {
+            format!("{}, default", device.host)
+        } else {
+            device.host.clone()
+        };
+        rows.push((
+            device.name.clone(),
+            note,
+            device.is_chosen(&app.config.audio),
+        ));
+    }
+
+    let list_height = area.height.saturating_sub(2) as usize;
+    let offset = if app.device_cursor_pos >= list_height {
+        app.device_cursor_pos - list_height + 1
+    } else {
+        0
+    };
+
+    let mut lines = Vec::new();
+    for (i, (name, note, chosen)) in rows.into_iter().enumerate().skip(offset).take(list_height) {
+        let is_selected = i == app.device_cursor_pos;
+        let style = if is_selected {
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+
+        let prefix = if is_selected { "> " } else { "  " };
+        let mark = if chosen { "● " } else { "  " };
+        lines.push(Line::from(vec![
+            Span::styled(prefix, style),
+            Span::styled(mark, Style::default().fg(Color::Green)),
+            Span::styled(format!("{} ", name), style),
+            Span::styled(format!("({})", note), Style::default().fg(Color::DarkGray)),
+        ]));
+    }
+    if app.devices.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "  No output devices found",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
@@ -116,6 +116,9 @@ pub fn render_downloads_view(f: &mut Frame, app: &App, area: Rect) {
                     format!("Downloading {:.1}%", p),
                     Style::default().fg(Color::Yellow),
                 ),
+                crate::app::DownloadStatus::Analyzing => {
+                    Span::styled("Finding loop points...", Style::default().fg(Color::Cyan))
+                }
                 crate::app::DownloadStatus::Done => {
                     Span::styled("Done", Style::default().fg(Color::Green))
                 }
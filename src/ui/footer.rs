@@ -1,4 +1,6 @@
 use crate::app::{App, CurrentView};
+use crate::duration::format_countdown;
+use crate::pomodoro::Phase;
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
@@ -62,6 +64,52 @@ pub fn render_footer(f: &mut Frame, app: &App, area: Rect) {
         ));
     }
 
+    if let Some(timer) = &app.sleep_timer {
+        let color = if timer.is_fading() {
+            Color::Magenta
+        } else {
+            Color::DarkGray
+        };
+        left_content.push(Span::raw("  │  "));
+        left_content.push(Span::styled(
+            format!("⏾ {}", format_countdown(timer.remaining)),
+            Style::default().fg(color),
+        ));
+    }
+
+    if app.wake_fade.is_some() {
+        left_content.push(Span::raw("  │  "));
+        left_content.push(Span::styled(
+            "⏰ Waking up",
+            Style::default().fg(Color::Yellow),
+        ));
+    } else if let Some(at) = app.alarms.upcoming() {
+        left_content.push(Span::raw("  │  "));
+        left_content.push(Span::styled(
+            format!("⏰ {}", at.format("%H:%M")),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+
+    if let Some(pomodoro) = &app.pomodoro {
+        let color = if pomodoro.phase == Phase::Work {
+            Color::Red
+        } else {
+            Color::Green
+        };
+        left_content.push(Span::raw("  │  "));
+        left_content.push(Span::styled(
+            format!(
+                "🍅 {} {}/{} {}",
+                pomodoro.phase.label(),
+                pomodoro.cycle(),
+                pomodoro.cycles_per_set(),
+                format_countdown(pomodoro.remaining)
+            ),
+            Style::default().fg(color),
+        ));
+    }
+
     let master_vol = Line::from(left_content);
 
     // Dynamic help text based on view
@@ -70,10 +118,20 @@ pub fn render_footer(f: &mut Frame, app: &App, area: Rect) {
             if app.preset_input_mode {
                 "Enter: Confirm  Esc: Cancel"
             } else {
-                "n: New  r: Rename  u: Update  d: Delete  Enter: Load  Tab: Sounds  q: Quit"
+                "n: New  f: Favourite  s: Sort  t: Tag  J/K: Move  Enter: Load  ?: Help"
+            }
+        }
+        CurrentView::Schedule => "r: Reload schedule.toml  Tab: Switch View  q: Quit",
+        CurrentView::Alarms => {
+            if app.alarm_input_mode {
+                "Enter: Confirm  Esc: Cancel"
+            } else {
+                "n: New  Space: On/Off  +/-: Fade  d: Delete  Tab: Switch View  q: Quit"
             }
         }
         CurrentView::Downloads => "Enter: Queue Download  Tab: Switch View  q: Quit",
+        CurrentView::Devices => "Enter: Use Device  Esc: Back  q: Quit",
+        CurrentView::Details => "n: Toggle Loudness Normalisation  Esc: Back  q: Quit",
         _ => "Tab: Presets  SPACE: Toggle  m: Mute  ?: Help  q: Quit",
     };
 
@@ -43,15 +43,19 @@ pub fn render_header(f: &mut Frame, app: &App, area: Rect) {
             .block(Block::default().borders(Borders::NONE));
         f.render_widget(p, chunks[1]);
     } else {
-        let mut titles = vec![" Sounds ", " Presets "];
+        let mut titles = vec![" Sounds ", " Presets ", " Schedule ", " Alarms "];
         if app.yt_dlp_available {
             titles.push(" Downloads ");
         }
 
         let selected_tab = match app.view {
-            CurrentView::Main | CurrentView::Help => 0,
+            CurrentView::Main | CurrentView::Help | CurrentView::Devices | CurrentView::Details => {
+                0
+            }
             CurrentView::Presets => 1,
-            CurrentView::Downloads => 2,
+            CurrentView::Schedule => 2,
+            CurrentView::Alarms => 3,
+            CurrentView::Downloads => 4,
             CurrentView::AssetMissing | CurrentView::DownloadingAssets => 0,
         };
 
@@ -31,7 +31,20 @@ pub fn render_help(f: &mut Frame, area: Rect) {
         Line::from("  Enter / Space   Toggle sound"),
         Line::from("  < / >           Master Volume"),
         Line::from("  + / -           Volume"),
+        Line::from("  v               Drift: Off / Wander / LFO"),
+        Line::from("  b / f           Seek -/+ 30s (B / F: 5 min)"),
+        Line::from("  { / }           Low-pass (darker with })"),
+        Line::from("  ( / )           High-pass (thinner with ))"),
+        Line::from("  e / E           Cycle EQ profile / Reset tone"),
+        Line::from("  H / L           Pan Left / Right"),
+        Line::from("  w / W           Wider / Narrower Stereo, C to centre"),
         Line::from("  s               Stop all"),
+        Line::from("  r               Resume sounds stopped with s"),
+        Line::from("  [ / ]           Previous / Next Preset"),
+        Line::from("  t               Cycle Sleep Timer"),
+        Line::from("  p / P           Start / Stop Pomodoro, Skip Phase"),
+        Line::from("  o               Choose Output Device"),
+        Line::from("  i               Sound Details (n: Loudness Normalisation)"),
         Line::from(""),
         Line::from(Span::styled(
             "Presets View",
@@ -41,6 +54,14 @@ pub fn render_help(f: &mut Frame, area: Rect) {
         Line::from("  r               Rename Selected Preset"),
         Line::from("  u               Update Preset (Overwrite with current)"),
         Line::from("  d               Delete Preset"),
+        Line::from("  D / T / I       Edit Description / Tags / Icon"),
+        Line::from("  f               Toggle Favourite"),
+        Line::from("  s               Sort by Hand / Name / Last Used"),
+        Line::from("  t               Filter by Tag"),
+        Line::from("  J / K           Move Preset Down / Up"),
+        Line::from("  x               Export Preset to a File"),
+        Line::from("  c               Copy Preset Code"),
+        Line::from("  i               Import a Preset File or Code"),
         Line::from("  Enter           Load Preset"),
         Line::from(""),
         Line::from(Span::styled("General", Style::default().fg(Color::Green))),
@@ -49,8 +70,8 @@ pub fn render_help(f: &mut Frame, area: Rect) {
         Line::from("  q               Quit"),
     ];
 
-    let width = 60;
-    let height = help_text.len() as u16 + 2;
+    let width = 60.min(area.width);
+    let height = (help_text.len() as u16 + 2).min(area.height);
 
     let area = Rect::new(
         (area.width - width) / 2,
@@ -1,4 +1,6 @@
 use crate::app::App;
+use crate::dsp::{Spatial, Tone};
+use crate::duration::format_countdown;
 use crate::static_data::Sound;
 use ratatui::{
     layout::{Alignment, Rect},
@@ -7,6 +9,7 @@ use ratatui::{
     widgets::{Block, BorderType, Borders, Paragraph},
     Frame,
 };
+use std::time::Duration;
 
 pub fn render_grid(f: &mut Frame, app: &App, area: Rect) {
     let mut current_y: i32 = area.y as i32 - app.grid_scroll as i32;
@@ -93,6 +96,67 @@ pub fn render_grid(f: &mut Frame, app: &App, area: Rect) {
     }
 }
 
+fn tone_label(tone: &Tone) -> Option<String> {
+    let hz = |v: f32| {
+        if v >= 1000.0 {
+            format!("{}k", (v / 100.0).round() / 10.0)
+        } else {
+            format!("{}", v.round())
+        }
+    };
+
+    let mut parts = Vec::new();
+    if let Some(v) = tone.high_pass {
+        parts.push(format!("HP {}", hz(v)));
+    }
+    if let Some(v) = tone.low_pass {
+        parts.push(format!("LP {}", hz(v)));
+    }
+    if tone.has_eq() {
+        parts.push("EQ".to_string());
+    }
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(" "))
+    }
+}
+
+fn spatial_label(spatial: &Spatial) -> Option<String> {
+    let mut parts = Vec::new();
+    let pan = (spatial.pan * 100.0).round() as i32;
+    if pan < 0 {
+        parts.push(format!("L{}", -pan));
+    } else if pan > 0 {
+        parts.push(format!("R{}", pan));
+    }
+    if spatial.width != 1.0 {
+        parts.push(format!("↔{}%", (spatial.width * 100.0).round() as i32));
+    }
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(" "))
+    }
+}
+
+/// Short loops would only show a counter going round, so the position is
+/// shown for tracks at least this long.
+const LONG_TRACK: Duration = Duration::from_secs(5 * 60);
+
+/// Elapsed and total time of a long track, e.g. "12:34/1:02:03".
+fn position_label(position: Option<(Duration, Option<Duration>)>) -> Option<String> {
+    let (elapsed, total) = position?;
+    let total = total.filter(|total| *total >= LONG_TRACK)?;
+    Some(format!(
+        "{}/{}",
+        format_countdown(elapsed.min(total)),
+        format_countdown(total)
+    ))
+}
+
 fn render_card(f: &mut Frame, app: &App, idx: usize, sound: &Sound, area: Rect) {
     let selected = idx == app.cursor_pos;
     let playing = if let Some(engine) = &app.audio_engine {
@@ -122,6 +186,62 @@ fn render_card(f: &mut Frame, app: &App, idx: usize, sound: &Sound, area: Rect)
         .border_style(border_style)
         .border_type(border_type);
 
+    // Playback position of long tracks on the bottom border, left of the tone
+    let position = if playing {
+        app.audio_engine
+            .as_ref()
+            .and_then(|engine| position_label(engine.position(&sound.id)))
+    } else {
+        None
+    };
+    let block = match &position {
+        Some(label) => block.title_bottom(
+            Line::from(Span::styled(
+                format!(" {} ", label),
+                Style::default().fg(Color::Yellow),
+            ))
+            .alignment(Alignment::Left),
+        ),
+        None => block,
+    };
+
+    // Compact tone indicator on the bottom border, e.g. "HP 120 LP 4k"
+    let block = match (tone_label(&sound.tone), &position) {
+        (Some(label), None) => block.title_bottom(
+            Line::from(Span::styled(
+                format!(" {} ", label),
+                Style::default().fg(Color::Magenta),
+            ))
+            .alignment(Alignment::Center),
+        ),
+        // Moved aside for the position, and left out if they don't both fit
+        (Some(label), Some(position))
+            if label.chars().count() + position.chars().count() + 4
+                < area.width.saturating_sub(2) as usize =>
+        {
+            block.title_bottom(
+                Line::from(Span::styled(
+                    format!(" {} ", label),
+                    Style::default().fg(Color::Magenta),
+                ))
+                .alignment(Alignment::Right),
+            )
+        }
+        _ => block,
+    };
+
+    // Pan indicator on the top border, e.g. "L40" or "R20 ↔150%"
+    let block = match spatial_label(&sound.spatial) {
+        Some(label) => block.title_top(
+            Line::from(Span::styled(
+                format!(" {} ", label),
+                Style::default().fg(Color::Cyan),
+            ))
+            .alignment(Alignment::Right),
+        ),
+        None => block,
+    };
+
     let bg_color = if sound.error_state {
         if selected {
             Color::LightRed
@@ -166,22 +286,39 @@ fn render_card(f: &mut Frame, app: &App, idx: usize, sound: &Sound, area: Rect)
         sound.name.clone()
     };
 
-    let vol_width = (area.width as usize).saturating_sub(10);
-    let knob_pos = if vol_width > 0 {
-        (sound.volume_linear * (vol_width - 1) as f32).round() as usize
+    // While drifting the bar fills to the live level and the knob stays at the set volume
+    let drift_level = if playing {
+        app.audio_engine
+            .as_ref()
+            .and_then(|engine| engine.drift_level(&sound.id))
     } else {
-        0
+        None
     };
+    let level = sound.volume_linear * drift_level.unwrap_or(1.0);
+
+    let vol_width = (area.width as usize).saturating_sub(10);
+    let slider_pos = |vol: f32| {
+        if vol_width > 0 {
+            (vol * (vol_width - 1) as f32).round() as usize
+        } else {
+            0
+        }
+    };
+    let knob_pos = slider_pos(sound.volume_linear);
+    let fill_pos = slider_pos(level);
     let mut slider = String::new();
     for i in 0..vol_width {
         if i == knob_pos {
             slider.push('●');
-        } else if i < knob_pos {
+        } else if i < fill_pos {
             slider.push('━');
+        } else if i < knob_pos {
+            slider.push('┄');
         } else {
             slider.push('─');
         }
     }
+    let drift_marker = if sound.drift.is_some() { '~' } else { ' ' };
 
     let content = vec![
         Line::from(Span::raw(icon)),
@@ -201,7 +338,7 @@ fn render_card(f: &mut Frame, app: &App, idx: usize, sound: &Sound, area: Rect)
                     Color::Blue
                 }),
             ),
-            Span::raw(format!(" {:>3}%", (sound.volume_linear * 100.0) as u32)),
+            Span::raw(format!("{}{:>3}%", drift_marker, (level * 100.0) as u32)),
         ]),
     ];
 
@@ -1,9 +1,11 @@
 use crate::app::App;
+use crate::presets::{Preset, PresetSort};
+use chrono::{DateTime, Local};
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, Paragraph, Wrap},
     Frame,
 };
 
@@ -15,17 +17,31 @@ pub fn render_presets(f: &mut Frame, app: &App, area: Rect) {
 
     let input_style = if app.preset_input_mode {
         Style::default().fg(Color::Yellow)
+    } else if app.preset_status.is_some() {
+        Style::default().fg(Color::Cyan)
     } else {
         Style::default().fg(Color::DarkGray)
     };
 
-    let (input_title, input_text) = if app.preset_input_mode {
+    let (input_title, input_text) = if app.preset_input_mode && app.preset_import_input {
+        (
+            "Import Preset",
+            format!("File or code: {}_", app.preset_input_buffer),
+        )
+    } else if let Some(field) = app.preset_edit_field.filter(|_| app.preset_input_mode) {
+        (
+            "Edit Preset",
+            format!("{}: {}_", field.label(), app.preset_input_buffer),
+        )
+    } else if app.preset_input_mode {
         let title = if app.preset_rename_target.is_some() {
             "Rename Preset"
         } else {
             "Create Preset"
         };
         (title, format!("Name: {}_", app.preset_input_buffer))
+    } else if let Some(status) = &app.preset_status {
+        ("Manage Presets", status.clone())
     } else {
         (
             "Manage Presets",
@@ -48,15 +64,29 @@ pub fn render_presets(f: &mut Frame, app: &App, area: Rect) {
         return;
     }
 
-    let list_height = chunks[1].height.saturating_sub(2) as usize; // Subtract borders
-    let offset = if app.preset_cursor_pos >= list_height {
-        app.preset_cursor_pos - list_height + 1
+    let panes = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Length(list_width(chunks[1].width)),
+            Constraint::Min(0),
+        ])
+        .split(chunks[1]);
+
+    let visible = app.visible_presets();
+    let cursor_row = visible
+        .iter()
+        .position(|&i| i == app.preset_cursor_pos)
+        .unwrap_or(0);
+    let list_height = panes[0].height.saturating_sub(2) as usize; // Subtract borders
+    let offset = if cursor_row >= list_height {
+        cursor_row - list_height + 1
     } else {
         0
     };
 
     let mut list_items = Vec::new();
-    for (i, preset) in presets.iter().enumerate().skip(offset).take(list_height) {
+    for &i in visible.iter().skip(offset).take(list_height) {
+        let preset = &presets[i];
         let is_selected = i == app.preset_cursor_pos;
         let style = if is_selected {
             Style::default()
@@ -69,21 +99,135 @@ pub fn render_presets(f: &mut Frame, app: &App, area: Rect) {
         let prefix = if is_selected { "> " } else { "  " };
         let active_sounds_count = preset.sounds.len();
 
-        let line = Line::from(vec![
+        let mut spans = vec![
             Span::styled(prefix, style),
-            Span::styled(format!("{} ", preset.name), style),
             Span::styled(
-                format!("({} sounds)", active_sounds_count),
-                Style::default().fg(Color::DarkGray),
+                if preset.favourite { "★ " } else { "  " },
+                Style::default().fg(Color::Yellow),
             ),
-        ]);
-        list_items.push(line);
+        ];
+        if let Some(icon) = &preset.icon {
+            spans.push(Span::raw(format!("{} ", icon)));
+        }
+        spans.push(Span::styled(format!("{} ", preset.name), style));
+        spans.push(Span::styled(
+            format!("({} sounds)", active_sounds_count),
+            Style::default().fg(Color::DarkGray),
+        ));
+        for tag in &preset.tags {
+            spans.push(Span::styled(
+                format!(" #{}", tag),
+                Style::default().fg(Color::Cyan),
+            ));
+        }
+        list_items.push(Line::from(spans));
+    }
+    if visible.is_empty() {
+        list_items.push(Line::styled(
+            "  No presets with this tag.",
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+
+    let mut title = String::from("Saved Presets");
+    if app.preset_sort != PresetSort::Manual {
+        title.push_str(&format!(" · by {}", app.preset_sort.label()));
+    }
+    if let Some(tag) = &app.preset_tag_filter {
+        title.push_str(&format!(" · #{}", tag));
+    }
+    let p_list =
+        Paragraph::new(list_items).block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(p_list, panes[0]);
+
+    if panes[1].width > 0 {
+        if let Some(index) = app.selected_preset() {
+            render_details(f, app, &presets[index], panes[1]);
+        }
+    }
+}
+
+/// Width of the preset list; the rest shows the selected preset's details
+/// when the terminal is wide enough.
+pub fn list_width(width: u16) -> u16 {
+    if width >= 80 {
+        width * 55 / 100
+    } else {
+        width
     }
+}
+
+fn render_details(f: &mut Frame, app: &App, preset: &Preset, area: Rect) {
+    let label = Style::default().fg(Color::DarkGray);
+    let mut lines = Vec::new();
+
+    if let Some(description) = &preset.description {
+        lines.push(Line::from(description.clone()));
+        lines.push(Line::from(""));
+    }
+    if !preset.tags.is_empty() {
+        lines.push(Line::from(vec![
+            Span::styled("Tags       ", label),
+            Span::styled(preset.tags.join(", "), Style::default().fg(Color::Cyan)),
+        ]));
+    }
+    if let Some(volume) = preset.master_volume {
+        lines.push(Line::from(vec![
+            Span::styled("Master     ", label),
+            Span::raw(if preset.muted {
+                format!("{:.0}% (muted)", volume * 100.0)
+            } else {
+                format!("{:.0}%", volume * 100.0)
+            }),
+        ]));
+    }
+    lines.push(Line::from(vec![
+        Span::styled("Last used  ", label),
+        Span::raw(format_date(preset.last_used)),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("Created    ", label),
+        Span::raw(format_date(preset.created)),
+    ]));
+    lines.push(Line::from(""));
+
+    let mut sounds: Vec<(&String, f32)> = preset
+        .sounds
+        .iter()
+        .map(|(id, sound)| (id, sound.volume))
+        .collect();
+    sounds.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    for (id, volume) in sounds {
+        let sound = app.sounds.iter().find(|s| &s.id == id);
+        let (name, style) = match sound {
+            Some(sound) => (
+                format!("{} {}", sound.icon, sound.name),
+                Style::default().fg(Color::White),
+            ),
+            None => (
+                format!("? {} (not installed)", id),
+                Style::default().fg(Color::Red),
+            ),
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:>4.0}%  ", volume * 100.0), label),
+            Span::styled(name, style),
+        ]));
+    }
+
+    let title = match &preset.icon {
+        Some(icon) => format!("{} {}", icon, preset.name),
+        None => preset.name.clone(),
+    };
+    let p_details = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(p_details, area);
+}
 
-    let p_list = Paragraph::new(list_items).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .title("Saved Presets"),
-    );
-    f.render_widget(p_list, chunks[1]);
+fn format_date(date: Option<DateTime<Local>>) -> String {
+    date.map_or_else(
+        || "never".to_string(),
+        |date| date.format("%Y-%m-%d %H:%M").to_string(),
+    )
 }
@@ -0,0 +1,82 @@
+use crate::app::App;
+use chrono::{Local, NaiveDateTime};
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+fn when_label(at: NaiveDateTime, now: NaiveDateTime) -> String {
+    let days = (at.date() - now.date()).num_days();
+    let day = match days {
+        0 => "Today".to_string(),
+        1 => "Tomorrow".to_string(),
+        _ => at.format("%a %d %b").to_string(),
+    };
+    format!("{:<10} {}", day, at.format("%H:%M"))
+}
+
+pub fn render_schedule(f: &mut Frame, app: &App, area: Rect) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Upcoming (schedule.toml)");
+
+    if app.schedule.entries.is_empty() {
+        let p_empty = Paragraph::new(vec![
+            Line::from("No scheduled presets."),
+            Line::from(""),
+            Line::from("Add [[rule]] entries with `at`, `preset` and optional `days`"),
+            Line::from("to schedule.toml in the config directory, then press r."),
+        ])
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(Color::DarkGray))
+        .block(block);
+        f.render_widget(p_empty, area);
+        return;
+    }
+
+    let now = Local::now().naive_local();
+    let list_height = area.height.saturating_sub(2) as usize;
+
+    let mut lines = Vec::new();
+    for (i, (at, entry)) in app.schedule.upcoming(list_height).into_iter().enumerate() {
+        let exists = app
+            .presets_config
+            .presets
+            .iter()
+            .any(|p| p.name.eq_ignore_ascii_case(&entry.preset));
+
+        let style = if i == 0 {
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        let preset_style = if exists {
+            style
+        } else {
+            Style::default().fg(Color::Red)
+        };
+
+        let mut spans = vec![
+            Span::styled(format!("  {}  ", when_label(at, now)), style),
+            Span::styled(entry.preset.clone(), preset_style),
+            Span::styled(
+                format!("  ({})", entry.days_label()),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ];
+        if !exists {
+            spans.push(Span::styled(
+                "  missing preset",
+                Style::default().fg(Color::Red),
+            ));
+        }
+        lines.push(Line::from(spans));
+    }
+
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
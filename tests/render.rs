@@ -0,0 +1,96 @@
+//! Renders mixes with `tanin render` and checks the files it writes.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// A drifting sound, so the render's drift seeding is covered too.
+const SESSION: &str = r#"
+global_volume = 0.5
+
+[sounds.pink_noise]
+enabled = false
+volume = 0.6
+drift = { mode = "wander", min = 0.2, max = 1.0, period_secs = 1.0 }
+"#;
+
+/// A temporary home with a saved session, removed on drop.
+struct Home(PathBuf);
+
+impl Home {
+    fn new(name: &str) -> Self {
+        let home = std::env::temp_dir().join(format!("tanin-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&home);
+        let cache = home.join(".cache").join("tanin");
+        std::fs::create_dir_all(&cache).unwrap();
+        std::fs::write(cache.join("session.toml"), SESSION).unwrap();
+        Self(home)
+    }
+
+    /// Renders five seconds of noise and a binaural tone to `file`. Five
+    /// seconds doesn't divide into FLAC blocks, so the last block is short.
+    fn render(&self, file: &str) -> PathBuf {
+        let out = self.0.join(file);
+        let status = Command::new(env!("CARGO_BIN_EXE_tanin"))
+            .args(["render", "pink_noise", "brown_noise", "binaural_beats"])
+            .args(["--duration", "5s", "--out"])
+            .arg(&out)
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .env("HOME", &self.0)
+            .env_remove("XDG_CONFIG_HOME")
+            .env_remove("XDG_DATA_HOME")
+            .env_remove("XDG_CACHE_HOME")
+            .stderr(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success(), "rendering {} failed", file);
+        out
+    }
+}
+
+impl Drop for Home {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn read(path: &Path) -> Vec<u8> {
+    std::fs::read(path).unwrap()
+}
+
+#[test]
+fn renders_are_byte_identical() {
+    let home = Home::new("deterministic");
+    for format in ["wav", "flac", "opus"] {
+        let first = home.render(&format!("first.{}", format));
+        let second = home.render(&format!("second.{}", format));
+        assert!(read(&first) == read(&second), "{} renders differ", format);
+    }
+}
+
+#[test]
+fn flac_decodes_to_the_wav_samples() {
+    let home = Home::new("flac");
+    let wav = home.render("mix.wav");
+    let flac = home.render("mix.flac");
+
+    let mut wav = hound::WavReader::open(wav).unwrap();
+    let mut flac = claxon::FlacReader::open(flac).unwrap();
+    let info = flac.streaminfo();
+    assert_eq!(info.sample_rate, wav.spec().sample_rate);
+    assert_eq!(info.channels, wav.spec().channels as u32);
+    assert_eq!(info.bits_per_sample, wav.spec().bits_per_sample as u32);
+    assert_eq!(info.samples, Some(wav.duration() as u64));
+
+    let expected: Vec<i32> = wav
+        .samples::<i16>()
+        .map(|sample| sample.unwrap() as i32)
+        .collect();
+    let decoded: Vec<i32> = flac.samples().map(Result::unwrap).collect();
+    assert!(
+        expected.iter().any(|&sample| sample != 0),
+        "render is silent"
+    );
+    assert_eq!(decoded.len(), expected.len());
+    let mismatch = decoded.iter().zip(&expected).position(|(a, b)| a != b);
+    assert_eq!(mismatch, None, "FLAC differs from the WAV");
+}
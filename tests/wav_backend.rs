@@ -0,0 +1,145 @@
+//! Drives `tanin daemon` with the WAV backend and checks what it recorded.
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Length of the windows the recording's level is measured over.
+const WINDOW: Duration = Duration::from_millis(100);
+
+struct Daemon {
+    child: Child,
+    home: PathBuf,
+    socket: PathBuf,
+    wav: PathBuf,
+}
+
+impl Daemon {
+    fn start(name: &str) -> Self {
+        let home = std::env::temp_dir().join(format!("tanin-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&home);
+        std::fs::create_dir_all(&home).unwrap();
+        let wav = home.join("out.wav");
+
+        let child = Command::new(env!("CARGO_BIN_EXE_tanin"))
+            .args(["daemon", "--backend", "wav", "--wav-file"])
+            .arg(&wav)
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .env("HOME", &home)
+            .env("XDG_RUNTIME_DIR", &home)
+            .env_remove("XDG_CONFIG_HOME")
+            .env_remove("XDG_DATA_HOME")
+            .env_remove("XDG_CACHE_HOME")
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+
+        let socket = home.join("tanin").join("tanin.sock");
+        let started = Instant::now();
+        while !socket.exists() {
+            assert!(
+                started.elapsed() < Duration::from_secs(10),
+                "daemon never listened"
+            );
+            thread::sleep(Duration::from_millis(50));
+        }
+        Self {
+            child,
+            home,
+            socket,
+            wav,
+        }
+    }
+
+    fn send(&self, request: &str) -> String {
+        let mut stream = UnixStream::connect(&self.socket).unwrap();
+        writeln!(stream, "{}", request).unwrap();
+        let mut reply = String::new();
+        BufReader::new(stream).read_line(&mut reply).unwrap();
+        assert!(reply.starts_with("ok"), "{} failed: {}", request, reply);
+        reply
+    }
+
+    /// Quits the daemon and returns the level of each window of its recording.
+    fn finish(mut self) -> Vec<f32> {
+        self.send("quit");
+        self.child.wait().unwrap();
+        levels(&self.wav)
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = std::fs::remove_dir_all(&self.home);
+    }
+}
+
+/// RMS level of each window of a recording.
+fn levels(path: &Path) -> Vec<f32> {
+    let mut reader = hound::WavReader::open(path).unwrap();
+    let spec = reader.spec();
+    let window = (WINDOW.as_secs_f64() * spec.sample_rate as f64) as usize * spec.channels as usize;
+    let samples: Vec<f32> = reader.samples::<f32>().map(Result::unwrap).collect();
+    samples
+        .chunks_exact(window)
+        .map(|chunk| (chunk.iter().map(|s| s * s).sum::<f32>() / chunk.len() as f32).sqrt())
+        .collect()
+}
+
+#[test]
+fn playing_sound_is_recorded() {
+    let daemon = Daemon::start("play");
+    daemon.send("play white_noise 1.0");
+    thread::sleep(Duration::from_secs(3));
+    let levels = daemon.finish();
+
+    let peak = levels.iter().copied().fold(0.0, f32::max);
+    assert!(peak > 0.01, "recording is silent, peak level {}", peak);
+}
+
+#[test]
+fn stopped_sound_fades_to_silence() {
+    let daemon = Daemon::start("fade");
+    daemon.send("play white_noise 1.0");
+    thread::sleep(Duration::from_secs(3));
+    daemon.send("stop white_noise");
+    // The fade takes two seconds, and the last second may not be flushed
+    thread::sleep(Duration::from_secs(4));
+    let levels = daemon.finish();
+
+    let (loudest, peak) = levels
+        .iter()
+        .copied()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap();
+    assert!(peak > 0.01, "recording is silent, peak level {}", peak);
+
+    let after = &levels[loudest..];
+    assert_eq!(
+        *after.last().unwrap(),
+        0.0,
+        "sound still playing: {:?}",
+        after
+    );
+    // A fade, not a cut
+    let fading = after
+        .iter()
+        .filter(|&&level| level > 0.1 * peak && level < 0.9 * peak);
+    assert!(
+        fading.count() >= 5,
+        "level dropped without fading: {:?}",
+        after
+    );
+    for pair in after.windows(2) {
+        assert!(
+            pair[0] - pair[1] < 0.25 * peak,
+            "level dropped sharply: {:?}",
+            after
+        );
+    }
+}